@@ -0,0 +1,84 @@
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::{cast, cast_to_i128};
+use crate::math::quote_asset::asset_to_reserve_amount;
+use crate::math_error;
+use crate::state::AMM;
+use anchor_lang::prelude::*;
+
+// 与AMM交换的方向（从AMM的视角看输入资产是增加还是减少）
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SwapDirection {
+    Add,    // 向AMM中加入输入资产
+    Remove, // 从AMM中移除输入资产
+}
+
+// 向AMM中加入（Add）或移除（Remove）quote_asset_amount数量的quote资产，返回对应交换得到的base资产数量
+// 注：返回值为有符号数，做多（Add）时为正，做空（Remove）时为负
+pub fn swap_quote_asset(
+    amm: &mut AMM,
+    // quote资产数量（QUOTE_PRECISION）
+    quote_asset_amount: u128,
+    direction: SwapDirection,
+    now: i64,
+) -> ClearingHouseResult<i128> {
+    // 交换改变储备量前，先用当前标记价格更新标记价格TWAP
+    amm::update_mark_twap(amm, now, None)?;
+
+    // 做空（Remove）时向上取整，使从AMM中移除的quote资产储备量更多，即取整误差不利于交易者
+    let quote_asset_reserve_amount = asset_to_reserve_amount(
+        quote_asset_amount,
+        amm.peg_multiplier,
+        direction == SwapDirection::Remove,
+    )?;
+
+    // 交易量不能小于AMM允许的quote资产最小交易量
+    if quote_asset_reserve_amount < amm.mininum_quote_asset_trade_size {
+        return Err(Errors::TradeSizeTooSmall);
+    }
+
+    let initial_base_asset_reserve = amm.base_asset_reserve;
+    let (new_base_asset_reserve, new_quote_asset_reserve) = amm::calculate_swap_output(
+        quote_asset_reserve_amount,
+        amm.quote_asset_reserve,
+        direction,
+        amm.sqrt_k,
+    )?;
+
+    amm.base_asset_reserve = new_base_asset_reserve;
+    amm.quote_asset_reserve = new_quote_asset_reserve;
+
+    cast_to_i128(initial_base_asset_reserve)?
+        .checked_sub(cast(new_base_asset_reserve)?)
+        .ok_or_else(math_error!())
+}
+
+// 向AMM中加入（Add）或移除（Remove）base_asset_swap_amount数量的base资产，返回对应交换的quote资产数量（QUOTE_PRECISION）
+pub fn swap_base_asset(
+    amm: &mut AMM,
+    base_asset_swap_amount: u128,
+    direction: SwapDirection,
+    now: i64,
+) -> ClearingHouseResult<u128> {
+    // 交换改变储备量前，先用当前标记价格更新标记价格TWAP
+    amm::update_mark_twap(amm, now, None)?;
+
+    let initial_quote_asset_reserve = amm.quote_asset_reserve;
+    let (new_quote_asset_reserve, new_base_asset_reserve) = amm::calculate_swap_output(
+        base_asset_swap_amount,
+        amm.base_asset_reserve,
+        direction,
+        amm.sqrt_k,
+    )?;
+
+    amm.base_asset_reserve = new_base_asset_reserve;
+    amm.quote_asset_reserve = new_quote_asset_reserve;
+
+    amm::calculate_quote_asset_amount_swapped(
+        initial_quote_asset_reserve,
+        new_quote_asset_reserve,
+        direction,
+        amm.peg_multiplier,
+    )
+}
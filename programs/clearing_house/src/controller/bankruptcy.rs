@@ -0,0 +1,288 @@
+use crate::math::bn::ClearingHouseResult;
+use crate::math::funding::calculate_socialized_loss_funding_rate_delta;
+use crate::math::position::direction_to_close_position;
+use crate::math_error;
+use crate::state::{BankruptcyHistory, BankruptcyRecord, Market, MarketPosition, Markets};
+use anchor_lang::prelude::*;
+
+// 将完全清算后的穿仓亏损bad_debt分摊到各市场，并逐个市场弥补，增添穿仓记录，返回需要从insurance_vault转出的数量
+// 按各仓位实现的亏损比例分摊；所有仓位都没有实现亏损（穿仓由资金费造成）时，按各仓位平仓的仓位价值比例分摊
+#[allow(clippy::too_many_arguments)]
+pub fn resolve_bankruptcy(
+    user_authority: Pubkey,
+    user: Pubkey,
+    liquidator: Pubkey,
+    markets: &mut Markets,
+    positions: &[MarketPosition; 5],
+    // 各仓位平仓前的base资产数量
+    base_asset_amounts_before: &[i128; 5],
+    // 各仓位平仓的仓位价值（QUOTE_PRECISION）
+    quote_asset_amounts_closed: &[u128; 5],
+    // 各仓位实现的亏损（QUOTE_PRECISION）
+    realized_losses: &[u128; 5],
+    bad_debt: u128,
+    insurance_fund_available: u128,
+    bankruptcy_history: &mut BankruptcyHistory,
+    now: i64,
+) -> ClearingHouseResult<u128> {
+    let total_realized_loss = sum(realized_losses)?;
+    let weights = if total_realized_loss > 0 {
+        realized_losses
+    } else {
+        quote_asset_amounts_closed
+    };
+    let total_weight = sum(weights)?;
+    let last_index = weights.iter().rposition(|weight| *weight > 0);
+
+    let mut insurance_fund_remaining = insurance_fund_available;
+    let mut loss_allocated: u128 = 0;
+    for (i, weight) in weights.iter().enumerate() {
+        if *weight == 0 {
+            continue;
+        }
+
+        // 最后一个市场分摊剩余的全部亏损，避免取整造成遗漏
+        let loss = if Some(i) == last_index {
+            bad_debt
+                .checked_sub(loss_allocated)
+                .ok_or_else(math_error!())?
+        } else {
+            bad_debt
+                .checked_mul(*weight)
+                .ok_or_else(math_error!())?
+                .checked_div(total_weight)
+                .ok_or_else(math_error!())?
+        };
+        loss_allocated = loss_allocated.checked_add(loss).ok_or_else(math_error!())?;
+
+        let market_index = positions[i].market_index;
+        let (
+            insurance_fund_payment,
+            fee_pool_payment,
+            socialized_loss,
+            cumulative_funding_rate_delta,
+        ) = resolve_bankruptcy_loss(
+            markets.get_market_mut(market_index),
+            loss,
+            insurance_fund_remaining,
+            base_asset_amounts_before[i],
+        )?;
+        insurance_fund_remaining = insurance_fund_remaining
+            .checked_sub(insurance_fund_payment)
+            .ok_or_else(math_error!())?;
+
+        // 增添穿仓记录
+        let record_id = bankruptcy_history.next_record_id();
+        bankruptcy_history.append(BankruptcyRecord {
+            ts: now,
+            market_index,
+            record_id,
+            user_authority,
+            user,
+            liquidator,
+            bad_debt,
+            loss,
+            insurance_fund_payment,
+            fee_pool_payment,
+            socialized_loss,
+            cumulative_funding_rate_delta,
+            direction: direction_to_close_position(base_asset_amounts_before[i]),
+            padding: [0; 15],
+        });
+    }
+
+    // insurance_vault弥补的总量
+    insurance_fund_available
+        .checked_sub(insurance_fund_remaining)
+        .ok_or_else(math_error!())
+}
+
+// 各仓位对应数量之和
+fn sum(amounts: &[u128; 5]) -> ClearingHouseResult<u128> {
+    amounts
+        .iter()
+        .try_fold(0_u128, |acc, amount| acc.checked_add(*amount))
+        .ok_or_else(math_error!())
+}
+
+// 弥补用户穿仓后分摊到某个市场的亏损（精度为QUOTE_PRECISION）：
+// 1. 先由insurance_vault弥补（不超过insurance_fund_available）；
+// 2. 再由该市场AMM的total_fee_minus_distributions弥补；
+// 3. 剩余部分通过累计资金费率分摊给穿仓仓位的对手方，对手方在下次结算资金费时支付。
+// 返回值：(insurance_vault弥补的部分, 手续费池弥补的部分, 分摊给对手方的部分, 对手方累计资金费率的变化量)
+// 注：对手方没有仓位时无法分摊，这部分亏损不会被弥补
+pub fn resolve_bankruptcy_loss(
+    market: &mut Market,
+    loss: u128,
+    insurance_fund_available: u128,
+    // 穿仓仓位平仓前的base资产数量
+    bankrupt_base_asset_amount: i128,
+) -> ClearingHouseResult<(u128, u128, u128, i128)> {
+    let insurance_fund_payment = loss.min(insurance_fund_available);
+    let loss_remaining = loss
+        .checked_sub(insurance_fund_payment)
+        .ok_or_else(math_error!())?;
+
+    let fee_pool_payment = loss_remaining.min(market.amm.total_fee_minus_distributions);
+    market.amm.total_fee_minus_distributions = market
+        .amm
+        .total_fee_minus_distributions
+        .checked_sub(fee_pool_payment)
+        .ok_or_else(math_error!())?;
+    let loss_remaining = loss_remaining
+        .checked_sub(fee_pool_payment)
+        .ok_or_else(math_error!())?;
+
+    if loss_remaining == 0 {
+        return Ok((insurance_fund_payment, fee_pool_payment, 0, 0));
+    }
+
+    // 穿仓仓位为多头时由空头承担，为空头时由多头承担
+    let opposing_base_asset_amount = if bankrupt_base_asset_amount > 0 {
+        market.base_asset_amount_short
+    } else {
+        market.base_asset_amount_long
+    };
+    if opposing_base_asset_amount == 0 {
+        return Ok((insurance_fund_payment, fee_pool_payment, 0, 0));
+    }
+
+    let cumulative_funding_rate_delta =
+        calculate_socialized_loss_funding_rate_delta(loss_remaining, opposing_base_asset_amount)?;
+    if opposing_base_asset_amount > 0 {
+        market.amm.cumulative_funding_rate_long = market
+            .amm
+            .cumulative_funding_rate_long
+            .checked_add(cumulative_funding_rate_delta)
+            .ok_or_else(math_error!())?;
+    } else {
+        market.amm.cumulative_funding_rate_short = market
+            .amm
+            .cumulative_funding_rate_short
+            .checked_add(cumulative_funding_rate_delta)
+            .ok_or_else(math_error!())?;
+    }
+
+    Ok((
+        insurance_fund_payment,
+        fee_pool_payment,
+        loss_remaining,
+        cumulative_funding_rate_delta,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{
+        AMM_RESERVE_PRECISION, AMM_TO_QUOTE_PRECISION_RATIO_I128, QUOTE_PRECISION,
+    };
+    use crate::math::funding::calculate_pending_funding_payment;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_resolve_bankruptcy_loss() {
+        let mut market = Market::zeroed();
+        market.amm.total_fee_minus_distributions = 3 * QUOTE_PRECISION;
+        market.base_asset_amount_long = 4 * AMM_RESERVE_PRECISION as i128;
+        market.base_asset_amount_short = -2 * AMM_RESERVE_PRECISION as i128;
+
+        // insurance_vault足够：全部由insurance_vault弥补
+        assert_eq!(
+            resolve_bankruptcy_loss(&mut market, QUOTE_PRECISION, 5 * QUOTE_PRECISION, 1).unwrap(),
+            (QUOTE_PRECISION, 0, 0, 0)
+        );
+
+        // insurance_vault不足：剩余部分由手续费池弥补
+        assert_eq!(
+            resolve_bankruptcy_loss(&mut market, 3 * QUOTE_PRECISION, QUOTE_PRECISION, 1).unwrap(),
+            (QUOTE_PRECISION, 2 * QUOTE_PRECISION, 0, 0)
+        );
+        assert_eq!(market.amm.total_fee_minus_distributions, QUOTE_PRECISION);
+
+        // 手续费池也不足：剩余部分分摊给对手方（穿仓仓位为多头，由空头承担）
+        let (insurance_fund_payment, fee_pool_payment, socialized_loss, funding_rate_delta) =
+            resolve_bankruptcy_loss(&mut market, 3 * QUOTE_PRECISION, 0, 1).unwrap();
+        assert_eq!(insurance_fund_payment, 0);
+        assert_eq!(fee_pool_payment, QUOTE_PRECISION);
+        assert_eq!(socialized_loss, 2 * QUOTE_PRECISION);
+        assert_eq!(market.amm.total_fee_minus_distributions, 0);
+        assert_eq!(market.amm.cumulative_funding_rate_short, funding_rate_delta);
+        assert_eq!(market.amm.cumulative_funding_rate_long, 0);
+
+        // 空头在下次结算资金费时支付分摊的亏损
+        let mut market_position = MarketPosition::zeroed();
+        market_position.base_asset_amount = market.base_asset_amount_short;
+        assert_eq!(
+            calculate_pending_funding_payment(&market_position, &market.amm).unwrap()
+                / AMM_TO_QUOTE_PRECISION_RATIO_I128,
+            -2 * QUOTE_PRECISION as i128
+        );
+
+        // 对手方没有仓位时无法分摊
+        market.base_asset_amount_long = 0;
+        assert_eq!(
+            resolve_bankruptcy_loss(&mut market, QUOTE_PRECISION, 0, -1).unwrap(),
+            (0, 0, 0, 0)
+        );
+    }
+
+    #[test]
+    fn test_resolve_bankruptcy_funding_only() {
+        // 用户在市场0、1的多头平仓均实现盈利，仅因资金费穿仓
+        let mut markets = Box::new(Markets::zeroed());
+        let market = markets.get_market_mut(0);
+        market.amm.total_fee_minus_distributions = QUOTE_PRECISION;
+        market.base_asset_amount_short = -2 * AMM_RESERVE_PRECISION as i128;
+        markets.get_market_mut(1).base_asset_amount_short = -(AMM_RESERVE_PRECISION as i128);
+
+        let mut positions = [MarketPosition::zeroed(); 5];
+        positions[1].market_index = 1;
+        let base_asset_amounts_before = [
+            AMM_RESERVE_PRECISION as i128,
+            AMM_RESERVE_PRECISION as i128,
+            0,
+            0,
+            0,
+        ];
+        let quote_asset_amounts_closed = [30 * QUOTE_PRECISION, 10 * QUOTE_PRECISION, 0, 0, 0];
+        let realized_losses = [0; 5];
+        let mut bankruptcy_history = Box::new(BankruptcyHistory::zeroed());
+
+        // 4个quote资产的穿仓亏损按平仓的仓位价值3:1分摊，insurance_vault只有2个quote资产
+        let insurance_fund_payment = resolve_bankruptcy(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            &mut markets,
+            &positions,
+            &base_asset_amounts_before,
+            &quote_asset_amounts_closed,
+            &realized_losses,
+            4 * QUOTE_PRECISION,
+            2 * QUOTE_PRECISION,
+            &mut bankruptcy_history,
+            0,
+        )
+        .unwrap();
+        assert_eq!(insurance_fund_payment, 2 * QUOTE_PRECISION);
+
+        // 市场0分摊3个quote资产：insurance_vault弥补2个，手续费池弥补1个
+        let market = markets.get_market(0);
+        assert_eq!(market.amm.total_fee_minus_distributions, 0);
+        assert_eq!(market.amm.cumulative_funding_rate_short, 0);
+
+        // 市场1分摊1个quote资产：insurance_vault和手续费池均已用完，分摊给空头
+        let market = markets.get_market(1);
+        let mut market_position = MarketPosition::zeroed();
+        market_position.base_asset_amount = market.base_asset_amount_short;
+        assert_eq!(
+            calculate_pending_funding_payment(&market_position, &market.amm).unwrap()
+                / AMM_TO_QUOTE_PRECISION_RATIO_I128,
+            -(QUOTE_PRECISION as i128)
+        );
+
+        // 每个市场增添一条穿仓记录
+        assert_eq!(bankruptcy_history.next_record_id(), 3);
+    }
+}
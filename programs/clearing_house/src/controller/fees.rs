@@ -0,0 +1,261 @@
+use anchor_lang::prelude::*;
+use std::cmp::min;
+
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::cast;
+use crate::math::fees::{calculate_fee_for_trade, calculate_filler_reward};
+use crate::math_error;
+use crate::state::{FeeStructure, Market, OrderDiscountTier, OrderFillerRewardStructure, User};
+
+// 收取交易手续费：从用户抵押品中扣除手续费，推荐人奖励计入推荐人的抵押品，其余计入市场的手续费池
+// 抵押品不足以支付手续费时只收取剩余的抵押品，计入市场的手续费和推荐人奖励均不超过实际收取的手续费
+// 成交订单时从计入市场的手续费中支付填充者奖励，由调用方计入填充者的抵押品
+// 注：推荐人账户通过remaining_accounts传入时，需由调用方写回
+// 返回值：(实际收取的手续费, 计入市场的手续费, 持币折扣, 推荐人奖励, 被推荐人折扣, 填充者奖励)，均为QUOTE_PRECISION
+pub fn charge_trade_fee(
+    user: &mut User,
+    referrer: Option<&mut User>,
+    market: &mut Market,
+    fee_structure: &FeeStructure,
+    quote_asset_amount: u128,
+    // 用户持有discount_mint代币所对应的折扣等级
+    discount_tier: OrderDiscountTier,
+    // 成交订单时的填充者奖励结构，开仓/平仓时为None
+    filler_reward_structure: Option<&OrderFillerRewardStructure>,
+) -> ClearingHouseResult<(u128, u128, u128, u128, u128, u128)> {
+    let (user_fee, fee_to_market, token_discount, referrer_reward, referee_discount) =
+        calculate_fee_for_trade(
+            quote_asset_amount,
+            fee_structure,
+            discount_tier,
+            referrer.is_some(),
+        )?;
+
+    // 优先计入市场的手续费池，剩余部分作为推荐人奖励
+    let user_fee = min(user_fee, user.collateral);
+    let fee_to_market = min(fee_to_market, user_fee);
+    let referrer_reward = min(
+        referrer_reward,
+        user_fee
+            .checked_sub(fee_to_market)
+            .ok_or_else(math_error!())?,
+    );
+
+    let filler_reward = match filler_reward_structure {
+        Some(filler_reward_structure) => {
+            calculate_filler_reward(fee_to_market, filler_reward_structure)?
+        }
+        None => 0,
+    };
+    let fee_to_market = fee_to_market
+        .checked_sub(filler_reward)
+        .ok_or_else(math_error!())?;
+
+    user.collateral = user
+        .collateral
+        .checked_sub(user_fee)
+        .ok_or_else(math_error!())?;
+    user.total_fee_paid = user
+        .total_fee_paid
+        .checked_add(cast(user_fee)?)
+        .ok_or_else(math_error!())?;
+    user.total_token_discount = user
+        .total_token_discount
+        .checked_add(token_discount)
+        .ok_or_else(math_error!())?;
+    user.total_referee_discount = user
+        .total_referee_discount
+        .checked_add(referee_discount)
+        .ok_or_else(math_error!())?;
+
+    if let Some(referrer) = referrer {
+        referrer.collateral = referrer
+            .collateral
+            .checked_add(referrer_reward)
+            .ok_or_else(math_error!())?;
+        referrer.total_referral_reward = referrer
+            .total_referral_reward
+            .checked_add(referrer_reward)
+            .ok_or_else(math_error!())?;
+    }
+
+    market.amm.total_fee = market
+        .amm
+        .total_fee
+        .checked_add(fee_to_market)
+        .ok_or_else(math_error!())?;
+    market.amm.total_fee_minus_distributions = market
+        .amm
+        .total_fee_minus_distributions
+        .checked_add(fee_to_market)
+        .ok_or_else(math_error!())?;
+
+    Ok((
+        user_fee,
+        fee_to_market,
+        token_discount,
+        referrer_reward,
+        referee_discount,
+        filler_reward,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{
+        DEFAULT_FEE_DENOMINATOR, DEFAULT_FEE_NUMERATOR, DEFAULT_REFEREE_DISCOUNT_DENOMINATOR,
+        DEFAULT_REFEREE_DISCOUNT_NUMERATOR, DEFAULT_REFERRER_REWARD_DENOMINATOR,
+        DEFAULT_REFERRER_REWARD_NUMERATOR, QUOTE_PRECISION,
+    };
+    use crate::state::ReferralDiscount;
+    use bytemuck::Zeroable;
+
+    fn new_user(collateral: u128) -> User {
+        User {
+            authority: Pubkey::new_unique(),
+            collateral,
+            cumculative_deposits: 0,
+            total_fee_paid: 0,
+            total_fee_rebate: 0,
+            total_token_discount: 0,
+            total_referral_reward: 0,
+            total_referee_discount: 0,
+            positons: Pubkey::new_unique(),
+            settled_position_value: 0,
+            collateral_claimed: 0,
+            last_collateral_available_to_claim: 0,
+            forgo_position_settlement: 0,
+            has_settled_position: 0,
+            padding: [0; 14],
+            referrer: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn test_charge_trade_fee() {
+        let fee_structure = FeeStructure {
+            fee_numerator: DEFAULT_FEE_NUMERATOR,
+            fee_denominator: DEFAULT_FEE_DENOMINATOR,
+            referral_discount: ReferralDiscount {
+                referral_reward_numerator: DEFAULT_REFERRER_REWARD_NUMERATOR,
+                referral_reward_denominator: DEFAULT_REFERRER_REWARD_DENOMINATOR,
+                referee_discount_numerator: DEFAULT_REFEREE_DISCOUNT_NUMERATOR,
+                referee_discount_denominator: DEFAULT_REFEREE_DISCOUNT_DENOMINATOR,
+            },
+            ..FeeStructure::zeroed()
+        };
+        let mut market = Market::zeroed();
+        // 10000 USDC的交易：基础手续费10，被推荐人折扣0.5，推荐人奖励0.5
+        let quote_asset_amount = 10_000 * QUOTE_PRECISION;
+
+        let mut user = new_user(100 * QUOTE_PRECISION);
+        let mut referrer = new_user(0);
+        let (user_fee, fee_to_market, _, referrer_reward, referee_discount, _) = charge_trade_fee(
+            &mut user,
+            Some(&mut referrer),
+            &mut market,
+            &fee_structure,
+            quote_asset_amount,
+            OrderDiscountTier::None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(user_fee, 9_500_000);
+        assert_eq!(fee_to_market, 9_000_000);
+        assert_eq!(referrer_reward, 500_000);
+        assert_eq!(referee_discount, 500_000);
+        assert_eq!(user.collateral, 100 * QUOTE_PRECISION - user_fee);
+        assert_eq!(user.total_fee_paid, user_fee as u64);
+        assert_eq!(referrer.collateral, referrer_reward);
+        assert_eq!(referrer.total_referral_reward, referrer_reward);
+        assert_eq!(market.amm.total_fee, fee_to_market);
+        assert_eq!(market.amm.total_fee_minus_distributions, fee_to_market);
+
+        // 抵押品不足以支付手续费：只收取剩余的抵押品，全部计入市场，推荐人不获得奖励
+        let mut user = new_user(2 * QUOTE_PRECISION);
+        let mut referrer = new_user(0);
+        let mut market = Market::zeroed();
+        let (user_fee, fee_to_market, _, referrer_reward, _, _) = charge_trade_fee(
+            &mut user,
+            Some(&mut referrer),
+            &mut market,
+            &fee_structure,
+            quote_asset_amount,
+            OrderDiscountTier::None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(user_fee, 2 * QUOTE_PRECISION);
+        assert_eq!(fee_to_market, 2 * QUOTE_PRECISION);
+        assert_eq!(referrer_reward, 0);
+        assert_eq!(user.collateral, 0);
+        assert_eq!(referrer.collateral, 0);
+        assert_eq!(market.amm.total_fee, 2 * QUOTE_PRECISION);
+
+        // 抵押品介于计入市场的手续费与手续费之间：推荐人只获得剩余部分
+        let mut user = new_user(9_200_000);
+        let mut referrer = new_user(0);
+        let mut market = Market::zeroed();
+        let (user_fee, fee_to_market, _, referrer_reward, _, _) = charge_trade_fee(
+            &mut user,
+            Some(&mut referrer),
+            &mut market,
+            &fee_structure,
+            quote_asset_amount,
+            OrderDiscountTier::None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(user_fee, 9_200_000);
+        assert_eq!(fee_to_market, 9_000_000);
+        assert_eq!(referrer_reward, 200_000);
+        assert_eq!(referrer.collateral, 200_000);
+
+        // 成交订单：填充者奖励从计入市场的手续费中支付
+        let mut user = new_user(100 * QUOTE_PRECISION);
+        let mut market = Market::zeroed();
+        let filler_reward_structure = OrderFillerRewardStructure {
+            reward_numerator: 1,
+            reward_denominator: 10,
+            time_based_reward_lower_bound: 0,
+        };
+        let (user_fee, fee_to_market, _, _, _, filler_reward) = charge_trade_fee(
+            &mut user,
+            None,
+            &mut market,
+            &fee_structure,
+            quote_asset_amount,
+            OrderDiscountTier::None,
+            Some(&filler_reward_structure),
+        )
+        .unwrap();
+        assert_eq!(user_fee, 10 * QUOTE_PRECISION);
+        assert_eq!(filler_reward, QUOTE_PRECISION);
+        assert_eq!(fee_to_market, 9 * QUOTE_PRECISION);
+        assert_eq!(market.amm.total_fee, 9 * QUOTE_PRECISION);
+
+        // 抵押品不足以支付手续费时，填充者奖励不超过实际收取的手续费
+        let mut user = new_user(1);
+        let mut market = Market::zeroed();
+        let filler_reward_structure = OrderFillerRewardStructure {
+            reward_numerator: 1,
+            reward_denominator: 10,
+            time_based_reward_lower_bound: QUOTE_PRECISION,
+        };
+        let (user_fee, fee_to_market, _, _, _, filler_reward) = charge_trade_fee(
+            &mut user,
+            None,
+            &mut market,
+            &fee_structure,
+            quote_asset_amount,
+            OrderDiscountTier::None,
+            Some(&filler_reward_structure),
+        )
+        .unwrap();
+        assert_eq!(user_fee, 1);
+        assert_eq!(filler_reward, 1);
+        assert_eq!(fee_to_market, 0);
+        assert_eq!(market.amm.total_fee, 0);
+    }
+}
@@ -0,0 +1,241 @@
+use anchor_lang::prelude::*;
+
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::collateral::calculate_updated_collateral;
+use crate::math::constant::AMM_TO_QUOTE_PRECISION_RATIO_I128;
+use crate::math::funding::{calculate_funding_payment, calculate_funding_rate};
+use crate::math_error;
+use crate::state::*;
+
+// 结算用户所有持仓自上次结算以来的资金费，并计入用户抵押品
+// 每个发生资金费变化的仓位都会在FundingPaymentHistory中增添一条FundingPaymentRecord
+// 返回值：结算的资金费之和（QUOTE_PRECISION），未按抵押品截断
+pub fn settle_funding_payment(
+    user: &mut User,
+    user_positions: &mut UserPositions,
+    markets: &Markets,
+    funding_payment_history: &mut FundingPaymentHistory,
+    now: i64,
+) -> ClearingHouseResult<i128> {
+    let user_key = user_positions.user;
+    // 所有仓位资金费之和（精度为AMM_RESERVE_PRECISION）
+    let mut funding_payment: i128 = 0;
+    for market_position in user_positions.positions.iter_mut() {
+        // 无持仓的仓位无需结算资金费
+        if market_position.base_asset_amount == 0 {
+            continue;
+        }
+
+        let amm = &markets.get_market(market_position.market_index).amm;
+        // 多头仓位使用多头累计资金费率，空头仓位使用空头累计资金费率
+        let amm_cumulative_funding_rate = if market_position.base_asset_amount > 0 {
+            amm.cumulative_funding_rate_long
+        } else {
+            amm.cumulative_funding_rate_short
+        };
+
+        if amm_cumulative_funding_rate != market_position.last_cumulative_funding_rate {
+            let market_funding_payment =
+                calculate_funding_payment(amm_cumulative_funding_rate, market_position)?;
+
+            let record_id = funding_payment_history.next_record_id();
+            funding_payment_history.append(FundingPaymentRecord {
+                ts: now,
+                market_index: market_position.market_index,
+                record_id,
+                user_authority: user.authority,
+                user: user_key,
+                funding_payment: market_funding_payment,
+                base_asset_amount: market_position.base_asset_amount,
+                amm_cumulative_funding_long: amm.cumulative_funding_rate_long,
+                amm_cumulative_funding_short: amm.cumulative_funding_rate_short,
+                user_last_cumulative_funding: market_position.last_cumulative_funding_rate,
+                user_last_funding_rate_ts: market_position.last_funding_rate_ts,
+                padding: [0; 8],
+            });
+
+            funding_payment = funding_payment
+                .checked_add(market_funding_payment)
+                .ok_or_else(math_error!())?;
+
+            market_position.last_cumulative_funding_rate = amm_cumulative_funding_rate;
+            market_position.last_funding_rate_ts = amm.last_funding_rate_ts;
+        }
+    }
+
+    // 将资金费从AMM_RESERVE_PRECISION转换为QUOTE_PRECISION后计入抵押品
+    let funding_payment_collateral = funding_payment
+        .checked_div(AMM_TO_QUOTE_PRECISION_RATIO_I128)
+        .ok_or_else(math_error!())?;
+
+    user.collateral = calculate_updated_collateral(user.collateral, funding_payment_collateral)?;
+
+    Ok(funding_payment_collateral)
+}
+
+// 更新市场的资金费率：距上次更新超过一个资金费率周期后，先用当前标记价格和预言机价格（oracle_price）更新两者的TWAP，
+// 再根据标记价格TWAP与预言机价格TWAP的溢价计算本周期的资金费率，
+// 并累加到多头和空头的累计资金费率中，同时在FundingRateHistory中增添一条FundingRateRecord
+pub fn update_funding_rate(
+    market_index: u64,
+    market: &mut Market,
+    oracle_price: i128,
+    now: i64,
+    funding_rate_history: &mut FundingRateHistory,
+    funding_paused: bool,
+) -> ClearingHouseResult {
+    if funding_paused {
+        return Err(Errors::FundingPaused);
+    }
+
+    // 每个资金费率周期只能更新一次
+    let time_since_last_update = now
+        .checked_sub(market.amm.last_funding_rate_ts)
+        .ok_or_else(math_error!())?;
+    if time_since_last_update < market.amm.funding_period {
+        return Err(Errors::FundingPeriodNotElapsed);
+    }
+
+    let mark_price_twap = amm::update_mark_twap(&mut market.amm, now, None)?;
+    let oracle_price_twap = amm::update_oracle_twap(&mut market.amm, now, oracle_price)?;
+    let funding_rate = calculate_funding_rate(
+        mark_price_twap,
+        oracle_price_twap,
+        market.amm.funding_period,
+    )?;
+
+    market.amm.cumulative_funding_rate_long = market
+        .amm
+        .cumulative_funding_rate_long
+        .checked_add(funding_rate)
+        .ok_or_else(math_error!())?;
+    market.amm.cumulative_funding_rate_short = market
+        .amm
+        .cumulative_funding_rate_short
+        .checked_add(funding_rate)
+        .ok_or_else(math_error!())?;
+    market.amm.last_funding_rate = funding_rate;
+    market.amm.last_funding_rate_ts = now;
+
+    let record_id = funding_rate_history.next_record_id();
+    funding_rate_history.append(FundingRateRecord {
+        ts: now,
+        market_index,
+        record_id,
+        funding_rate,
+        cumulative_funding_rate_long: market.amm.cumulative_funding_rate_long,
+        cumulative_funding_rate_short: market.amm.cumulative_funding_rate_short,
+        oracle_price_twap,
+        mark_price_twap,
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::collateral::calculate_equity;
+    use crate::math::constant::{
+        AMM_RESERVE_PRECISION, FUNDING_PAYMENT_PRECISION, MARK_PRICE_PRECISION, QUOTE_PRECISION,
+    };
+    use bytemuck::Zeroable;
+
+    // 资金费率为1（即溢价为1个quote资产）
+    const FUNDING_RATE: i128 = (MARK_PRICE_PRECISION * FUNDING_PAYMENT_PRECISION) as i128;
+
+    #[test]
+    fn test_settle_funding_payment() {
+        let mut markets = Box::new(Markets::zeroed());
+        markets.get_market_mut(0).amm.cumulative_funding_rate_long = FUNDING_RATE;
+        markets.get_market_mut(0).amm.cumulative_funding_rate_short = FUNDING_RATE;
+        markets.get_market_mut(0).amm.last_funding_rate_ts = 100;
+        markets.get_market_mut(1).amm.cumulative_funding_rate_short = -FUNDING_RATE;
+
+        let mut user = User {
+            authority: Pubkey::new_unique(),
+            collateral: 100 * QUOTE_PRECISION,
+            cumculative_deposits: 0,
+            total_fee_paid: 0,
+            total_fee_rebate: 0,
+            total_token_discount: 0,
+            total_referral_reward: 0,
+            total_referee_discount: 0,
+            positons: Pubkey::new_unique(),
+            settled_position_value: 0,
+            collateral_claimed: 0,
+            last_collateral_available_to_claim: 0,
+            forgo_position_settlement: 0,
+            has_settled_position: 0,
+            padding: [0; 14],
+            referrer: Pubkey::default(),
+        };
+        let mut user_positions = UserPositions::zeroed();
+        user_positions.user = Pubkey::new_unique();
+        // 市场0的2个单位多头：资金费率为正，支付2个quote资产
+        user_positions.positions[0].market_index = 0;
+        user_positions.positions[0].base_asset_amount = 2 * AMM_RESERVE_PRECISION as i128;
+        // 市场1的1个单位空头：空头资金费率为负，支付1个quote资产
+        user_positions.positions[1].market_index = 1;
+        user_positions.positions[1].base_asset_amount = -(AMM_RESERVE_PRECISION as i128);
+        // 市场0无持仓的仓位不结算
+        user_positions.positions[2].market_index = 0;
+
+        let mut funding_payment_history = Box::new(FundingPaymentHistory::zeroed());
+        let funding_payment = settle_funding_payment(
+            &mut user,
+            &mut user_positions,
+            &markets,
+            &mut funding_payment_history,
+            200,
+        )
+        .unwrap();
+
+        assert_eq!(funding_payment, -3 * QUOTE_PRECISION as i128);
+        assert_eq!(user.collateral, 97 * QUOTE_PRECISION);
+        assert_eq!(
+            user_positions.positions[0].last_cumulative_funding_rate,
+            FUNDING_RATE
+        );
+        assert_eq!(user_positions.positions[0].last_funding_rate_ts, 100);
+        assert_eq!(
+            user_positions.positions[1].last_cumulative_funding_rate,
+            -FUNDING_RATE
+        );
+        assert_eq!(user_positions.positions[2].last_cumulative_funding_rate, 0);
+        // 增添了2条FundingPaymentRecord
+        assert_eq!(funding_payment_history.next_record_id(), 3);
+
+        // 累计资金费率未变化时，再次结算不会产生新的资金费
+        settle_funding_payment(
+            &mut user,
+            &mut user_positions,
+            &markets,
+            &mut funding_payment_history,
+            300,
+        )
+        .unwrap();
+        assert_eq!(user.collateral, 97 * QUOTE_PRECISION);
+        assert_eq!(funding_payment_history.next_record_id(), 3);
+
+        // 资金费亏损超过抵押品：抵押品截断为0，但返回完整的资金费，用于计算穿仓亏损
+        user.collateral = QUOTE_PRECISION;
+        markets.get_market_mut(0).amm.cumulative_funding_rate_long = 3 * FUNDING_RATE;
+        let funding_payment = settle_funding_payment(
+            &mut user,
+            &mut user_positions,
+            &markets,
+            &mut funding_payment_history,
+            400,
+        )
+        .unwrap();
+        assert_eq!(funding_payment, -4 * QUOTE_PRECISION as i128);
+        assert_eq!(user.collateral, 0);
+        assert_eq!(
+            calculate_equity(QUOTE_PRECISION, funding_payment).unwrap(),
+            -3 * QUOTE_PRECISION as i128
+        );
+    }
+}
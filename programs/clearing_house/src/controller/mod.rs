@@ -1 +1,7 @@
+pub mod amm;
+pub mod bankruptcy;
+pub mod fees;
+pub mod funding;
+pub mod orders;
 pub mod position;
+pub mod token;
@@ -0,0 +1,775 @@
+use anchor_lang::prelude::*;
+use bytemuck::Zeroable;
+use std::cmp::min;
+
+use crate::controller;
+use crate::controller::position::{add_new_position, get_position_index, PositionDirection};
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::{cast, cast_to_i128};
+use crate::math::fees::calculate_fee_tier;
+use crate::math::margin::{calculate_free_collateral, meets_initial_margin};
+use crate::math::oracle;
+use crate::math::orders::{
+    calculate_base_asset_amount_to_fill, calculate_limit_price,
+    calculate_max_reduce_only_base_asset_amount, is_order_triggered,
+};
+use crate::math::position::{calculate_base_asset_value_and_pnl, calculate_entry_price};
+use crate::math_error;
+use crate::order_validation::validate_order;
+use crate::state::{
+    FundingPaymentHistory, Markets, Order, OrderAction, OrderHistory, OrderParams, OrderRecord,
+    OrderState, OrderStatus, OrderType, State, TradeHistory, TradeRecord, User, UserOrders,
+    UserPositions,
+};
+
+// 下单：占用一个订单槽，分配全局唯一的订单ID，并增添OrderAction::Place记录
+// 注：下单时不成交，也不收取手续费
+#[allow(clippy::too_many_arguments)]
+pub fn place_order(
+    state: &State,
+    order_state: &OrderState,
+    user: &Account<User>,
+    user_positions: &mut UserPositions,
+    markets: &Markets,
+    user_orders: &mut UserOrders,
+    order_history: &mut OrderHistory,
+    // 用户持有的discount_mint代币数量
+    discount_token_balance: u64,
+    params: &OrderParams,
+    oracle: &AccountInfo,
+    clock: &Clock,
+) -> ClearingHouseResult {
+    let now = clock.unix_timestamp;
+
+    // 要求market_index对应的市场已经初始化
+    if Markets::index_from_u64(params.market_index) >= markets.markets.len()
+        || !markets.get_market(params.market_index).is_initialized()
+    {
+        return Err(Errors::MarketIndexNotInitialized);
+    }
+    let market = markets.get_market(params.market_index);
+
+    // 要求传入的预言机账户为该市场的预言机，且预言机价格需通过有效性检验
+    if !market.amm.oracle.eq(oracle.key) {
+        return Err(Errors::InvalidOracle);
+    }
+    let oracle_price_data = market.amm.get_oracle_price(oracle, clock.slot)?;
+    oracle::is_oracle_valid(
+        &market.amm,
+        &oracle_price_data,
+        &state.oracle_guard_rails.validity,
+    )?;
+
+    // 找到该市场对应的仓位，没有时占用一个新的仓位槽
+    let position_index = get_position_index(user_positions, params.market_index)
+        .or_else(|_| add_new_position(user_positions, params.market_index))?;
+
+    // 非0的user_order_id在用户的订单中必须唯一，以便按user_order_id撤单
+    if user_orders
+        .get_order_index_by_user_order_id(params.user_order_id)
+        .is_some()
+    {
+        return Err(Errors::DuplicateUserOrderId);
+    }
+
+    let new_order_index = user_orders
+        .get_available_order_index()
+        .ok_or(Errors::MaxNumberOfOrders)?;
+
+    let market_position = &mut user_positions.positions[position_index];
+    let new_order = Order {
+        // 触发单在满足触发条件前处于Init状态
+        status: match params.order_type {
+            OrderType::TriggerMarket | OrderType::TriggerLimit => OrderStatus::Init,
+            _ => OrderStatus::Open,
+        },
+        order_type: params.order_type,
+        direction: params.direction,
+        user_order_id: params.user_order_id,
+        reduce_only: params.reduce_only as u8,
+        post_only: params.post_only as u8,
+        immediate_or_cancel: params.immediate_or_cancel as u8,
+        // 下单时根据用户持有的discount_mint代币数量确定订单的手续费折扣等级
+        discount_tier: calculate_fee_tier(&state.fee_structure, discount_token_balance),
+        trigger_condition: params.trigger_condition,
+        padding: [0; 7],
+        ts: now,
+        market_index: params.market_index,
+        max_ts: params.max_ts,
+        padding1: [0; 8],
+        order_id: order_history.next_order_id(),
+        price: params.price,
+        // 下单时用户在该市场的仓位
+        user_base_asset_amount: market_position.base_asset_amount,
+        quote_asset_amount: params.quote_asset_amount,
+        base_asset_amount: params.base_asset_amount,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        fee: 0,
+        trigger_price: params.trigger_price,
+        referrer: user.referrer,
+        oracle_price_offset: params.oracle_price_offset,
+    };
+
+    validate_order(&new_order, market, order_state, oracle_price_data.price)?;
+    // 过期时间必须晚于下单时间
+    if new_order.max_ts != 0 && new_order.max_ts <= now {
+        return Err(Errors::InvalidOrder);
+    }
+
+    user_orders.orders[new_order_index] = new_order;
+    market_position.open_orders = market_position
+        .open_orders
+        .checked_add(1)
+        .ok_or_else(math_error!())?;
+
+    // 增添下单记录
+    let record_id = order_history.next_record_id();
+    order_history.append(OrderRecord {
+        ts: now,
+        action: OrderAction::Place,
+        padding: [0; 7],
+        record_id,
+        user: user.key(),
+        authority: user.authority,
+        order: new_order,
+        filler: Pubkey::default(),
+        trade_record_id: 0,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        fee: 0,
+        filler_reward: 0,
+        quote_asset_amount_surplus: 0,
+    });
+
+    Ok(())
+}
+
+// 撤单：释放订单槽，减少对应仓位的open_orders，并增添OrderAction::Cancel记录
+pub fn cancel_order(
+    user: &Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    order_index: usize,
+    order_history: &mut OrderHistory,
+    now: i64,
+) -> ClearingHouseResult {
+    remove_order(
+        user,
+        user_positions,
+        user_orders,
+        order_index,
+        order_history,
+        OrderAction::Cancel,
+        &Pubkey::default(),
+        0,
+        now,
+    )
+}
+
+// 释放订单槽，减少对应仓位的open_orders，并增添action对应的订单记录
+#[allow(clippy::too_many_arguments)]
+fn remove_order(
+    user: &Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    order_index: usize,
+    order_history: &mut OrderHistory,
+    action: OrderAction,
+    // 撤单时为默认地址，过期时为执行过期操作的keeper
+    filler: &Pubkey,
+    filler_reward: u128,
+    now: i64,
+) -> ClearingHouseResult {
+    let order = user_orders.orders[order_index];
+    if order.is_available() {
+        return Err(Errors::OrderDoesNotExist);
+    }
+
+    let position_index = get_position_index(user_positions, order.market_index)?;
+    let market_position = &mut user_positions.positions[position_index];
+    market_position.open_orders = market_position
+        .open_orders
+        .checked_sub(1)
+        .ok_or_else(math_error!())?;
+    user_orders.orders[order_index] = Order::zeroed();
+
+    let record_id = order_history.next_record_id();
+    order_history.append(OrderRecord {
+        ts: now,
+        action,
+        padding: [0; 7],
+        record_id,
+        user: user.key(),
+        authority: user.authority,
+        order,
+        filler: *filler,
+        trade_record_id: 0,
+        base_asset_amount_filled: 0,
+        quote_asset_amount_filled: 0,
+        fee: 0,
+        filler_reward,
+        quote_asset_amount_surplus: 0,
+    });
+
+    Ok(())
+}
+
+// 撤销用户的全部订单，可按market_index和交易方向筛选（为None时不筛选）
+pub fn cancel_all_orders(
+    user: &Account<User>,
+    user_positions: &mut UserPositions,
+    user_orders: &mut UserOrders,
+    order_history: &mut OrderHistory,
+    market_index: Option<u64>,
+    direction: Option<PositionDirection>,
+    now: i64,
+) -> ClearingHouseResult {
+    for order_index in 0..user_orders.orders.len() {
+        let order = &user_orders.orders[order_index];
+        if order.is_available()
+            || market_index.is_some_and(|market_index| market_index != order.market_index)
+            || direction.is_some_and(|direction| direction != order.direction)
+        {
+            continue;
+        }
+
+        cancel_order(
+            user,
+            user_positions,
+            user_orders,
+            order_index,
+            order_history,
+            now,
+        )?;
+    }
+
+    Ok(())
+}
+
+// 成交订单：满足订单的价格条件时与AMM成交，向用户收取手续费并从中支付填充者奖励，增添交易记录和OrderAction::Fill记录
+// 订单全部成交后释放订单槽，部分成交时保留订单等待后续成交
+// 推荐人奖励在成交时计入推荐人的抵押品（推荐人账户由调用方写回）
+// 返回值：填充者奖励，由调用方计入填充者的抵押品
+#[allow(clippy::too_many_arguments)]
+pub fn fill_order(
+    order_id: u128,
+    state: &State,
+    order_state: &OrderState,
+    user: &mut Account<User>,
+    // 用户登记的推荐人的User账户，用户没有推荐人时为None
+    referrer: Option<&mut User>,
+    user_positions: &mut UserPositions,
+    markets: &mut Markets,
+    user_orders: &mut UserOrders,
+    // 填充者的User账户地址
+    filler: &Pubkey,
+    oracle: &AccountInfo,
+    trade_history: &mut TradeHistory,
+    order_history: &mut OrderHistory,
+    funding_payment_history: &mut FundingPaymentHistory,
+    clock: &Clock,
+) -> ClearingHouseResult<u128> {
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    let order_index = user_orders
+        .get_order_index(order_id)
+        .ok_or(Errors::OrderDoesNotExist)?;
+    let mut order = user_orders.orders[order_index];
+    // 已过期的订单不能成交，只能由keeper移除
+    if order.is_expired(now) {
+        return Err(Errors::OrderExpired);
+    }
+
+    let market_index = order.market_index;
+    // 要求传入的预言机账户为该市场的预言机
+    if !markets.get_market(market_index).amm.oracle.eq(oracle.key) {
+        return Err(Errors::InvalidOracle);
+    }
+
+    // 先结算用户所有持仓的资金费
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    // 下单时已占用该市场的仓位槽
+    let position_index = get_position_index(user_positions, market_index)?;
+
+    let mark_price_before;
+    let oracle_price;
+    let triggered;
+    let limit_price;
+    // 本次可成交的数量：按base资产数量下单时为base资产数量，按quote资产数量下单时为quote资产数量
+    let amount_to_fill;
+    {
+        let market = markets.get_market_mut(market_index);
+        let market_position = &user_positions.positions[position_index];
+        mark_price_before = market.amm.mark_price()?;
+
+        // 预言机价格需通过有效性检验，再用其更新预言机价格TWAP
+        let oracle_price_data = market.amm.get_oracle_price(oracle, clock_slot)?;
+        oracle::is_oracle_valid(
+            &market.amm,
+            &oracle_price_data,
+            &state.oracle_guard_rails.validity,
+        )?;
+        oracle_price = oracle_price_data.price;
+        amm::update_oracle_twap(&mut market.amm, now, oracle_price)?;
+
+        // 触发单需先以预言机价格（而非可被操纵的标记价格）判断是否满足触发条件，触发后转为市价单或限价单
+        triggered = order.status == OrderStatus::Init;
+        if triggered {
+            if !is_order_triggered(&order, oracle_price)? {
+                return Err(Errors::OrderNotTriggered);
+            }
+            order.status = OrderStatus::Open;
+            order.order_type = match order.order_type {
+                OrderType::TriggerMarket => OrderType::Market,
+                OrderType::TriggerLimit => OrderType::Limit,
+                _ => return Err(Errors::InvalidOrder),
+            };
+        }
+
+        // 挂钩预言机价格的限价单，在成交时以最新的预言机价格重新计算限价
+        limit_price = calculate_limit_price(&order, oracle_price)?;
+
+        // 仅减仓的订单只能与仓位反向成交，且成交数量不超过剩余仓位
+        let max_reduce_only_base_asset_amount = calculate_max_reduce_only_base_asset_amount(
+            order.direction,
+            market_position.base_asset_amount,
+        );
+
+        amount_to_fill = if order.base_asset_amount != 0 {
+            // 按base资产数量下单：指定限价时，最多成交到标记价格达到限价为止
+            let base_asset_amount =
+                calculate_base_asset_amount_to_fill(&order, &market.amm, limit_price)?;
+            if order.reduce_only == 1 {
+                min(base_asset_amount, max_reduce_only_base_asset_amount)
+            } else {
+                base_asset_amount
+            }
+        } else {
+            // 按quote资产数量下单的市价单：一次全部成交
+            let quote_asset_amount = order
+                .quote_asset_amount
+                .checked_sub(order.quote_asset_amount_filled)
+                .ok_or_else(math_error!())?;
+            if order.reduce_only == 1 {
+                let max_reduce_only_quote_asset_amount = if max_reduce_only_base_asset_amount == 0 {
+                    0
+                } else {
+                    calculate_base_asset_value_and_pnl(market_position, &market.amm)?.0
+                };
+                min(quote_asset_amount, max_reduce_only_quote_asset_amount)
+            } else {
+                quote_asset_amount
+            }
+        };
+    }
+
+    if amount_to_fill == 0 {
+        // IOC订单无法成交时直接撤单
+        if order.immediate_or_cancel == 1 {
+            cancel_order(
+                user,
+                user_positions,
+                user_orders,
+                order_index,
+                order_history,
+                now,
+            )?;
+            return Ok(0);
+        }
+        // 刚被触发的限价单暂时无法成交时，保存触发状态，等待后续成交
+        if triggered {
+            user_orders.orders[order_index] = order;
+            return Ok(0);
+        }
+        return Err(Errors::OrderCannotBeFilled);
+    }
+
+    let mark_price_after;
+    let potentially_risk_increasing;
+    // 本次成交的base资产数量和quote资产数量（绝对值）
+    let base_asset_amount;
+    let quote_asset_amount;
+    {
+        let market = markets.get_market_mut(market_index);
+        let market_position = &mut user_positions.positions[position_index];
+
+        if order.base_asset_amount != 0 {
+            base_asset_amount = amount_to_fill;
+            (potentially_risk_increasing, quote_asset_amount) =
+                controller::position::update_position_with_base_asset_amount(
+                    base_asset_amount,
+                    order.direction,
+                    user,
+                    market,
+                    market_position,
+                    now,
+                )?;
+        } else {
+            quote_asset_amount = amount_to_fill;
+            (potentially_risk_increasing, base_asset_amount) =
+                controller::position::update_position_with_quote_asset_amount(
+                    quote_asset_amount,
+                    order.direction,
+                    user,
+                    market,
+                    market_position,
+                    now,
+                )?;
+
+            // 指定价格时成交均价不能劣于订单价格
+            if limit_price != 0 {
+                let entry_price = calculate_entry_price(quote_asset_amount, base_asset_amount)?;
+                let price_within_limit = match order.direction {
+                    PositionDirection::Long => entry_price <= limit_price,
+                    PositionDirection::Short => entry_price >= limit_price,
+                };
+                if !price_within_limit {
+                    return Err(Errors::SlippageOutsideLimit);
+                }
+            }
+        }
+
+        mark_price_after = market.amm.mark_price()?;
+
+        // 可能增加风险的成交不能使标记价格偏离预言机价格超过上限（已超过上限时，不能进一步扩大偏离）
+        if potentially_risk_increasing {
+            let spread_pct_before = oracle::calculate_oracle_mark_spread_pct(
+                &market.amm,
+                oracle_price,
+                Some(mark_price_before),
+            )?;
+            let spread_pct_after = oracle::calculate_oracle_mark_spread_pct(
+                &market.amm,
+                oracle_price,
+                Some(mark_price_after),
+            )?;
+            if spread_pct_after.unsigned_abs() > spread_pct_before.unsigned_abs()
+                && oracle::is_oracle_mark_too_divergent(
+                    spread_pct_after,
+                    &state.oracle_guard_rails.price_divergence,
+                )?
+            {
+                return Err(Errors::OracleMarkSpreadLimit);
+            }
+        }
+    }
+
+    // 收取手续费：持币折扣等级在下单时确定，推荐人为用户登记的推荐人
+    // 填充者奖励从计入市场的手续费中支付
+    let (user_fee, _, token_discount, _, referee_discount, filler_reward) =
+        controller::fees::charge_trade_fee(
+            user,
+            referrer,
+            markets.get_market_mut(market_index),
+            &state.fee_structure,
+            quote_asset_amount,
+            order.discount_tier,
+            Some(&order_state.order_filler_reward_structure),
+        )?;
+
+    // 可能增加风险的成交完成后，用户仍需满足初始保证金要求
+    if potentially_risk_increasing && !meets_initial_margin(user, user_positions, markets)? {
+        return Err(Errors::InsufficientCollateral);
+    }
+
+    // 增添交易记录
+    let trade_record_id = trade_history.next_record_id();
+    trade_history.append(TradeRecord {
+        ts: now,
+        market_index,
+        record_id: trade_record_id,
+        user_authority: user.authority,
+        user: user.key(),
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before,
+        mark_price_after,
+        fee: cast(user_fee)?,
+        quote_asset_amount_surplus: 0,
+        referee_discount,
+        token_discount,
+        oracle_price,
+        liquidation: 0,
+        direction: order.direction,
+        padding: [0; 14],
+    });
+
+    // 更新订单的成交数量和手续费
+    order.base_asset_amount_filled = order
+        .base_asset_amount_filled
+        .checked_add(base_asset_amount)
+        .ok_or_else(math_error!())?;
+    order.quote_asset_amount_filled = order
+        .quote_asset_amount_filled
+        .checked_add(quote_asset_amount)
+        .ok_or_else(math_error!())?;
+    order.fee = order
+        .fee
+        .checked_add(cast_to_i128(user_fee)?)
+        .ok_or_else(math_error!())?;
+
+    let mut fully_filled = if order.base_asset_amount != 0 {
+        order.base_asset_amount_filled >= order.base_asset_amount
+    } else {
+        order.quote_asset_amount_filled >= order.quote_asset_amount
+    };
+    // 仅减仓的订单在仓位平掉后视为全部成交
+    if order.reduce_only == 1 && user_positions.positions[position_index].base_asset_amount == 0 {
+        fully_filled = true;
+    }
+    if fully_filled {
+        // 全部成交：释放订单槽
+        let market_position = &mut user_positions.positions[position_index];
+        market_position.open_orders = market_position
+            .open_orders
+            .checked_sub(1)
+            .ok_or_else(math_error!())?;
+        user_orders.orders[order_index] = Order::zeroed();
+    } else {
+        user_orders.orders[order_index] = order;
+    }
+
+    // 增添成交记录，通过trade_record_id关联交易记录
+    let record_id = order_history.next_record_id();
+    order_history.append(OrderRecord {
+        ts: now,
+        action: OrderAction::Fill,
+        padding: [0; 7],
+        record_id,
+        user: user.key(),
+        authority: user.authority,
+        order,
+        filler: *filler,
+        trade_record_id,
+        base_asset_amount_filled: base_asset_amount,
+        quote_asset_amount_filled: quote_asset_amount,
+        fee: cast_to_i128(user_fee)?,
+        filler_reward,
+        quote_asset_amount_surplus: 0,
+    });
+
+    // IOC订单未成交的剩余部分在本次成交后立即撤单
+    if !fully_filled && order.immediate_or_cancel == 1 {
+        cancel_order(
+            user,
+            user_positions,
+            user_orders,
+            order_index,
+            order_history,
+            now,
+        )?;
+    }
+
+    Ok(filler_reward)
+}
+
+// 移除用户所有已过期的订单，并增添OrderAction::Expire记录
+// 每个过期订单向keeper支付order_expiry_reward的奖励，奖励只从用户的可用抵押品中支付，支付后用户仍满足初始保证金要求
+// 返回值：keeper获得的奖励总额，由调用方计入keeper的抵押品
+#[allow(clippy::too_many_arguments)]
+pub fn expire_orders(
+    order_state: &OrderState,
+    user: &mut Account<User>,
+    user_positions: &mut UserPositions,
+    markets: &Markets,
+    user_orders: &mut UserOrders,
+    order_history: &mut OrderHistory,
+    // keeper的User账户地址
+    keeper: &Pubkey,
+    now: i64,
+) -> ClearingHouseResult<u128> {
+    // 可用抵押品可能包含未实现盈利，奖励同时不能超过用户的抵押品
+    let mut free_collateral = min(
+        calculate_free_collateral(user, user_positions, markets)?,
+        user.collateral,
+    );
+    let mut total_keeper_reward: u128 = 0;
+    for order_index in 0..user_orders.orders.len() {
+        if !user_orders.orders[order_index].is_expired(now) {
+            continue;
+        }
+
+        let keeper_reward = min(order_state.order_expiry_reward, free_collateral);
+        free_collateral = free_collateral
+            .checked_sub(keeper_reward)
+            .ok_or_else(math_error!())?;
+        user.collateral = user
+            .collateral
+            .checked_sub(keeper_reward)
+            .ok_or_else(math_error!())?;
+        total_keeper_reward = total_keeper_reward
+            .checked_add(keeper_reward)
+            .ok_or_else(math_error!())?;
+
+        remove_order(
+            user,
+            user_positions,
+            user_orders,
+            order_index,
+            order_history,
+            OrderAction::Expire,
+            keeper,
+            keeper_reward,
+            now,
+        )?;
+    }
+
+    Ok(total_keeper_reward)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION};
+    use crate::math::margin::calculate_free_collateral;
+    use crate::state::OrderFillerRewardStructure;
+
+    fn new_user_data(collateral: u128) -> Vec<u8> {
+        let user = User {
+            authority: Pubkey::new_unique(),
+            collateral,
+            cumculative_deposits: 0,
+            total_fee_paid: 0,
+            total_fee_rebate: 0,
+            total_token_discount: 0,
+            total_referral_reward: 0,
+            total_referee_discount: 0,
+            positons: Pubkey::new_unique(),
+            settled_position_value: 0,
+            collateral_claimed: 0,
+            last_collateral_available_to_claim: 0,
+            forgo_position_settlement: 0,
+            has_settled_position: 0,
+            padding: [0; 14],
+            referrer: Pubkey::default(),
+        };
+        let mut data = Vec::new();
+        user.try_serialize(&mut data).unwrap();
+        data
+    }
+
+    // 市场0中的2个已过期订单
+    fn new_expired_orders(user_positions: &mut UserPositions) -> Box<UserOrders> {
+        let mut user_orders = Box::new(UserOrders::zeroed());
+        for (i, order) in user_orders.orders.iter_mut().take(2).enumerate() {
+            order.order_id = i as u128 + 1;
+            order.status = OrderStatus::Open;
+            order.order_type = OrderType::Limit;
+            order.base_asset_amount = AMM_RESERVE_PRECISION;
+            order.max_ts = 100;
+        }
+        user_positions.positions[0].open_orders = 2;
+        user_orders
+    }
+
+    #[test]
+    fn test_expire_orders() {
+        let order_state = OrderState {
+            order_history: Pubkey::default(),
+            order_filler_reward_structure: OrderFillerRewardStructure {
+                reward_numerator: 1,
+                reward_denominator: 10,
+                time_based_reward_lower_bound: 10_000,
+            },
+            min_order_quote_asset_amount: 500_000,
+            order_expiry_reward: 50 * QUOTE_PRECISION,
+        };
+        // 市场0：标记价格为50，初始保证金率为20%
+        let mut markets = Box::new(Markets::zeroed());
+        let market = markets.get_market_mut(0);
+        let reserve = 1_000_000 * AMM_RESERVE_PRECISION;
+        market.amm.base_asset_reserve = reserve;
+        market.amm.quote_asset_reserve = reserve;
+        market.amm.sqrt_k = reserve;
+        market.amm.peg_multiplier = 50 * PEG_PRECISION;
+        market.margin_ratio_initial = 2000;
+        market.margin_ratio_partial = 625;
+        market.margin_ratio_maintenance = 500;
+        let keeper = Pubkey::new_unique();
+        let key = Pubkey::new_unique();
+        let owner = crate::ID;
+
+        // 无持仓，奖励大于用户的抵押品：只支付剩余的抵押品
+        let mut lamports = 0;
+        let mut data = new_user_data(30 * QUOTE_PRECISION);
+        let account_info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let mut user: Account<User> = Account::try_from(&account_info).unwrap();
+        let mut user_positions = UserPositions::zeroed();
+        let mut user_orders = new_expired_orders(&mut user_positions);
+        let mut order_history = Box::new(OrderHistory::zeroed());
+        let keeper_reward = expire_orders(
+            &order_state,
+            &mut user,
+            &mut user_positions,
+            &markets,
+            &mut user_orders,
+            &mut order_history,
+            &keeper,
+            101,
+        )
+        .unwrap();
+        assert_eq!(keeper_reward, 30 * QUOTE_PRECISION);
+        assert_eq!(user.collateral, 0);
+        assert!(user_orders.orders.iter().all(|order| order.is_available()));
+        assert_eq!(user_positions.positions[0].open_orders, 0);
+
+        // 持有10个单位多头（价值约500），开仓成本为480：奖励只从可用抵押品中支付，支付后仍满足初始保证金要求
+        let mut lamports = 0;
+        let mut data = new_user_data(100 * QUOTE_PRECISION);
+        let account_info = AccountInfo::new(
+            &key,
+            false,
+            true,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+        let mut user: Account<User> = Account::try_from(&account_info).unwrap();
+        let mut user_positions = UserPositions::zeroed();
+        user_positions.positions[0].base_asset_amount = 10 * AMM_RESERVE_PRECISION as i128;
+        user_positions.positions[0].quote_asset_amount = 480 * QUOTE_PRECISION as i128;
+        let mut user_orders = new_expired_orders(&mut user_positions);
+        let free_collateral = calculate_free_collateral(&user, &user_positions, &markets).unwrap();
+        assert!(free_collateral > 0 && free_collateral < 50 * QUOTE_PRECISION);
+        let keeper_reward = expire_orders(
+            &order_state,
+            &mut user,
+            &mut user_positions,
+            &markets,
+            &mut user_orders,
+            &mut order_history,
+            &keeper,
+            101,
+        )
+        .unwrap();
+        assert_eq!(keeper_reward, free_collateral);
+        assert_eq!(user.collateral, 100 * QUOTE_PRECISION - free_collateral);
+        assert!(meets_initial_margin(&user, &user_positions, &markets).unwrap());
+        assert_eq!(
+            calculate_free_collateral(&user, &user_positions, &markets).unwrap(),
+            0
+        );
+    }
+}
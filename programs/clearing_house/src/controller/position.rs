@@ -1,7 +1,21 @@
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
 
-#[derive(Clone, Copy, AnchorDeserialize, AnchorSerialize)]
+use crate::controller::amm::{swap_base_asset, swap_quote_asset, SwapDirection};
+use crate::errors::Errors;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::{cast, cast_to_i128, cast_to_u128};
+use crate::math::collateral::calculate_updated_collateral;
+use crate::math::pnl::calculate_pnl;
+use crate::math::position::{
+    calculate_base_asset_value_and_pnl, direction_to_close_position,
+    swap_direction_to_close_position,
+};
+use crate::math::quote_asset::asset_to_reserve_amount;
+use crate::math_error;
+use crate::state::{Market, MarketPosition, User, UserPositions};
+
+#[derive(Clone, Copy, PartialEq, Eq, AnchorDeserialize, AnchorSerialize)]
 #[repr(u8)]
 pub enum PositionDirection {
     Long,
@@ -10,3 +24,587 @@ pub enum PositionDirection {
 
 unsafe impl Zeroable for PositionDirection {}
 unsafe impl Pod for PositionDirection {}
+
+// 在用户的UserPositions中找到market_index对应的活跃仓位的索引
+pub fn get_position_index(
+    user_positions: &UserPositions,
+    market_index: u64,
+) -> ClearingHouseResult<usize> {
+    user_positions
+        .positions
+        .iter()
+        .position(|market_position| market_position.is_for(market_index))
+        .ok_or(Errors::UserHasNoPositionInMarket)
+}
+
+// 在用户的UserPositions中占用一个可用的仓位槽，用于market_index对应的市场，返回该仓位的索引
+pub fn add_new_position(
+    user_positions: &mut UserPositions,
+    market_index: u64,
+) -> ClearingHouseResult<usize> {
+    let new_position_index = user_positions
+        .positions
+        .iter()
+        .position(|market_position| market_position.is_available())
+        .ok_or(Errors::MaxNumberOfPositions)?;
+
+    user_positions.positions[new_position_index] = MarketPosition {
+        market_index,
+        last_funding_rate_ts: 0,
+        base_asset_amount: 0,
+        quote_asset_amount: 0,
+        last_cumulative_funding_rate: 0,
+        last_cumulative_repeg_rebate: 0,
+        open_orders: 0,
+        padding: [0; 7],
+    };
+
+    Ok(new_position_index)
+}
+
+// 沿仓位方向（或在无持仓时）加仓，花费quote_asset_amount数量的quote资产，返回获得的base资产数量
+pub fn increase(
+    direction: PositionDirection,
+    quote_asset_amount: u128,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<i128> {
+    if quote_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    // 做多：向AMM中加入quote资产换出base资产；做空：从AMM中移除quote资产（即卖出base资产）
+    let swap_direction = match direction {
+        PositionDirection::Long => SwapDirection::Add,
+        PositionDirection::Short => SwapDirection::Remove,
+    };
+    let base_asset_acquired =
+        swap_quote_asset(&mut market.amm, quote_asset_amount, swap_direction, now)?;
+
+    update_position_after_increase(
+        direction,
+        base_asset_acquired,
+        quote_asset_amount,
+        market,
+        market_position,
+    )?;
+
+    Ok(base_asset_acquired)
+}
+
+// 沿仓位方向（或在无持仓时）加仓，获得base_asset_amount数量的base资产，返回花费的quote资产数量
+pub fn increase_with_base_asset_amount(
+    direction: PositionDirection,
+    base_asset_amount: u128,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<u128> {
+    if base_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    // 做多：从AMM中移除base资产（即买入）；做空：向AMM中加入base资产（即卖出）
+    let (swap_direction, base_asset_acquired) = match direction {
+        PositionDirection::Long => (SwapDirection::Remove, cast_to_i128(base_asset_amount)?),
+        PositionDirection::Short => (SwapDirection::Add, -cast_to_i128(base_asset_amount)?),
+    };
+    let quote_asset_amount =
+        swap_base_asset(&mut market.amm, base_asset_amount, swap_direction, now)?;
+
+    update_position_after_increase(
+        direction,
+        base_asset_acquired,
+        quote_asset_amount,
+        market,
+        market_position,
+    )?;
+
+    Ok(quote_asset_amount)
+}
+
+// 加仓与AMM交换后，更新仓位和市场的持仓数据
+fn update_position_after_increase(
+    direction: PositionDirection,
+    base_asset_acquired: i128,
+    quote_asset_amount: u128,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult {
+    // 新开仓时，记录当前方向的累计资金费率，并增加市场的持仓用户数量
+    if market_position.base_asset_amount == 0 {
+        market_position.last_cumulative_funding_rate = match direction {
+            PositionDirection::Long => market.amm.cumulative_funding_rate_long,
+            PositionDirection::Short => market.amm.cumulative_funding_rate_short,
+        };
+        market_position.last_funding_rate_ts = market.amm.last_funding_rate_ts;
+
+        market.open_interest = market
+            .open_interest
+            .checked_add(1)
+            .ok_or_else(math_error!())?;
+    }
+
+    market_position.quote_asset_amount = market_position
+        .quote_asset_amount
+        .checked_add(cast(quote_asset_amount)?)
+        .ok_or_else(math_error!())?;
+
+    market_position.base_asset_amount = market_position
+        .base_asset_amount
+        .checked_add(base_asset_acquired)
+        .ok_or_else(math_error!())?;
+    market.base_asset_amount = market
+        .base_asset_amount
+        .checked_add(base_asset_acquired)
+        .ok_or_else(math_error!())?;
+    if market_position.base_asset_amount > 0 {
+        market.base_asset_amount_long = market
+            .base_asset_amount_long
+            .checked_add(base_asset_acquired)
+            .ok_or_else(math_error!())?;
+    } else {
+        market.base_asset_amount_short = market
+            .base_asset_amount_short
+            .checked_add(base_asset_acquired)
+            .ok_or_else(math_error!())?;
+    }
+
+    Ok(())
+}
+
+// 反向减仓（不平仓），交换quote_asset_swap_amount数量的quote资产，按比例实现盈亏并计入用户抵押品，返回交换的base资产数量
+pub fn reduce(
+    // 本次交易方向（与仓位方向相反）
+    direction: PositionDirection,
+    quote_asset_swap_amount: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<i128> {
+    let swap_direction = match direction {
+        PositionDirection::Long => SwapDirection::Add,
+        PositionDirection::Short => SwapDirection::Remove,
+    };
+    let base_asset_swapped = swap_quote_asset(
+        &mut market.amm,
+        quote_asset_swap_amount,
+        swap_direction,
+        now,
+    )?;
+
+    update_position_after_reduce(
+        base_asset_swapped,
+        quote_asset_swap_amount,
+        user,
+        market,
+        market_position,
+    )?;
+
+    Ok(base_asset_swapped)
+}
+
+// 反向减仓（不平仓），交换base_asset_amount数量的base资产，按比例实现盈亏并计入用户抵押品，返回交换的quote资产数量
+pub fn reduce_with_base_asset_amount(
+    base_asset_amount: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<u128> {
+    let swap_direction = swap_direction_to_close_position(market_position.base_asset_amount);
+    let quote_asset_swap_amount =
+        swap_base_asset(&mut market.amm, base_asset_amount, swap_direction, now)?;
+
+    // 减多仓时base资产减少，减空仓时base资产增加
+    let base_asset_swapped = if market_position.base_asset_amount > 0 {
+        -cast_to_i128(base_asset_amount)?
+    } else {
+        cast_to_i128(base_asset_amount)?
+    };
+
+    update_position_after_reduce(
+        base_asset_swapped,
+        quote_asset_swap_amount,
+        user,
+        market,
+        market_position,
+    )?;
+
+    Ok(quote_asset_swap_amount)
+}
+
+// 减仓与AMM交换后，更新仓位和市场的持仓数据，并实现被平掉部分的盈亏
+fn update_position_after_reduce(
+    base_asset_swapped: i128,
+    quote_asset_swap_amount: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+) -> ClearingHouseResult {
+    let base_asset_amount_before = market_position.base_asset_amount;
+    market_position.base_asset_amount = market_position
+        .base_asset_amount
+        .checked_add(base_asset_swapped)
+        .ok_or_else(math_error!())?;
+
+    // 由于取整，减仓后的仓位可能恰好为0
+    if market_position.base_asset_amount == 0 {
+        market.open_interest = market
+            .open_interest
+            .checked_sub(1)
+            .ok_or_else(math_error!())?;
+    }
+
+    market.base_asset_amount = market
+        .base_asset_amount
+        .checked_add(base_asset_swapped)
+        .ok_or_else(math_error!())?;
+    if base_asset_amount_before > 0 {
+        market.base_asset_amount_long = market
+            .base_asset_amount_long
+            .checked_add(base_asset_swapped)
+            .ok_or_else(math_error!())?;
+    } else {
+        market.base_asset_amount_short = market
+            .base_asset_amount_short
+            .checked_add(base_asset_swapped)
+            .ok_or_else(math_error!())?;
+    }
+
+    // 按减仓的base资产比例，计算被平掉部分的开仓成本
+    let initial_quote_asset_amount_closed = cast_to_u128(market_position.quote_asset_amount)?
+        .checked_mul(base_asset_swapped.unsigned_abs())
+        .ok_or_else(math_error!())?
+        .checked_div(base_asset_amount_before.unsigned_abs())
+        .ok_or_else(math_error!())?;
+
+    market_position.quote_asset_amount = market_position
+        .quote_asset_amount
+        .checked_sub(cast(initial_quote_asset_amount_closed)?)
+        .ok_or_else(math_error!())?;
+
+    let pnl = calculate_pnl(
+        quote_asset_swap_amount,
+        initial_quote_asset_amount_closed,
+        swap_direction_to_close_position(base_asset_amount_before),
+    )?;
+
+    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+
+    Ok(())
+}
+
+// 将仓位全部平掉，实现盈亏并计入用户抵押品
+// 返回值：(交换的quote资产数量, 平掉的base资产数量（平仓前的仓位）, 实现的盈亏)
+pub fn close(
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<(u128, i128, i128)> {
+    if market_position.base_asset_amount == 0 {
+        return Ok((0, 0, 0));
+    }
+
+    let swap_direction = swap_direction_to_close_position(market_position.base_asset_amount);
+    let quote_asset_swapped = swap_base_asset(
+        &mut market.amm,
+        market_position.base_asset_amount.unsigned_abs(),
+        swap_direction,
+        now,
+    )?;
+
+    let pnl = calculate_pnl(
+        quote_asset_swapped,
+        cast_to_u128(market_position.quote_asset_amount)?,
+        swap_direction,
+    )?;
+
+    user.collateral = calculate_updated_collateral(user.collateral, pnl)?;
+
+    market.open_interest = market
+        .open_interest
+        .checked_sub(1)
+        .ok_or_else(math_error!())?;
+    market.base_asset_amount = market
+        .base_asset_amount
+        .checked_sub(market_position.base_asset_amount)
+        .ok_or_else(math_error!())?;
+    if market_position.base_asset_amount > 0 {
+        market.base_asset_amount_long = market
+            .base_asset_amount_long
+            .checked_sub(market_position.base_asset_amount)
+            .ok_or_else(math_error!())?;
+    } else {
+        market.base_asset_amount_short = market
+            .base_asset_amount_short
+            .checked_sub(market_position.base_asset_amount)
+            .ok_or_else(math_error!())?;
+    }
+
+    let base_asset_amount = market_position.base_asset_amount;
+    market_position.base_asset_amount = 0;
+    market_position.quote_asset_amount = 0;
+    market_position.last_cumulative_funding_rate = 0;
+    market_position.last_funding_rate_ts = 0;
+
+    Ok((quote_asset_swapped, base_asset_amount, pnl))
+}
+
+// 部分清算时反向减仓quote_asset_amount数量的quote资产；
+// 减仓数量低于AMM的quote资产最小交易量时直接平掉全部仓位，避免单个小仓位导致整个部分清算失败
+// 返回值：(交换的base资产数量（绝对值）, 交换的quote资产数量)
+pub fn reduce_for_partial_liquidation(
+    quote_asset_amount: u128,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<(u128, u128)> {
+    let direction = direction_to_close_position(market_position.base_asset_amount);
+    let quote_asset_reserve_amount = asset_to_reserve_amount(
+        quote_asset_amount,
+        market.amm.peg_multiplier,
+        direction == PositionDirection::Short,
+    )?;
+
+    if quote_asset_reserve_amount < market.amm.mininum_quote_asset_trade_size {
+        let (quote_asset_swapped, base_asset_amount, _) =
+            close(user, market, market_position, now)?;
+        return Ok((base_asset_amount.unsigned_abs(), quote_asset_swapped));
+    }
+
+    let base_asset_swapped = reduce(
+        direction,
+        quote_asset_amount,
+        user,
+        market,
+        market_position,
+        now,
+    )?;
+
+    Ok((base_asset_swapped.unsigned_abs(), quote_asset_amount))
+}
+
+// 按quote资产数量成交：无持仓或与仓位同向时加仓，反向时减仓，或平仓后反向开仓
+// 返回值：(本次交易是否可能增加用户的风险, 交换的base资产数量（绝对值）)
+pub fn update_position_with_quote_asset_amount(
+    quote_asset_amount: u128,
+    direction: PositionDirection,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<(bool, u128)> {
+    // 无持仓或与仓位同向：加仓
+    if market_position.base_asset_amount == 0
+        || (market_position.base_asset_amount > 0 && direction == PositionDirection::Long)
+        || (market_position.base_asset_amount < 0 && direction == PositionDirection::Short)
+    {
+        let base_asset_amount =
+            increase(direction, quote_asset_amount, market, market_position, now)?;
+        return Ok((true, base_asset_amount.unsigned_abs()));
+    }
+
+    // 与仓位反向：先判断是减仓还是平仓后反向开仓
+    let (base_asset_value, _unrealized_pnl) =
+        calculate_base_asset_value_and_pnl(market_position, &market.amm)?;
+
+    if base_asset_value > quote_asset_amount {
+        let base_asset_amount = reduce(
+            direction,
+            quote_asset_amount,
+            user,
+            market,
+            market_position,
+            now,
+        )?;
+        return Ok((false, base_asset_amount.unsigned_abs()));
+    }
+
+    // 平仓后剩余的quote资产数量用于反向开仓
+    let quote_asset_amount_after_close = quote_asset_amount
+        .checked_sub(base_asset_value)
+        .ok_or_else(math_error!())?;
+    // 反向开出的仓位价值不大于原仓位价值时，视为降低风险
+    let potentially_risk_increasing = quote_asset_amount_after_close > base_asset_value;
+
+    let (_, base_asset_amount_closed, _) = close(user, market, market_position, now)?;
+    let base_asset_amount_opened = increase(
+        direction,
+        quote_asset_amount_after_close,
+        market,
+        market_position,
+        now,
+    )?;
+
+    let base_asset_amount = base_asset_amount_closed
+        .unsigned_abs()
+        .checked_add(base_asset_amount_opened.unsigned_abs())
+        .ok_or_else(math_error!())?;
+
+    Ok((potentially_risk_increasing, base_asset_amount))
+}
+
+// 按base资产数量成交：无持仓或与仓位同向时加仓，反向时减仓，或平仓后反向开仓
+// 返回值：(本次交易是否可能增加用户的风险, 交换的quote资产数量)
+pub fn update_position_with_base_asset_amount(
+    base_asset_amount: u128,
+    direction: PositionDirection,
+    user: &mut User,
+    market: &mut Market,
+    market_position: &mut MarketPosition,
+    now: i64,
+) -> ClearingHouseResult<(bool, u128)> {
+    // 无持仓或与仓位同向：加仓
+    if market_position.base_asset_amount == 0
+        || (market_position.base_asset_amount > 0 && direction == PositionDirection::Long)
+        || (market_position.base_asset_amount < 0 && direction == PositionDirection::Short)
+    {
+        let quote_asset_amount = increase_with_base_asset_amount(
+            direction,
+            base_asset_amount,
+            market,
+            market_position,
+            now,
+        )?;
+        return Ok((true, quote_asset_amount));
+    }
+
+    let existing_base_asset_amount = market_position.base_asset_amount.unsigned_abs();
+    if existing_base_asset_amount > base_asset_amount {
+        let quote_asset_amount =
+            reduce_with_base_asset_amount(base_asset_amount, user, market, market_position, now)?;
+        return Ok((false, quote_asset_amount));
+    }
+
+    // 平仓后剩余的base资产数量用于反向开仓
+    let base_asset_amount_after_close = base_asset_amount
+        .checked_sub(existing_base_asset_amount)
+        .ok_or_else(math_error!())?;
+    // 反向开出的仓位不大于原仓位时，视为降低风险
+    let potentially_risk_increasing = base_asset_amount_after_close > existing_base_asset_amount;
+
+    let (quote_asset_amount_closed, _, _) = close(user, market, market_position, now)?;
+    let quote_asset_amount_opened = increase_with_base_asset_amount(
+        direction,
+        base_asset_amount_after_close,
+        market,
+        market_position,
+        now,
+    )?;
+
+    let quote_asset_amount = quote_asset_amount_closed
+        .checked_add(quote_asset_amount_opened)
+        .ok_or_else(math_error!())?;
+
+    Ok((potentially_risk_increasing, quote_asset_amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION};
+    use crate::state::Markets;
+
+    fn new_user(collateral: u128) -> User {
+        User {
+            authority: Pubkey::new_unique(),
+            collateral,
+            cumculative_deposits: 0,
+            total_fee_paid: 0,
+            total_fee_rebate: 0,
+            total_token_discount: 0,
+            total_referral_reward: 0,
+            total_referee_discount: 0,
+            positons: Pubkey::new_unique(),
+            settled_position_value: 0,
+            collateral_claimed: 0,
+            last_collateral_available_to_claim: 0,
+            forgo_position_settlement: 0,
+            has_settled_position: 0,
+            padding: [0; 14],
+            referrer: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn test_reduce_for_partial_liquidation() {
+        // 市场0、1：标记价格为50
+        let mut markets = Box::new(Markets::zeroed());
+        for market_index in 0..2 {
+            let market = markets.get_market_mut(market_index);
+            let reserve = 1_000_000 * AMM_RESERVE_PRECISION;
+            market.amm.base_asset_reserve = reserve;
+            market.amm.quote_asset_reserve = reserve;
+            market.amm.sqrt_k = reserve;
+            market.amm.peg_multiplier = 50 * PEG_PRECISION;
+            market.amm.mininum_quote_asset_trade_size = 10_000_000;
+        }
+
+        // 市场0中1000个quote资产的多头，市场1中0.0001个quote资产的多头
+        let mut user = new_user(0);
+        let mut user_positions = UserPositions::zeroed();
+        user_positions.positions[1].market_index = 1;
+        for (i, quote_asset_amount) in [1000 * QUOTE_PRECISION, 100].iter().enumerate() {
+            increase(
+                PositionDirection::Long,
+                *quote_asset_amount,
+                markets.get_market_mut(i as u64),
+                &mut user_positions.positions[i],
+                0,
+            )
+            .unwrap();
+        }
+        let base_asset_amount_before = user_positions.positions[0].base_asset_amount;
+
+        // 各仓位减仓25%的仓位价值
+        let mut quote_asset_amounts = [0_u128; 2];
+        for (i, quote_asset_amount) in quote_asset_amounts.iter_mut().enumerate() {
+            let (position_base_asset_value, _) = calculate_base_asset_value_and_pnl(
+                &user_positions.positions[i],
+                &markets.get_market(i as u64).amm,
+            )
+            .unwrap();
+            *quote_asset_amount = position_base_asset_value * 25 / 100;
+        }
+
+        // 小仓位的减仓数量低于最小交易量，直接减仓会失败
+        let mut market = *markets.get_market(1);
+        let mut market_position = user_positions.positions[1];
+        assert!(matches!(
+            reduce(
+                PositionDirection::Short,
+                quote_asset_amounts[1],
+                &mut user,
+                &mut market,
+                &mut market_position,
+                0,
+            ),
+            Err(Errors::TradeSizeTooSmall)
+        ));
+
+        for (i, quote_asset_amount) in quote_asset_amounts.iter().enumerate() {
+            reduce_for_partial_liquidation(
+                *quote_asset_amount,
+                &mut user,
+                markets.get_market_mut(i as u64),
+                &mut user_positions.positions[i],
+                0,
+            )
+            .unwrap();
+        }
+
+        // 大仓位减仓约25%，小仓位被全部平掉
+        let base_asset_amount_after = user_positions.positions[0].base_asset_amount;
+        assert!(base_asset_amount_after > base_asset_amount_before * 74 / 100);
+        assert!(base_asset_amount_after < base_asset_amount_before * 76 / 100);
+        assert_eq!(user_positions.positions[1].base_asset_amount, 0);
+        assert_eq!(user_positions.positions[1].quote_asset_amount, 0);
+        assert_eq!(markets.get_market(1).open_interest, 0);
+        assert_eq!(markets.get_market(1).base_asset_amount, 0);
+    }
+}
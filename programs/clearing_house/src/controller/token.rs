@@ -0,0 +1,41 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, Token, TokenAccount, Transfer};
+
+// 将用户的token转入本program的vault，由用户（signer）授权
+pub fn receive<'info>(
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    authority: &Signer<'info>,
+    amount: u64,
+) -> Result<()> {
+    let cpi_accounts = Transfer {
+        from: from.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.to_account_info(),
+    };
+    let cpi_context = CpiContext::new(token_program.to_account_info(), cpi_accounts);
+    token::transfer(cpi_context, amount)
+}
+
+// 将本program的vault中的token转出，由vault的authority（pda）签名
+// 注：vault authority的seeds为[vault地址]，nonce为生成该pda时的bump
+pub fn send<'info>(
+    token_program: &Program<'info, Token>,
+    from: &Account<'info, TokenAccount>,
+    to: &Account<'info, TokenAccount>,
+    authority: &AccountInfo<'info>,
+    nonce: u8,
+    amount: u64,
+) -> Result<()> {
+    let from_key = from.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[from_key.as_ref(), &[nonce]]];
+    let cpi_accounts = Transfer {
+        from: from.to_account_info(),
+        to: to.to_account_info(),
+        authority: authority.to_account_info(),
+    };
+    let cpi_context =
+        CpiContext::new_with_signer(token_program.to_account_info(), cpi_accounts, signer_seeds);
+    token::transfer(cpi_context, amount)
+}
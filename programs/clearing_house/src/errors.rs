@@ -32,6 +32,80 @@ pub enum Errors {
     InvalidWhitelistToken,
     #[msg("No balance")]
     WhitelistTokenNoBalance,
+    #[msg("Exchange is paused")]
+    ExchangePaused,
+    #[msg("Deposit amount must be greater than 0")]
+    InsufficientDeposit,
+    #[msg("User cumulative deposits exceed max deposit")]
+    UserMaxDeposit,
+    #[msg("Insufficient collateral")]
+    InsufficientCollateral,
+    #[msg("Trade size too large")]
+    TradeSizeTooLarge,
+    #[msg("Trade size too small")]
+    TradeSizeTooSmall,
+    #[msg("Market index not initialized")]
+    MarketIndexNotInitialized,
+    #[msg("Invalid oracle account")]
+    InvalidOracle,
+    #[msg("Max number of positions taken")]
+    MaxNumberOfPositions,
+    #[msg("User has no position in market")]
+    UserHasNoPositionInMarket,
+    #[msg("Trade price outside of limit price")]
+    SlippageOutsideLimit,
+    #[msg("Funding is paused")]
+    FundingPaused,
+    #[msg("Funding period has not elapsed since last funding rate update")]
+    FundingPeriodNotElapsed,
+    #[msg("Oracle price is stale")]
+    OraclePriceStale,
+    #[msg("Oracle has insufficient number of data points")]
+    OracleInsufficientDataPoints,
+    #[msg("Oracle price is non-positive")]
+    OraclePriceNonPositive,
+    #[msg("Oracle confidence interval is too wide")]
+    OracleConfidenceTooWide,
+    #[msg("Oracle price is too volatile")]
+    OraclePriceTooVolatile,
+    #[msg("Mark price diverges too far from oracle price")]
+    OracleMarkSpreadLimit,
+    #[msg("Admin does not control prices")]
+    AdminControlsPricesDisabled,
+    #[msg("Oracle account not owned by the market's oracle program")]
+    InvalidOracleOwner,
+    #[msg("Oracle feed id does not match the market's feed id")]
+    InvalidOracleFeedId,
+    #[msg("Oracle price update is not fully verified")]
+    OracleNotFullyVerified,
+    #[msg("User has sufficient collateral and cannot be liquidated")]
+    SufficientCollateral,
+    #[msg("Oracle account for market not found in remaining accounts")]
+    OracleNotFound,
+    #[msg("Invalid discount token")]
+    InvalidDiscountToken,
+    #[msg("Invalid referrer")]
+    InvalidReferrer,
+    #[msg("Max number of orders taken")]
+    MaxNumberOfOrders,
+    #[msg("Order amount too small")]
+    OrderAmountTooSmall,
+    #[msg("Invalid order")]
+    InvalidOrder,
+    #[msg("Order does not exist")]
+    OrderDoesNotExist,
+    #[msg("User order id is already in use")]
+    DuplicateUserOrderId,
+    #[msg("Order trigger condition not met")]
+    OrderNotTriggered,
+    #[msg("Order cannot be filled at the current price")]
+    OrderCannotBeFilled,
+    #[msg("Post-only order would cross the AMM")]
+    PostOnlyOrderWouldCross,
+    #[msg("Order has expired")]
+    OrderExpired,
+    #[msg("Withdrawal amount must be greater than 0")]
+    InsufficientWithdrawal,
 }
 
 // #[macro_export]：使宏可以被其他模块通过`use crate::math_error;`导入
@@ -0,0 +1,107 @@
+use crate::controller;
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 按全局订单ID撤单
+pub fn handle_cancel_order(ctx: Context<CancelOrder>, order_id: u128) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+    let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+    let order_index = user_orders
+        .get_order_index(order_id)
+        .ok_or(Errors::OrderDoesNotExist)?;
+    controller::orders::cancel_order(
+        &ctx.accounts.user,
+        user_positions,
+        user_orders,
+        order_index,
+        order_history,
+        now,
+    )?;
+
+    Ok(())
+}
+
+// 按用户自定义的user_order_id撤单
+pub fn handle_cancel_order_by_user_id(ctx: Context<CancelOrder>, user_order_id: u8) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+    let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+    let order_index = user_orders
+        .get_order_index_by_user_order_id(user_order_id)
+        .ok_or(Errors::OrderDoesNotExist)?;
+    controller::orders::cancel_order(
+        &ctx.accounts.user,
+        user_positions,
+        user_orders,
+        order_index,
+        order_history,
+        now,
+    )?;
+
+    Ok(())
+}
+
+// 撤销全部订单，可按market_index和交易方向筛选
+pub fn handle_cancel_all_orders(
+    ctx: Context<CancelOrder>,
+    market_index: Option<u64>,
+    direction: Option<PositionDirection>,
+) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+    let order_history = &mut ctx.accounts.order_history.load_mut()?;
+
+    controller::orders::cancel_all_orders(
+        &ctx.accounts.user,
+        user_positions,
+        user_orders,
+        order_history,
+        market_index,
+        direction,
+        now,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CancelOrder<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        has_one = authority,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        seeds = [b"user_orders", user.key().as_ref()],
+        bump,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = state.load()?.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+}
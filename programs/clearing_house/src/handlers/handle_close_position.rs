@@ -0,0 +1,169 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::cast::cast;
+use crate::math::fees::calculate_fee_tier;
+use crate::math::oracle;
+use crate::math::position::direction_to_close_position;
+use crate::optional_accounts::{get_discount_token, get_referrer, ManagePositionOptionalAccounts};
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+pub fn handle_close_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ClosePosition<'info>>,
+    market_index: u64,
+    optional_accounts: ManagePositionOptionalAccounts,
+) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let user = &mut ctx.accounts.user;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    let markets = &mut ctx.accounts.markets.load_mut()?;
+    // 要求market_index对应的市场已经初始化
+    if Markets::index_from_u64(market_index) >= markets.markets.len()
+        || !markets.get_market(market_index).is_initialized()
+    {
+        return err!(Errors::MarketIndexNotInitialized);
+    }
+
+    // 要求传入的预言机账户为该市场的预言机
+    if !markets
+        .get_market(market_index)
+        .amm
+        .oracle
+        .eq(ctx.accounts.oracle.key)
+    {
+        return err!(Errors::InvalidOracle);
+    }
+
+    // 先结算用户所有持仓的资金费
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    let position_index = controller::position::get_position_index(user_positions, market_index)?;
+    let market_position = &mut user_positions.positions[position_index];
+    // 仅有挂单而无持仓时，无仓位可平
+    if market_position.base_asset_amount == 0 {
+        return err!(Errors::UserHasNoPositionInMarket);
+    }
+
+    let market = markets.get_market_mut(market_index);
+    let mark_price_before = market.amm.mark_price()?;
+    let direction_to_close = direction_to_close_position(market_position.base_asset_amount);
+
+    // 预言机价格需通过有效性检验，再用其更新预言机价格TWAP
+    let oracle_price_data = market
+        .amm
+        .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
+    oracle::is_oracle_valid(
+        &market.amm,
+        &oracle_price_data,
+        &state.oracle_guard_rails.validity,
+    )?;
+    let oracle_price = oracle_price_data.price;
+    amm::update_oracle_twap(&mut market.amm, now, oracle_price)?;
+
+    // 将仓位的全部base资产与AMM交换，实现盈亏并计入用户抵押品
+    // 注：平仓后该仓位base_asset_amount为0，若无挂单则该仓位槽变为可用（is_available）
+    let (quote_asset_amount, base_asset_amount, _pnl) =
+        controller::position::close(user, market, market_position, now)?;
+    let base_asset_amount = base_asset_amount.unsigned_abs();
+
+    // 收取手续费：根据用户持有的discount_mint代币数量确定持币折扣等级，有推荐人时享受被推荐人折扣
+    let discount_token = get_discount_token(
+        optional_accounts,
+        ctx.remaining_accounts,
+        &state.discount_mint,
+        ctx.accounts.authority.key,
+    )?;
+    let mut referrer = get_referrer(optional_accounts, ctx.remaining_accounts, &user.referrer)?;
+    let discount_tier = calculate_fee_tier(
+        &state.fee_structure,
+        discount_token.map_or(0, |token| token.amount),
+    );
+    let (user_fee, _, token_discount, _, referee_discount, _) = controller::fees::charge_trade_fee(
+        user,
+        referrer.as_deref_mut(),
+        market,
+        &state.fee_structure,
+        quote_asset_amount,
+        discount_tier,
+        None,
+    )?;
+    if let Some(referrer) = referrer.as_ref() {
+        // 推荐人账户通过remaining_accounts传入，需手动写回
+        referrer.exit(ctx.program_id)?;
+    }
+
+    let mark_price_after = market.amm.mark_price()?;
+
+    // 增添交易记录
+    let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+    let record_id = trade_history.next_record_id();
+    trade_history.append(TradeRecord {
+        ts: now,
+        market_index,
+        record_id,
+        user_authority: *ctx.accounts.authority.key,
+        user: user.key(),
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before,
+        mark_price_after,
+        fee: cast(user_fee)?,
+        quote_asset_amount_surplus: 0,
+        referee_discount,
+        token_discount,
+        oracle_price,
+        liquidation: 0,
+        direction: direction_to_close,
+        padding: [0; 14],
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClosePosition<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = state.load()?.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    /// CHECK: checked in `close_position`
+    pub oracle: UncheckedAccount<'info>,
+}
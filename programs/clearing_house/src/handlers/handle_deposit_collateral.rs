@@ -1,16 +1,74 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math::cast::{cast, cast_to_i128};
+use crate::math_error;
 use crate::state::*;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{Token, TokenAccount};
 
-pub fn handle_deposit_collateral(ctx: Context<DepositCollateral>, _amount: u64) -> Result<()> {
+pub fn handle_deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> Result<()> {
+    // 存款数量不能为0
+    if amount == 0 {
+        return err!(Errors::InsufficientDeposit);
+    }
+
     let user = &mut ctx.accounts.user;
-    let _now = Clock::get()?.unix_timestamp;
+    let now = Clock::get()?.unix_timestamp;
+
+    let collateral_before = user.collateral;
+    let cumculative_deposits_before = user.cumculative_deposits;
+
+    // 增加用户的抵押品和累计存款
+    user.collateral = user
+        .collateral
+        .checked_add(cast(amount)?)
+        .ok_or_else(math_error!())?;
+    user.cumculative_deposits = user
+        .cumculative_deposits
+        .checked_add(cast(amount)?)
+        .ok_or_else(math_error!())?;
+
+    // 如果state.max_deposit不为0，用户累计存款不能超过该限额
+    let max_deposit = ctx.accounts.state.load()?.max_deposit;
+    if max_deposit > 0 && user.cumculative_deposits > cast_to_i128(max_deposit)? {
+        return err!(Errors::UserMaxDeposit);
+    }
+
+    // 结算用户所有持仓的资金费
+    let markets = &ctx.accounts.markets.load()?;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
 
-    let _collateral_before = user.collateral;
-    let _cumculative_deposits_before = user.cumculative_deposits;
+    // 将amount数量的抵押品从用户的token account转入collateral_vault
+    controller::token::receive(
+        &ctx.accounts.token_program,
+        &ctx.accounts.user_collateral_account,
+        &ctx.accounts.collateral_vault,
+        &ctx.accounts.authority,
+        amount,
+    )?;
 
-    let _markets = ctx.accounts.markets.load()?;
-    let _user_position = ctx.accounts.user_positions.load_mut()?;
+    // 增添存款记录
+    let deposit_history = &mut ctx.accounts.deposit_history.load_mut()?;
+    let record_id = deposit_history.next_record_id();
+    deposit_history.append(DepositRecord {
+        ts: now,
+        amount,
+        record_id,
+        user_authority: user.authority,
+        user: user.key(),
+        collateral_before,
+        cumulative_deposits_before: cumculative_deposits_before,
+        direction: DepositDirection::Deposit,
+        padding: [0; 15],
+    });
 
     Ok(())
 }
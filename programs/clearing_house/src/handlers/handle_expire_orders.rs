@@ -0,0 +1,81 @@
+use crate::controller;
+use crate::math_error;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 任何人都可以作为keeper移除用户已过期的订单，并从用户的可用抵押品中获得奖励
+pub fn handle_expire_orders(ctx: Context<ExpireOrders>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+    let keeper_key = ctx.accounts.keeper.key();
+
+    let keeper_reward = {
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let markets = &ctx.accounts.markets.load()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        controller::orders::expire_orders(
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            user_positions,
+            markets,
+            user_orders,
+            order_history,
+            &keeper_key,
+            now,
+        )?
+    };
+
+    // keeper奖励计入keeper的抵押品
+    let keeper = &mut ctx.accounts.keeper;
+    keeper.collateral = keeper
+        .collateral
+        .checked_add(keeper_reward)
+        .ok_or_else(math_error!())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ExpireOrders<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub keeper: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        // 保证keeper与user不是同一账户
+        constraint = !user.key().eq(&keeper.key()),
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        seeds = [b"user_orders", user.key().as_ref()],
+        bump,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = state.load()?.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+}
@@ -0,0 +1,139 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math_error;
+use crate::optional_accounts::{get_referrer, ManagePositionOptionalAccounts};
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 任何人都可以作为填充者成交满足价格条件的订单，并获得填充者奖励
+// 用户登记了推荐人时，推荐人的User账户需通过ctx.remaining_accounts传入（推荐人即填充者时无需传入）
+pub fn handle_fill_order<'info>(
+    ctx: Context<'_, '_, 'info, 'info, FillOrder<'info>>,
+    order_id: u128,
+) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let clock = Clock::get()?;
+    let filler_key = ctx.accounts.filler.key();
+
+    // 用户登记的推荐人不是填充者时，推荐人账户需通过remaining_accounts传入
+    let referrer_key = ctx.accounts.user.referrer;
+    let mut referrer = if referrer_key.eq(&Pubkey::default()) || referrer_key.eq(&filler_key) {
+        None
+    } else {
+        Some(
+            get_referrer(
+                ManagePositionOptionalAccounts {
+                    discount_token: false,
+                    referrer: true,
+                },
+                ctx.remaining_accounts,
+                &referrer_key,
+            )?
+            .ok_or(Errors::InvalidReferrer)?,
+        )
+    };
+
+    let filler_reward = {
+        let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+        let markets = &mut ctx.accounts.markets.load_mut()?;
+        let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+        let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+        let order_history = &mut ctx.accounts.order_history.load_mut()?;
+        let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+        // 推荐人即填充者时，推荐人奖励直接计入填充者
+        let referrer: Option<&mut User> = if referrer_key.eq(&filler_key) {
+            Some(&mut ctx.accounts.filler)
+        } else {
+            referrer.as_deref_mut()
+        };
+        controller::orders::fill_order(
+            order_id,
+            &state,
+            &ctx.accounts.order_state,
+            &mut ctx.accounts.user,
+            referrer,
+            user_positions,
+            markets,
+            user_orders,
+            &filler_key,
+            &ctx.accounts.oracle,
+            trade_history,
+            order_history,
+            funding_payment_history,
+            &clock,
+        )?
+    };
+
+    // 推荐人账户通过remaining_accounts传入，需手动写回
+    if let Some(referrer) = referrer.as_ref() {
+        referrer.exit(ctx.program_id)?;
+    }
+
+    // 填充者奖励计入填充者的抵押品
+    let filler = &mut ctx.accounts.filler;
+    filler.collateral = filler
+        .collateral
+        .checked_add(filler_reward)
+        .ok_or_else(math_error!())?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct FillOrder<'info> {
+    // 填充者
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority
+    )]
+    pub filler: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        // 不能成交自己的订单
+        constraint = !user.key().eq(&filler.key()),
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        seeds = [b"user_orders", user.key().as_ref()],
+        bump,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = state.load()?.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = state.load()?.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    /// CHECK: checked in `fill_order`
+    pub oracle: UncheckedAccount<'info>,
+}
@@ -123,7 +123,8 @@ pub fn handle_initialize(ctx: Context<Initialize>, admin_controls_prices: bool)
         max_deposit: 0,
         extended_curve_history: default_pubkey,
         order_state: default_pubkey,
-        padding1: [0, 0, 0, 0],
+        bankruptcy_history: default_pubkey,
+        padding1: [0, 0],
     };
 
     Ok(())
@@ -0,0 +1,42 @@
+use crate::errors::Errors;
+use crate::state::*;
+use anchor_lang::prelude::*;
+use std::mem::size_of;
+
+// 创建由管理员设置价格的预言机账户，可作为OracleSource::Admin类型市场的预言机
+pub fn handle_initialize_admin_oracle(
+    ctx: Context<InitializeAdminOracle>,
+    // 价格（MARK_PRICE_PRECISION）
+    price: i128,
+    // 置信区间（MARK_PRICE_PRECISION）
+    confidence: u128,
+) -> Result<()> {
+    // 只有在管理员控制价格时才允许使用管理员设置的价格
+    if ctx.accounts.state.load()?.admin_controls_prices == 0 {
+        return err!(Errors::AdminControlsPricesDisabled);
+    }
+
+    let admin_oracle = &mut ctx.accounts.admin_oracle;
+    admin_oracle.price = price;
+    admin_oracle.confidence = confidence;
+    admin_oracle.last_update_slot = Clock::get()?.slot;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeAdminOracle<'info> {
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<AdminOracle>(),
+    )]
+    pub admin_oracle: Box<Account<'info, AdminOracle>>,
+    pub system_program: Program<'info, System>,
+}
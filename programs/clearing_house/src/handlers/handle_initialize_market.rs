@@ -16,7 +16,7 @@ pub fn handle_initialize_market(
     amm_periodicity: i64,
     // 锚定乘数，用于调整 AMM 价格与预言机价格的偏差
     amm_peg_multiplier: u128,
-    // 预言机类型（Pyth/Switchboard）
+    // 预言机类型（Pyth/Switchboard/Admin）
     oracle_source: OracleSource,
     // 初始保证金率（如 2000 = 20%），控制开仓最低抵押率
     margin_ratio_initial: u32,
@@ -52,25 +52,21 @@ pub fn handle_initialize_market(
         .checked_mul(U192::from(amm_quote_asset_reserve))
         .ok_or_else(math_error!())?;
 
+    // 校验oracle账户的owner program与oracle_source匹配，防止传入伪造的预言机账户
+    validate_oracle_owner(oracle_source, &ctx.accounts.oracle)?;
+
     // 根据flag oracle_source，从oracle中读取价格（已调整为市场标记价格精度）
+    let oracle_reader = load_oracle_reader(oracle_source, &ctx.accounts.oracle)?;
+    // 记录oracle账户的owner program和pyth的feed id，之后每次读取价格时都会校验
+    let oracle_program = *ctx.accounts.oracle.owner;
+    let pyth_feed_id = oracle_reader.get_feed_id();
     let OraclePriceData {
         price: oracle_price,
         ..
-    } = match oracle_source {
-        OracleSource::Pyth => market
-            .amm
-            .get_pyth_price(&ctx.accounts.oracle, clock_slot)
-            .unwrap(),
-        // todo: swtich board
-        OracleSource::Switchboard => todo!(),
-    };
+    } = oracle_reader.get_price_data(clock_slot)?;
 
-    // todo: get twap price
-    let last_oracle_twap_price = match oracle_source {
-        // todo: add pyth twap update in context
-        OracleSource::Pyth => market.amm.get_pyth_ema_price(&ctx.accounts.oracle)?,
-        OracleSource::Switchboard => todo!(),
-    };
+    // 预言机价格TWAP的初始值，之后在每次交易和资金费率更新时由math::amm::update_oracle_twap维护
+    let last_oracle_twap_price = oracle_reader.get_twap()?;
 
     // 检验初始保证金率、部分平仓保证金率和维持保证金率
     margin_validation::margin_validation(
@@ -106,6 +102,8 @@ pub fn handle_initialize_market(
             last_oracle_price_twap_ts: now,
             last_oracle_price_twap: last_oracle_twap_price,
             oracle: *ctx.accounts.oracle.key,
+            oracle_program,
+            pyth_feed_id,
             last_oracle_price: oracle_price,
             base_spread: 0,
             oracle_source,
@@ -24,6 +24,7 @@ pub fn handle_initialize_order_state(ctx: Context<InitializeOrderState>) -> Resu
             time_based_reward_lower_bound: 10_000, // 1 cent
         },
         min_order_quote_asset_amount: 500_000, // 50 cents
+        order_expiry_reward: 10_000,           // 1 cent
     };
 
     Ok(())
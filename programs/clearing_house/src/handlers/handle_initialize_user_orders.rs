@@ -0,0 +1,28 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+use std::mem::size_of;
+
+// 初始化用户的订单账户，下单前需要先初始化
+pub fn handle_initialize_user_orders(ctx: Context<InitializeUserOrders>) -> Result<()> {
+    ctx.accounts.user_orders.load_init()?.user = ctx.accounts.user.key();
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeUserOrders<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        has_one = authority
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + size_of::<UserOrders>(),
+        seeds = [b"user_orders", user.key().as_ref()],
+        bump
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    pub system_program: Program<'info, System>,
+}
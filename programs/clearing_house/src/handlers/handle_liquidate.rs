@@ -0,0 +1,366 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::cast::cast;
+use crate::math::collateral::calculate_equity;
+use crate::math::margin::{calculate_liquidation_status, LiquidationStatus, LiquidationType};
+use crate::math::oracle;
+use crate::math::position::{calculate_base_asset_value_and_pnl, direction_to_close_position};
+use crate::math_error;
+use crate::state::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+use std::cmp::max;
+
+// 清算保证金不足的用户：
+// 总抵押品低于维持保证金要求时，平掉全部仓位（完全清算）；
+// 总抵押品低于部分清算保证金要求时，按partial_liquidation_close_percentage平掉部分仓位（部分清算）。
+// 清算罚金从用户抵押品中扣除，一部分转给清算人，其余转入insurance_vault
+// 注：用户各持仓市场的预言机账户需通过remaining_accounts传入
+pub fn handle_liquidate(ctx: Context<Liquidate>) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let user = &mut ctx.accounts.user;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    // 先结算用户所有持仓的资金费
+    let markets = &mut ctx.accounts.markets.load_mut()?;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    // 资金费亏损超过抵押品时user.collateral会被截断为0，记录结算前的抵押品和结算的资金费，用于计算穿仓亏损
+    let collateral_before_funding = user.collateral;
+    let funding_payment = controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    // 各持仓市场的预言机价格需通过有效性检验；
+    // 若启用了use_for_liquidations，标记价格与预言机价格偏离过大时拒绝清算，防止操纵vAMM价格来清算用户
+    let mut oracle_prices = [0_i128; 5];
+    for (i, market_position) in user_positions.positions.iter().enumerate() {
+        if market_position.base_asset_amount == 0 {
+            continue;
+        }
+
+        let market = markets.get_market_mut(market_position.market_index);
+        let price_oracle = ctx
+            .remaining_accounts
+            .iter()
+            .find(|account_info| account_info.key.eq(&market.amm.oracle))
+            .ok_or(Errors::OracleNotFound)?;
+        let oracle_price_data = market.amm.get_oracle_price(price_oracle, clock_slot)?;
+        oracle::is_oracle_valid(
+            &market.amm,
+            &oracle_price_data,
+            &state.oracle_guard_rails.validity,
+        )?;
+
+        if state.oracle_guard_rails.use_for_liquidations == 1 {
+            let oracle_mark_spread_pct = oracle::calculate_oracle_mark_spread_pct(
+                &market.amm,
+                oracle_price_data.price,
+                None,
+            )?;
+            if oracle::is_oracle_mark_too_divergent(
+                oracle_mark_spread_pct,
+                &state.oracle_guard_rails.price_divergence,
+            )? {
+                return err!(Errors::OracleMarkSpreadLimit);
+            }
+        }
+
+        amm::update_oracle_twap(&mut market.amm, now, oracle_price_data.price)?;
+        oracle_prices[i] = oracle_price_data.price;
+    }
+
+    // 清算类型，以及清算前的总抵押品、仓位总价值、保证金率和未实现盈亏
+    let LiquidationStatus {
+        liquidation_type,
+        total_collateral,
+        base_asset_value,
+        margin_ratio,
+        unrealized_pnl,
+    } = calculate_liquidation_status(user, user_positions, markets)?;
+    if liquidation_type == LiquidationType::None {
+        return err!(Errors::SufficientCollateral);
+    }
+
+    let collateral = user.collateral;
+    let mut base_asset_value_closed: u128 = 0;
+    // 完全清算时各仓位平仓前的base资产数量、平仓的仓位价值、实现的亏损，以及所有仓位实现的盈亏之和
+    let mut base_asset_amounts_before = [0_i128; 5];
+    let mut quote_asset_amounts_closed = [0_u128; 5];
+    let mut realized_losses = [0_u128; 5];
+    let mut realized_pnl: i128 = 0;
+    let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+    for (i, market_position) in user_positions.positions.iter_mut().enumerate() {
+        if market_position.base_asset_amount == 0 {
+            continue;
+        }
+
+        let market_index = market_position.market_index;
+        let market = markets.get_market_mut(market_index);
+        let mark_price_before = market.amm.mark_price()?;
+        let direction = direction_to_close_position(market_position.base_asset_amount);
+
+        let (base_asset_amount, quote_asset_amount) = if liquidation_type == LiquidationType::Full {
+            // 平掉全部仓位
+            let (quote_asset_amount, base_asset_amount, pnl) =
+                controller::position::close(user, market, market_position, now)?;
+            base_asset_amounts_before[i] = base_asset_amount;
+            quote_asset_amounts_closed[i] = quote_asset_amount;
+            if pnl < 0 {
+                realized_losses[i] = pnl.unsigned_abs();
+            }
+            realized_pnl = realized_pnl.checked_add(pnl).ok_or_else(math_error!())?;
+            (base_asset_amount.unsigned_abs(), quote_asset_amount)
+        } else {
+            // 按仓位价值的partial_liquidation_close_percentage反向减仓
+            let (position_base_asset_value, _pnl) =
+                calculate_base_asset_value_and_pnl(market_position, &market.amm)?;
+            let quote_asset_amount = position_base_asset_value
+                .checked_mul(state.partial_liquidation_close_percentage_numerator)
+                .ok_or_else(math_error!())?
+                .checked_div(state.partial_liquidation_close_percentage_denominator)
+                .ok_or_else(math_error!())?;
+            controller::position::reduce_for_partial_liquidation(
+                quote_asset_amount,
+                user,
+                market,
+                market_position,
+                now,
+            )?
+        };
+
+        base_asset_value_closed = base_asset_value_closed
+            .checked_add(quote_asset_amount)
+            .ok_or_else(math_error!())?;
+
+        let mark_price_after = market.amm.mark_price()?;
+
+        // 增添清算产生的交易记录
+        let record_id = trade_history.next_record_id();
+        trade_history.append(TradeRecord {
+            ts: now,
+            market_index,
+            record_id,
+            user_authority: user.authority,
+            user: user.key(),
+            base_asset_amount,
+            quote_asset_amount,
+            mark_price_before,
+            mark_price_after,
+            fee: 0,
+            quote_asset_amount_surplus: 0,
+            referee_discount: 0,
+            token_discount: 0,
+            oracle_price: oracle_prices[i],
+            liquidation: 1,
+            direction,
+            padding: [0; 14],
+        });
+    }
+
+    // 完全清算后用户的权益为负（穿仓）时，亏损依次由insurance_vault、手续费池弥补，剩余部分分摊给对手方
+    if liquidation_type == LiquidationType::Full {
+        let equity = calculate_equity(
+            collateral_before_funding,
+            funding_payment
+                .checked_add(realized_pnl)
+                .ok_or_else(math_error!())?,
+        )?;
+        // 结算资金费和逐个平仓时抵押品可能先被截断为0，这里按资金费和全部仓位的盈亏之和修正用户的抵押品
+        user.collateral = cast(max(equity, 0))?;
+
+        if equity < 0 {
+            let bad_debt = equity.unsigned_abs();
+            let bankruptcy_history = &mut ctx.accounts.bankruptcy_history.load_mut()?;
+            let insurance_fund_payment = controller::bankruptcy::resolve_bankruptcy(
+                user.authority,
+                user.key(),
+                ctx.accounts.liquidator.key(),
+                markets,
+                &user_positions.positions,
+                &base_asset_amounts_before,
+                &quote_asset_amounts_closed,
+                &realized_losses,
+                bad_debt,
+                cast(ctx.accounts.insurance_vault.amount)?,
+                bankruptcy_history,
+                now,
+            )?;
+
+            // 从insurance_vault转入collateral_vault，由insurance_vault_authority签名
+            if insurance_fund_payment > 0 {
+                controller::token::send(
+                    &ctx.accounts.token_program,
+                    &ctx.accounts.insurance_vault,
+                    &ctx.accounts.collateral_vault,
+                    &ctx.accounts.insurance_vault_authority,
+                    state.insurance_vault_authority_nonce,
+                    cast(insurance_fund_payment)?,
+                )?;
+            }
+        }
+    }
+
+    // 计算清算罚金及清算人分得的份额
+    // 完全清算：罚金按平仓后用户剩余的抵押品计算；部分清算：罚金按清算前的总抵押品计算
+    let (liquidation_fee, liquidator_share_denominator) =
+        if liquidation_type == LiquidationType::Full {
+            (
+                user.collateral
+                    .checked_mul(state.full_liquidation_penalty_percentage_numerator)
+                    .ok_or_else(math_error!())?
+                    .checked_div(state.full_liquidation_penalty_percentage_denominator)
+                    .ok_or_else(math_error!())?,
+                state.full_liquidation_liquidator_share_denominator,
+            )
+        } else {
+            (
+                total_collateral
+                    .checked_mul(state.partial_liquidation_penalty_percentage_numberator)
+                    .ok_or_else(math_error!())?
+                    .checked_div(state.partial_liquidation_penalty_percentage_denominator)
+                    .ok_or_else(math_error!())?,
+                state.partial_liquidation_liquidator_share_denominator,
+            )
+        };
+    // 罚金不能超过用户的抵押品
+    let liquidation_fee = liquidation_fee.min(user.collateral);
+
+    let fee_to_liquidator = liquidation_fee
+        .checked_div(liquidator_share_denominator)
+        .ok_or_else(math_error!())?;
+    let fee_to_insurance_fund = liquidation_fee
+        .checked_sub(fee_to_liquidator)
+        .ok_or_else(math_error!())?;
+
+    user.collateral = user
+        .collateral
+        .checked_sub(liquidation_fee)
+        .ok_or_else(math_error!())?;
+
+    // 从collateral_vault分别转给清算人和insurance_vault，由collateral_vault_authority签名
+    let fee_to_liquidator: u64 = cast(fee_to_liquidator)?;
+    let fee_to_insurance_fund: u64 = cast(fee_to_insurance_fund)?;
+    if fee_to_liquidator > 0 {
+        controller::token::send(
+            &ctx.accounts.token_program,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.liquidator_account,
+            &ctx.accounts.collateral_vault_authority,
+            state.collateral_vault_authority_nonce,
+            fee_to_liquidator,
+        )?;
+    }
+    if fee_to_insurance_fund > 0 {
+        controller::token::send(
+            &ctx.accounts.token_program,
+            &ctx.accounts.collateral_vault,
+            &ctx.accounts.insurance_vault,
+            &ctx.accounts.collateral_vault_authority,
+            state.collateral_vault_authority_nonce,
+            fee_to_insurance_fund,
+        )?;
+    }
+
+    // 增添清算记录
+    let liquidation_history = &mut ctx.accounts.liquidation_history.load_mut()?;
+    let record_id = liquidation_history.next_record_id();
+    liquidation_history.append(LiquidationRecord {
+        record_id,
+        user_authority: user.authority,
+        user: user.key(),
+        liquidator: *ctx.accounts.liquidator.key,
+        base_asset_value,
+        base_asset_value_closed,
+        liquidation_fee,
+        fee_to_liquidator,
+        fee_to_insurance_fund,
+        total_collateral,
+        collateral,
+        unrealized_pnl,
+        margin_ratio,
+        ts: now,
+        partial: if liquidation_type == LiquidationType::Partial {
+            1
+        } else {
+            0
+        },
+        padding: [0; 7],
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct Liquidate<'info> {
+    pub liquidator: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = state.load()?.collateral_vault.eq(&collateral_vault.key())
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: checked by the constraint below
+    #[account(
+        constraint = state.load()?.collateral_vault_authority.eq(&collateral_vault_authority.key())
+    )]
+    pub collateral_vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = state.load()?.insurance_vault.eq(&insurance_vault.key())
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: checked by the constraint below
+    #[account(
+        constraint = state.load()?.insurance_vault_authority.eq(&insurance_vault_authority.key())
+    )]
+    pub insurance_vault_authority: UncheckedAccount<'info>,
+    // 接收清算人份额的token account
+    #[account(mut)]
+    pub liquidator_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        mut,
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = state.load()?.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.liquidation_history.eq(&liquidation_history.key())
+    )]
+    pub liquidation_history: AccountLoader<'info, LiquidationHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.bankruptcy_history.eq(&bankruptcy_history.key())
+    )]
+    pub bankruptcy_history: AccountLoader<'info, BankruptcyHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    pub token_program: Program<'info, Token>,
+}
@@ -0,0 +1,232 @@
+use crate::controller;
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+use crate::math::amm;
+use crate::math::cast::cast;
+use crate::math::fees::calculate_fee_tier;
+use crate::math::margin::meets_initial_margin;
+use crate::math::oracle;
+use crate::math::position::calculate_entry_price;
+use crate::optional_accounts::{get_discount_token, get_referrer, ManagePositionOptionalAccounts};
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+pub fn handle_open_position<'info>(
+    ctx: Context<'_, '_, 'info, 'info, OpenPosition<'info>>,
+    direction: PositionDirection,
+    // 本次交易的quote资产数量（QUOTE_PRECISION）
+    quote_asset_amount: u128,
+    market_index: u64,
+    // 可接受的最差成交均价（MARK_PRICE_PRECISION），为0时不做限制
+    limit_price: u128,
+    optional_accounts: ManagePositionOptionalAccounts,
+) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let user = &mut ctx.accounts.user;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    if quote_asset_amount == 0 {
+        return err!(Errors::TradeSizeTooSmall);
+    }
+
+    let markets = &mut ctx.accounts.markets.load_mut()?;
+    // 要求market_index对应的市场已经初始化
+    if Markets::index_from_u64(market_index) >= markets.markets.len()
+        || !markets.get_market(market_index).is_initialized()
+    {
+        return err!(Errors::MarketIndexNotInitialized);
+    }
+
+    // 要求传入的预言机账户为该市场的预言机
+    if !markets
+        .get_market(market_index)
+        .amm
+        .oracle
+        .eq(ctx.accounts.oracle.key)
+    {
+        return err!(Errors::InvalidOracle);
+    }
+
+    // 先结算用户所有持仓的资金费
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    // 找到用户在该市场的仓位，没有则占用一个新的仓位槽
+    let position_index =
+        match controller::position::get_position_index(user_positions, market_index) {
+            Ok(position_index) => position_index,
+            Err(_) => controller::position::add_new_position(user_positions, market_index)?,
+        };
+
+    let mark_price_before;
+    let mark_price_after;
+    let oracle_price;
+    // 本次交易是否可能增加用户的风险（加仓或反向开出更大的仓位）
+    let potentially_risk_increasing;
+    // 本次交易实际交换的base资产数量（绝对值）
+    let base_asset_amount;
+    {
+        let market = markets.get_market_mut(market_index);
+        let market_position = &mut user_positions.positions[position_index];
+        mark_price_before = market.amm.mark_price()?;
+
+        // 预言机价格需通过有效性检验，再用其更新预言机价格TWAP
+        let oracle_price_data = market
+            .amm
+            .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
+        oracle::is_oracle_valid(
+            &market.amm,
+            &oracle_price_data,
+            &state.oracle_guard_rails.validity,
+        )?;
+        oracle_price = oracle_price_data.price;
+        amm::update_oracle_twap(&mut market.amm, now, oracle_price)?;
+
+        (potentially_risk_increasing, base_asset_amount) =
+            controller::position::update_position_with_quote_asset_amount(
+                quote_asset_amount,
+                direction,
+                user,
+                market,
+                market_position,
+                now,
+            )?;
+
+        mark_price_after = market.amm.mark_price()?;
+
+        // 可能增加风险的交易不能使标记价格偏离预言机价格超过上限（已超过上限时，不能进一步扩大偏离）
+        if potentially_risk_increasing {
+            let spread_pct_before = oracle::calculate_oracle_mark_spread_pct(
+                &market.amm,
+                oracle_price,
+                Some(mark_price_before),
+            )?;
+            let spread_pct_after = oracle::calculate_oracle_mark_spread_pct(
+                &market.amm,
+                oracle_price,
+                Some(mark_price_after),
+            )?;
+            if spread_pct_after.unsigned_abs() > spread_pct_before.unsigned_abs()
+                && oracle::is_oracle_mark_too_divergent(
+                    spread_pct_after,
+                    &state.oracle_guard_rails.price_divergence,
+                )?
+            {
+                return err!(Errors::OracleMarkSpreadLimit);
+            }
+        }
+    }
+
+    // 成交均价不能劣于用户给定的限价
+    if limit_price != 0 {
+        let entry_price = calculate_entry_price(quote_asset_amount, base_asset_amount)?;
+        let price_within_limit = match direction {
+            PositionDirection::Long => entry_price <= limit_price,
+            PositionDirection::Short => entry_price >= limit_price,
+        };
+        if !price_within_limit {
+            return err!(Errors::SlippageOutsideLimit);
+        }
+    }
+
+    // 收取手续费：根据用户持有的discount_mint代币数量确定持币折扣等级，有推荐人时享受被推荐人折扣
+    let discount_token = get_discount_token(
+        optional_accounts,
+        ctx.remaining_accounts,
+        &state.discount_mint,
+        ctx.accounts.authority.key,
+    )?;
+    let mut referrer = get_referrer(optional_accounts, ctx.remaining_accounts, &user.referrer)?;
+    let discount_tier = calculate_fee_tier(
+        &state.fee_structure,
+        discount_token.map_or(0, |token| token.amount),
+    );
+    let (user_fee, _, token_discount, _, referee_discount, _) = controller::fees::charge_trade_fee(
+        user,
+        referrer.as_deref_mut(),
+        markets.get_market_mut(market_index),
+        &state.fee_structure,
+        quote_asset_amount,
+        discount_tier,
+        None,
+    )?;
+    if let Some(referrer) = referrer.as_ref() {
+        // 推荐人账户通过remaining_accounts传入，需手动写回
+        referrer.exit(ctx.program_id)?;
+    }
+
+    // 可能增加风险的交易完成后，用户仍需满足初始保证金要求
+    if potentially_risk_increasing && !meets_initial_margin(user, user_positions, markets)? {
+        return err!(Errors::InsufficientCollateral);
+    }
+
+    // 增添交易记录
+    let trade_history = &mut ctx.accounts.trade_history.load_mut()?;
+    let record_id = trade_history.next_record_id();
+    trade_history.append(TradeRecord {
+        ts: now,
+        market_index,
+        record_id,
+        user_authority: *ctx.accounts.authority.key,
+        user: user.key(),
+        base_asset_amount,
+        quote_asset_amount,
+        mark_price_before,
+        mark_price_after,
+        fee: cast(user_fee)?,
+        quote_asset_amount_surplus: 0,
+        referee_discount,
+        token_discount,
+        oracle_price,
+        liquidation: 0,
+        direction,
+        padding: [0; 14],
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct OpenPosition<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = state.load()?.trade_history.eq(&trade_history.key())
+    )]
+    pub trade_history: AccountLoader<'info, TradeHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    /// CHECK: checked in `open_position`
+    pub oracle: UncheckedAccount<'info>,
+}
@@ -0,0 +1,76 @@
+use crate::controller;
+use crate::optional_accounts::get_discount_token;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+pub fn handle_place_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let clock = Clock::get()?;
+
+    let discount_token = get_discount_token(
+        params.optional_accounts,
+        ctx.remaining_accounts,
+        &state.discount_mint,
+        ctx.accounts.authority.key,
+    )?;
+
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let markets = &ctx.accounts.markets.load()?;
+    let user_orders = &mut ctx.accounts.user_orders.load_mut()?;
+    let order_history = &mut ctx.accounts.order_history.load_mut()?;
+    controller::orders::place_order(
+        &state,
+        &ctx.accounts.order_state,
+        &ctx.accounts.user,
+        user_positions,
+        markets,
+        user_orders,
+        order_history,
+        discount_token.map_or(0, |token| token.amount),
+        &params,
+        &ctx.accounts.oracle,
+        &clock,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct PlaceOrder<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        has_one = authority,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        seeds = [b"user_orders", user.key().as_ref()],
+        bump,
+        has_one = user
+    )]
+    pub user_orders: AccountLoader<'info, UserOrders>,
+    #[account(
+        constraint = state.load()?.order_state.eq(&order_state.key())
+    )]
+    pub order_state: Box<Account<'info, OrderState>>,
+    #[account(
+        mut,
+        constraint = order_state.order_history.eq(&order_history.key())
+    )]
+    pub order_history: AccountLoader<'info, OrderHistory>,
+    /// CHECK: checked in `place_order`
+    pub oracle: UncheckedAccount<'info>,
+}
@@ -0,0 +1,48 @@
+use crate::controller;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 任何人都可以调用，用于结算user所有持仓的资金费
+pub fn handle_settle_funding_payment(ctx: Context<SettleFundingPayment>) -> Result<()> {
+    let now = Clock::get()?.unix_timestamp;
+
+    let user = &mut ctx.accounts.user;
+    let markets = &ctx.accounts.markets.load()?;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SettleFundingPayment<'info> {
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+}
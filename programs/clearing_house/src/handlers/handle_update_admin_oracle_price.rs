@@ -0,0 +1,34 @@
+use crate::errors::Errors;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 管理员更新预言机账户中的价格
+pub fn handle_update_admin_oracle_price(
+    ctx: Context<UpdateAdminOraclePrice>,
+    // 价格（MARK_PRICE_PRECISION）
+    price: i128,
+    // 置信区间（MARK_PRICE_PRECISION）
+    confidence: u128,
+) -> Result<()> {
+    if ctx.accounts.state.load()?.admin_controls_prices == 0 {
+        return err!(Errors::AdminControlsPricesDisabled);
+    }
+
+    let admin_oracle = &mut ctx.accounts.admin_oracle;
+    admin_oracle.price = price;
+    admin_oracle.confidence = confidence;
+    admin_oracle.last_update_slot = Clock::get()?.slot;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateAdminOraclePrice<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        has_one = admin
+    )]
+    pub state: AccountLoader<'info, State>,
+    #[account(mut)]
+    pub admin_oracle: Box<Account<'info, AdminOracle>>,
+}
@@ -0,0 +1,22 @@
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 管理员设置用于手续费持币折扣的代币mint地址
+// 注：设置为Pubkey::default()时关闭持币折扣
+pub fn handle_update_discount_mint(
+    ctx: Context<AdminUpdateState>,
+    discount_mint: Pubkey,
+) -> Result<()> {
+    ctx.accounts.state.load_mut()?.discount_mint = discount_mint;
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct AdminUpdateState<'info> {
+    pub admin: Signer<'info>,
+    #[account(
+        mut,
+        has_one = admin
+    )]
+    pub state: AccountLoader<'info, State>,
+}
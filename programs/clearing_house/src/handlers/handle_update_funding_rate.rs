@@ -0,0 +1,67 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math::oracle;
+use crate::state::*;
+use anchor_lang::prelude::*;
+
+// 任何人都可以调用，用于更新market_index对应市场的资金费率
+pub fn handle_update_funding_rate(
+    ctx: Context<UpdateFundingRate>,
+    market_index: u64,
+) -> Result<()> {
+    let state = ctx.accounts.state.load()?;
+    let clock = Clock::get()?;
+    let now = clock.unix_timestamp;
+    let clock_slot = clock.slot;
+
+    let markets = &mut ctx.accounts.markets.load_mut()?;
+    // 要求market_index对应的市场已经初始化
+    if Markets::index_from_u64(market_index) >= markets.markets.len()
+        || !markets.get_market(market_index).is_initialized()
+    {
+        return err!(Errors::MarketIndexNotInitialized);
+    }
+
+    let market = markets.get_market_mut(market_index);
+    // 要求传入的预言机账户为该市场的预言机
+    if !market.amm.oracle.eq(ctx.accounts.oracle.key) {
+        return err!(Errors::InvalidOracle);
+    }
+    let oracle_price_data = market
+        .amm
+        .get_oracle_price(&ctx.accounts.oracle, clock_slot)?;
+    oracle::is_oracle_valid(
+        &market.amm,
+        &oracle_price_data,
+        &state.oracle_guard_rails.validity,
+    )?;
+
+    let funding_rate_history = &mut ctx.accounts.funding_rate_history.load_mut()?;
+    controller::funding::update_funding_rate(
+        market_index,
+        market,
+        oracle_price_data.price,
+        now,
+        funding_rate_history,
+        state.funding_paused == 1,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct UpdateFundingRate<'info> {
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_rate_history.eq(&funding_rate_history.key())
+    )]
+    pub funding_rate_history: AccountLoader<'info, FundingRateHistory>,
+    /// CHECK: checked in `update_funding_rate`
+    pub oracle: UncheckedAccount<'info>,
+}
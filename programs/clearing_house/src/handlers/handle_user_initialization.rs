@@ -1,7 +1,11 @@
 use anchor_lang::prelude::*;
 use std::mem::size_of;
 
-use crate::{errors::Errors, optional_accounts::get_whitelist_token, state::*};
+use crate::{
+    errors::Errors,
+    optional_accounts::{get_referrer_for_initialize_user, get_whitelist_token},
+    state::*,
+};
 
 // 当state.whitelist_mint不为Pubkey::default()时，要求signer必须得持有该whitelist才可以初始化自己的User和UserPositions
 // 当state.whitelist_mint为Pubkey::default()时, 没有任何要求
@@ -10,7 +14,7 @@ pub fn initialize(
     user: &mut Box<Account<User>>,
     user_positions: &AccountLoader<UserPositions>,
     authority: &Signer,
-    // 只能有一个元素，该元素为signer的whitelist token account地址
+    // 依次为signer的whitelist token account地址（可选）和推荐人的User账户地址（可选）
     remaining_accounts: &[AccountInfo],
     optional_accounts: InitializeUserOptionalAccounts,
 ) -> Result<()> {
@@ -38,9 +42,14 @@ pub fn initialize(
         require_neq!(whitelist_token.amount, 0, Errors::WhitelistTokenNoBalance);
     }
 
+    // 登记推荐人：推荐人的User账户必须已经存在，且不能推荐自己
+    let referrer =
+        get_referrer_for_initialize_user(&optional_accounts, remaining_accounts, authority.key)?;
+
     // 初始化pda<User>
     user.authority = *authority.key;
     user.positons = user_positions.key();
+    user.referrer = referrer.unwrap_or_default();
 
     // 初始化account<UserPositions>
     user_positions.load_init()?.user = user.key();
@@ -51,6 +60,7 @@ pub fn initialize(
 #[derive(AnchorSerialize, AnchorDeserialize)]
 pub struct InitializeUserOptionalAccounts {
     pub whitelist_token: bool,
+    pub referrer: bool,
 }
 
 #[derive(Accounts)]
@@ -0,0 +1,162 @@
+use crate::controller;
+use crate::errors::Errors;
+use crate::math::cast::{cast, cast_to_u128};
+use crate::math::margin::calculate_free_collateral;
+use crate::math::withdrawal::calculate_withdrawal_amounts;
+use crate::math_error;
+use crate::state::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token::{Token, TokenAccount};
+
+pub fn handle_withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64) -> Result<()> {
+    // 提款数量不能为0
+    if amount == 0 {
+        return err!(Errors::InsufficientWithdrawal);
+    }
+
+    let state = ctx.accounts.state.load()?;
+    let user = &mut ctx.accounts.user;
+    let now = Clock::get()?.unix_timestamp;
+
+    let collateral_before = user.collateral;
+    let cumculative_deposits_before = user.cumculative_deposits;
+
+    // 先结算用户所有持仓的资金费
+    let markets = &ctx.accounts.markets.load()?;
+    let user_positions = &mut ctx.accounts.user_positions.load_mut()?;
+    let funding_payment_history = &mut ctx.accounts.funding_payment_history.load_mut()?;
+    controller::funding::settle_funding_payment(
+        user,
+        user_positions,
+        markets,
+        funding_payment_history,
+        now,
+    )?;
+
+    // 提款数量不能超过用户的抵押品
+    if cast_to_u128(amount)? > user.collateral {
+        return err!(Errors::InsufficientCollateral);
+    }
+
+    // 提款数量不能超过用户的可用抵押品（即提款后仍需满足各市场的初始保证金要求）
+    let free_collateral = calculate_free_collateral(user, user_positions, markets)?;
+    if cast_to_u128(amount)? > free_collateral {
+        return err!(Errors::InsufficientCollateral);
+    }
+
+    // collateral_vault余额不足时，由insurance_vault补足
+    let (collateral_vault_withdrawal, insurance_vault_withdrawal) = calculate_withdrawal_amounts(
+        amount,
+        &ctx.accounts.collateral_vault,
+        &ctx.accounts.insurance_vault,
+    )?;
+
+    // 当两个vault的余额都不足时，实际提款数量会小于amount
+    let amount_withdrawn = collateral_vault_withdrawal
+        .checked_add(insurance_vault_withdrawal)
+        .ok_or_else(math_error!())?;
+
+    user.collateral = user
+        .collateral
+        .checked_sub(cast(amount_withdrawn)?)
+        .ok_or_else(math_error!())?;
+    user.cumculative_deposits = user
+        .cumculative_deposits
+        .checked_sub(cast(amount_withdrawn)?)
+        .ok_or_else(math_error!())?;
+
+    // 从collateral_vault转出，由collateral_vault_authority签名
+    controller::token::send(
+        &ctx.accounts.token_program,
+        &ctx.accounts.collateral_vault,
+        &ctx.accounts.user_collateral_account,
+        &ctx.accounts.collateral_vault_authority,
+        state.collateral_vault_authority_nonce,
+        collateral_vault_withdrawal,
+    )?;
+
+    // 从insurance_vault转出差额，由insurance_vault_authority签名
+    if insurance_vault_withdrawal > 0 {
+        controller::token::send(
+            &ctx.accounts.token_program,
+            &ctx.accounts.insurance_vault,
+            &ctx.accounts.user_collateral_account,
+            &ctx.accounts.insurance_vault_authority,
+            state.insurance_vault_authority_nonce,
+            insurance_vault_withdrawal,
+        )?;
+    }
+
+    // 增添提款记录
+    let deposit_history = &mut ctx.accounts.deposit_history.load_mut()?;
+    let record_id = deposit_history.next_record_id();
+    deposit_history.append(DepositRecord {
+        ts: now,
+        amount: amount_withdrawn,
+        record_id,
+        user_authority: user.authority,
+        user: user.key(),
+        collateral_before,
+        cumulative_deposits_before: cumculative_deposits_before,
+        direction: DepositDirection::Withdraw,
+        padding: [0; 15],
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawCollateral<'info> {
+    pub authority: Signer<'info>,
+    pub state: AccountLoader<'info, State>,
+    #[account(
+        mut,
+        has_one = authority,
+        // 保证user与user_positions的一致性
+        constraint = user.positons.key().eq(&user_positions.key())
+    )]
+    pub user: Box<Account<'info, User>>,
+    #[account(
+        mut,
+        constraint = state.load()?.collateral_vault.eq(&collateral_vault.key())
+    )]
+    pub collateral_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: checked by the constraint below
+    #[account(
+        constraint = state.load()?.collateral_vault_authority.eq(&collateral_vault_authority.key())
+    )]
+    pub collateral_vault_authority: UncheckedAccount<'info>,
+    #[account(
+        mut,
+        constraint = state.load()?.insurance_vault.eq(&insurance_vault.key())
+    )]
+    pub insurance_vault: Box<Account<'info, TokenAccount>>,
+    /// CHECK: checked by the constraint below
+    #[account(
+        constraint = state.load()?.insurance_vault_authority.eq(&insurance_vault_authority.key())
+    )]
+    pub insurance_vault_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub user_collateral_account: Box<Account<'info, TokenAccount>>,
+    #[account(
+        constraint = state.load()?.markets.eq(&markets.key())
+    )]
+    pub markets: AccountLoader<'info, Markets>,
+    #[account(
+        mut,
+        // 保证user与user_positions的一致性
+        has_one = user
+    )]
+    pub user_positions: AccountLoader<'info, UserPositions>,
+    #[account(
+        mut,
+        constraint = state.load()?.funding_payment_history.eq(&funding_payment_history.key())
+    )]
+    pub funding_payment_history: AccountLoader<'info, FundingPaymentHistory>,
+    #[account(
+        mut,
+        constraint = state.load()?.deposit_history.eq(&deposit_history.key())
+    )]
+    pub deposit_history: AccountLoader<'info, DepositHistory>,
+    pub token_program: Program<'info, Token>,
+}
@@ -10,8 +10,50 @@ pub use handle_initialize_order_state::*;
 pub mod handle_initialize_market;
 pub use handle_initialize_market::*;
 
+pub mod handle_initialize_admin_oracle;
+pub use handle_initialize_admin_oracle::*;
+
+pub mod handle_update_admin_oracle_price;
+pub use handle_update_admin_oracle_price::*;
+
+pub mod handle_update_discount_mint;
+pub use handle_update_discount_mint::*;
+
 pub mod handle_user_initialization;
 pub use handle_user_initialization::*;
 
 pub mod handle_deposit_collateral;
 pub use handle_deposit_collateral::*;
+
+pub mod handle_withdraw_collateral;
+pub use handle_withdraw_collateral::*;
+
+pub mod handle_open_position;
+pub use handle_open_position::*;
+
+pub mod handle_close_position;
+pub use handle_close_position::*;
+
+pub mod handle_update_funding_rate;
+pub use handle_update_funding_rate::*;
+
+pub mod handle_settle_funding_payment;
+pub use handle_settle_funding_payment::*;
+
+pub mod handle_liquidate;
+pub use handle_liquidate::*;
+
+pub mod handle_initialize_user_orders;
+pub use handle_initialize_user_orders::*;
+
+pub mod handle_place_order;
+pub use handle_place_order::*;
+
+pub mod handle_cancel_order;
+pub use handle_cancel_order::*;
+
+pub mod handle_fill_order;
+pub use handle_fill_order::*;
+
+pub mod handle_expire_orders;
+pub use handle_expire_orders::*;
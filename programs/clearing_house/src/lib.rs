@@ -9,9 +9,11 @@ pub mod handlers;
 pub mod margin_validation;
 pub mod math;
 pub mod optional_accounts;
+pub mod order_validation;
 pub mod state;
 
 use controller::position::PositionDirection;
+use optional_accounts::ManagePositionOptionalAccounts;
 
 declare_id!("3LptehCCdJcnsG8DaFJKqCGLorUswXYmmkCTkrzTjh1D");
 
@@ -42,7 +44,7 @@ pub mod clearing_house {
         amm_periodicity: i64,
         // 锚定乘数，用于调整 AMM 价格与预言机价格的偏差
         amm_peg_multiplier: u128,
-        // 预言机类型（Pyth/Switchboard）
+        // 预言机类型（Pyth/Switchboard/Admin）
         oracle_source: OracleSource,
         // 初始保证金率（如 2000 = 20%），控制开仓最低抵押率
         margin_ratio_initial: u32,
@@ -65,6 +67,29 @@ pub mod clearing_house {
         )
     }
 
+    pub fn initialize_admin_oracle(
+        ctx: Context<InitializeAdminOracle>,
+        price: i128,
+        confidence: u128,
+    ) -> Result<()> {
+        handle_initialize_admin_oracle(ctx, price, confidence)
+    }
+
+    pub fn update_admin_oracle_price(
+        ctx: Context<UpdateAdminOraclePrice>,
+        price: i128,
+        confidence: u128,
+    ) -> Result<()> {
+        handle_update_admin_oracle_price(ctx, price, confidence)
+    }
+
+    pub fn update_discount_mint(
+        ctx: Context<AdminUpdateState>,
+        discount_mint: Pubkey,
+    ) -> Result<()> {
+        handle_update_discount_mint(ctx, discount_mint)
+    }
+
     pub fn initialize_user(
         ctx: Context<InitializeUser>,
         optional_accounts: handle_user_initialization::InitializeUserOptionalAccounts,
@@ -93,7 +118,106 @@ pub mod clearing_house {
         )
     }
 
+    pub fn initialize_user_orders(ctx: Context<InitializeUserOrders>) -> Result<()> {
+        handle_initialize_user_orders(ctx)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
     pub fn deposit_collateral(ctx: Context<DepositCollateral>, amount: u64) -> Result<()> {
         handle_deposit_collateral(ctx, amount)
     }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn withdraw_collateral(ctx: Context<WithdrawCollateral>, amount: u64) -> Result<()> {
+        handle_withdraw_collateral(ctx, amount)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn open_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, OpenPosition<'info>>,
+        direction: PositionDirection,
+        quote_asset_amount: u128,
+        market_index: u64,
+        limit_price: u128,
+        optional_accounts: ManagePositionOptionalAccounts,
+    ) -> Result<()> {
+        handle_open_position(
+            ctx,
+            direction,
+            quote_asset_amount,
+            market_index,
+            limit_price,
+            optional_accounts,
+        )
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn close_position<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ClosePosition<'info>>,
+        market_index: u64,
+        optional_accounts: ManagePositionOptionalAccounts,
+    ) -> Result<()> {
+        handle_close_position(ctx, market_index, optional_accounts)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn update_funding_rate(ctx: Context<UpdateFundingRate>, market_index: u64) -> Result<()> {
+        handle_update_funding_rate(ctx, market_index)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn settle_funding_payment(ctx: Context<SettleFundingPayment>) -> Result<()> {
+        handle_settle_funding_payment(ctx)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn place_order(ctx: Context<PlaceOrder>, params: OrderParams) -> Result<()> {
+        handle_place_order(ctx, params)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn cancel_order(ctx: Context<CancelOrder>, order_id: u128) -> Result<()> {
+        handle_cancel_order(ctx, order_id)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn cancel_order_by_user_id(ctx: Context<CancelOrder>, user_order_id: u8) -> Result<()> {
+        handle_cancel_order_by_user_id(ctx, user_order_id)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn cancel_all_orders(
+        ctx: Context<CancelOrder>,
+        market_index: Option<u64>,
+        direction: Option<PositionDirection>,
+    ) -> Result<()> {
+        handle_cancel_all_orders(ctx, market_index, direction)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn fill_order<'info>(
+        ctx: Context<'_, '_, 'info, 'info, FillOrder<'info>>,
+        order_id: u128,
+    ) -> Result<()> {
+        handle_fill_order(ctx, order_id)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn expire_orders(ctx: Context<ExpireOrders>) -> Result<()> {
+        handle_expire_orders(ctx)
+    }
+
+    #[access_control(exchange_not_paused(&ctx.accounts.state))]
+    pub fn liquidate(ctx: Context<Liquidate>) -> Result<()> {
+        handle_liquidate(ctx)
+    }
+}
+
+// 要求exchange未暂停
+fn exchange_not_paused(state: &AccountLoader<State>) -> Result<()> {
+    if state.load()?.exchange_paused == 1 {
+        return err!(errors::Errors::ExchangePaused);
+    }
+
+    Ok(())
 }
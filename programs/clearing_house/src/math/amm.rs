@@ -1,10 +1,17 @@
 use crate::math_error;
 use anchor_lang::prelude::*;
+use std::cmp::max;
 
+use crate::controller::amm::SwapDirection;
+use crate::errors::Errors;
 use crate::math::{
-    bn::{ClearingHouseResult, U192},
+    bn::{ClearingHouseResult, U192, U256},
+    cast::{cast_to_i128, cast_to_u128},
     constant::*,
+    position::swap_direction_to_close_position,
+    quote_asset::reserve_to_asset_amount,
 };
+use crate::state::{Market, AMM};
 
 // quote_asset_reserve/base_asset_reserve*(peg_multiplier/PEG_PRECISION) -> 最后提升到MARK_PRICE_PRECISION精度
 pub fn calculate_price(
@@ -27,9 +34,266 @@ pub fn calculate_price(
         .try_to_u128()
 }
 
+// 基于恒定乘积公式x*y=k，计算向AMM中加入（或移除）swap_amount数量的输入资产后，输出资产和输入资产的新储备量
+// 既可用于base资产的交换（输入base，输出quote），也可用于quote资产的交换（输入quote，输出base）
+// 返回值：(输出资产新储备量, 输入资产新储备量)
+// 注：输出资产新储备量k/new_input向上取整，使得无论交换方向如何，取整误差总是不利于交易者
+//  - Add：交易者换出的输出资产（output_reserve - new_output_reserve）更少
+//  - Remove：交易者需要换入的输出资产（new_output_reserve - output_reserve）更多
+pub fn calculate_swap_output(
+    // 输入资产的交换数量
+    swap_amount: u128,
+    // 输入资产当前储备量
+    input_asset_reserve: u128,
+    direction: SwapDirection,
+    // √k
+    invariant_sqrt: u128,
+) -> ClearingHouseResult<(u128, u128)> {
+    let invariant_sqrt_u256 = U256::from(invariant_sqrt);
+    let invariant = invariant_sqrt_u256
+        .checked_mul(invariant_sqrt_u256)
+        .ok_or_else(math_error!())?;
+
+    // 移除的数量不能超过当前储备量
+    if direction == SwapDirection::Remove && swap_amount >= input_asset_reserve {
+        return Err(Errors::TradeSizeTooLarge);
+    }
+
+    let new_input_asset_reserve = match direction {
+        SwapDirection::Add => input_asset_reserve.checked_add(swap_amount),
+        SwapDirection::Remove => input_asset_reserve.checked_sub(swap_amount),
+    }
+    .ok_or_else(math_error!())?;
+
+    // 输出资产新储备量 = ⌈k / 输入资产新储备量⌉
+    let new_input_asset_reserve_u256 = U256::from(new_input_asset_reserve);
+    let mut new_output_asset_reserve = invariant
+        .checked_div(new_input_asset_reserve_u256)
+        .ok_or_else(math_error!())?;
+    if !(invariant % new_input_asset_reserve_u256).is_zero() {
+        new_output_asset_reserve = new_output_asset_reserve
+            .checked_add(U256::one())
+            .ok_or_else(math_error!())?;
+    }
+
+    Ok((
+        new_output_asset_reserve.try_to_u128()?,
+        new_input_asset_reserve,
+    ))
+}
+
+// 根据交换前后quote资产的储备量，计算交换的quote资产数量（经过peg调整，QUOTE_PRECISION）
+// 注：取整总是不利于交易者
+//  - Add（向AMM卖出base资产）：交易者收到quote资产，向下取整
+//  - Remove（从AMM买入base资产）：交易者支付quote资产，向上取整
+pub fn calculate_quote_asset_amount_swapped(
+    quote_asset_reserve_before: u128,
+    quote_asset_reserve_after: u128,
+    // base资产的交换方向
+    swap_direction: SwapDirection,
+    peg_multiplier: u128,
+) -> ClearingHouseResult<u128> {
+    let quote_asset_reserve_change = match swap_direction {
+        // 向AMM中加入base资产，quote资产储备量减少
+        SwapDirection::Add => quote_asset_reserve_before.checked_sub(quote_asset_reserve_after),
+        // 从AMM中移除base资产，quote资产储备量增加
+        SwapDirection::Remove => quote_asset_reserve_after.checked_sub(quote_asset_reserve_before),
+    }
+    .ok_or_else(math_error!())?;
+
+    reserve_to_asset_amount(
+        quote_asset_reserve_change,
+        peg_multiplier,
+        swap_direction == SwapDirection::Remove,
+    )
+}
+
+// 计算base资产数量为base_asset_amount的仓位的价值，即平仓时可换得（或需支付）的quote资产数量
+pub fn calculate_base_asset_value(base_asset_amount: i128, amm: &AMM) -> ClearingHouseResult<u128> {
+    if base_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    let swap_direction = swap_direction_to_close_position(base_asset_amount);
+    let (new_quote_asset_reserve, _new_base_asset_reserve) = calculate_swap_output(
+        base_asset_amount.unsigned_abs(),
+        amm.base_asset_reserve,
+        swap_direction,
+        amm.sqrt_k,
+    )?;
+
+    calculate_quote_asset_amount_swapped(
+        amm.quote_asset_reserve,
+        new_quote_asset_reserve,
+        swap_direction,
+        amm.peg_multiplier,
+    )
+}
+
+// 计算终局价格：假设市场上所有用户的净仓位（market.base_asset_amount）都与AMM平仓后的标记价格
+pub fn calculate_terminal_price(market: &Market) -> ClearingHouseResult<u128> {
+    if market.base_asset_amount == 0 {
+        return market.amm.mark_price();
+    }
+
+    let swap_direction = swap_direction_to_close_position(market.base_asset_amount);
+    let (new_quote_asset_reserve, new_base_asset_reserve) = calculate_swap_output(
+        market.base_asset_amount.unsigned_abs(),
+        market.amm.base_asset_reserve,
+        swap_direction,
+        market.amm.sqrt_k,
+    )?;
+
+    calculate_price(
+        new_quote_asset_reserve,
+        new_base_asset_reserve,
+        market.amm.peg_multiplier,
+    )
+}
+
+// 以时间为权重，计算新数据与旧TWAP的加权平均：(new_data*new_weight + old_data*old_weight)/(new_weight + old_weight)
+pub fn calculate_twap(
+    new_data: i128,
+    old_data: i128,
+    new_weight: i128,
+    old_weight: i128,
+) -> ClearingHouseResult<i128> {
+    let denominator = new_weight
+        .checked_add(old_weight)
+        .ok_or_else(math_error!())?;
+    let weighted_old_data = old_data.checked_mul(old_weight).ok_or_else(math_error!())?;
+    let weighted_new_data = new_data.checked_mul(new_weight).ok_or_else(math_error!())?;
+
+    weighted_old_data
+        .checked_add(weighted_new_data)
+        .ok_or_else(math_error!())?
+        .checked_div(denominator)
+        .ok_or_else(math_error!())
+}
+
+// 计算TWAP中新数据和旧TWAP各自的时间权重，返回值：(新数据权重, 旧TWAP权重)
+// 新数据的权重为距上次更新经过的时间，旧TWAP的权重为资金费率周期中剩余的时间（至少为1），
+// 即距上次更新超过一个资金费率周期时，TWAP几乎完全由新数据决定；同一时间戳内的多次更新不改变TWAP
+fn calculate_twap_weights(
+    now: i64,
+    last_ts: i64,
+    funding_period: i64,
+) -> ClearingHouseResult<(i128, i128)> {
+    let since_last = cast_to_i128(max(0, now.checked_sub(last_ts).ok_or_else(math_error!())?))?;
+    let from_start = max(
+        1,
+        cast_to_i128(funding_period)?
+            .checked_sub(since_last)
+            .ok_or_else(math_error!())?,
+    );
+
+    Ok((since_last, from_start))
+}
+
+// 计算标记价格的新TWAP（不修改AMM）
+// precomputed_mark_price为上次更新以来AMM所处的标记价格，为None时使用AMM当前的标记价格
+pub fn calculate_new_mark_twap(
+    amm: &AMM,
+    now: i64,
+    precomputed_mark_price: Option<u128>,
+) -> ClearingHouseResult<u128> {
+    let mark_price = match precomputed_mark_price {
+        Some(mark_price) => mark_price,
+        None => amm.mark_price()?,
+    };
+    let (since_last, from_start) =
+        calculate_twap_weights(now, amm.last_mark_price_twap_ts, amm.funding_period)?;
+
+    cast_to_u128(calculate_twap(
+        cast_to_i128(mark_price)?,
+        cast_to_i128(amm.last_mark_price_twap)?,
+        since_last,
+        from_start,
+    )?)
+}
+
+// 更新AMM的标记价格TWAP及其时间戳，返回新的标记价格TWAP
+// 注：需在改变AMM储备量之前调用，使本次更新计入的是上次更新以来实际生效的标记价格
+pub fn update_mark_twap(
+    amm: &mut AMM,
+    now: i64,
+    precomputed_mark_price: Option<u128>,
+) -> ClearingHouseResult<u128> {
+    let mark_twap = calculate_new_mark_twap(amm, now, precomputed_mark_price)?;
+    amm.last_mark_price_twap = mark_twap;
+    amm.last_mark_price_twap_ts = max(now, amm.last_mark_price_twap_ts);
+
+    Ok(mark_twap)
+}
+
+// 计算预言机价格的新TWAP（不修改AMM），oracle_price为当前预言机价格（MARK_PRICE_PRECISION）
+pub fn calculate_new_oracle_twap(
+    amm: &AMM,
+    now: i64,
+    oracle_price: i128,
+) -> ClearingHouseResult<i128> {
+    let (since_last, from_start) =
+        calculate_twap_weights(now, amm.last_oracle_price_twap_ts, amm.funding_period)?;
+
+    calculate_twap(
+        oracle_price,
+        amm.last_oracle_price_twap,
+        since_last,
+        from_start,
+    )
+}
+
+// 更新AMM的预言机价格TWAP及其时间戳，同时记录最新的预言机价格，返回新的预言机价格TWAP
+pub fn update_oracle_twap(
+    amm: &mut AMM,
+    now: i64,
+    oracle_price: i128,
+) -> ClearingHouseResult<i128> {
+    let oracle_twap = calculate_new_oracle_twap(amm, now, oracle_price)?;
+    amm.last_oracle_price_twap = oracle_twap;
+    amm.last_oracle_price_twap_ts = max(now, amm.last_oracle_price_twap_ts);
+    amm.last_oracle_price = oracle_price;
+
+    Ok(oracle_twap)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::math::quote_asset::asset_to_reserve_amount;
+    use bytemuck::Zeroable;
+
+    fn amm(reserve: u128, peg_multiplier: u128) -> AMM {
+        AMM {
+            base_asset_reserve: reserve,
+            quote_asset_reserve: reserve,
+            sqrt_k: reserve,
+            peg_multiplier,
+            ..AMM::zeroed()
+        }
+    }
+
+    // 模拟用quote_asset_amount数量的quote资产与AMM交换，返回交易者获得的base资产数量（做多为正，做空为负）
+    fn swap_quote(amm: &mut AMM, quote_asset_amount: u128, direction: SwapDirection) -> i128 {
+        let quote_asset_reserve_amount = asset_to_reserve_amount(
+            quote_asset_amount,
+            amm.peg_multiplier,
+            direction == SwapDirection::Remove,
+        )
+        .unwrap();
+        let (new_base_asset_reserve, new_quote_asset_reserve) = calculate_swap_output(
+            quote_asset_reserve_amount,
+            amm.quote_asset_reserve,
+            direction,
+            amm.sqrt_k,
+        )
+        .unwrap();
+        let base_asset_amount = amm.base_asset_reserve as i128 - new_base_asset_reserve as i128;
+        amm.base_asset_reserve = new_base_asset_reserve;
+        amm.quote_asset_reserve = new_quote_asset_reserve;
+        base_asset_amount
+    }
+
     #[test]
     fn test_calculate_price() {
         assert_eq!(
@@ -37,4 +301,182 @@ mod tests {
             MARK_PRICE_PRECISION
         );
     }
+
+    #[test]
+    fn test_calculate_swap_output() {
+        // k = 100，整除时无取整误差
+        assert_eq!(
+            calculate_swap_output(10, 10, SwapDirection::Add, 10).unwrap(),
+            (5, 20)
+        );
+        assert_eq!(
+            calculate_swap_output(5, 10, SwapDirection::Remove, 10).unwrap(),
+            (20, 5)
+        );
+
+        // 100 / 13 = 7.69，输出资产新储备量向上取整为8，交易者只能换出2而非3
+        assert_eq!(
+            calculate_swap_output(3, 10, SwapDirection::Add, 10).unwrap(),
+            (8, 13)
+        );
+        // 100 / 7 = 14.29，输出资产新储备量向上取整为15，交易者需要换入5而非4
+        assert_eq!(
+            calculate_swap_output(3, 10, SwapDirection::Remove, 10).unwrap(),
+            (15, 7)
+        );
+
+        // 移除的数量不能达到或超过当前储备量
+        assert!(calculate_swap_output(10, 10, SwapDirection::Remove, 10).is_err());
+        assert!(calculate_swap_output(11, 10, SwapDirection::Remove, 10).is_err());
+    }
+
+    #[test]
+    fn test_calculate_quote_asset_amount_swapped() {
+        let reserve = 1000 * AMM_RESERVE_PRECISION;
+        // 储备量变化不足1个最小quote单位时：卖出base（Add）收到的quote向下取整，买入base（Remove）支付的quote向上取整
+        assert_eq!(
+            calculate_quote_asset_amount_swapped(
+                reserve,
+                reserve - 1,
+                SwapDirection::Add,
+                PEG_PRECISION
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            calculate_quote_asset_amount_swapped(
+                reserve,
+                reserve + 1,
+                SwapDirection::Remove,
+                PEG_PRECISION
+            )
+            .unwrap(),
+            1
+        );
+        // peg为50时，1单位储备量对应50单位quote资产
+        assert_eq!(
+            calculate_quote_asset_amount_swapped(
+                reserve,
+                reserve - AMM_RESERVE_PRECISION,
+                SwapDirection::Add,
+                50 * PEG_PRECISION
+            )
+            .unwrap(),
+            50 * QUOTE_PRECISION
+        );
+    }
+
+    #[test]
+    fn test_round_trip_rounds_against_trader() {
+        let reserve = 1_000_000 * AMM_RESERVE_PRECISION;
+        for peg_multiplier in [PEG_PRECISION, 3 * PEG_PRECISION, 53_123] {
+            for quote_asset_amount in [1_000_001, 123_456_789, 10_000 * QUOTE_PRECISION + 7] {
+                // 做多后立即平仓，平仓可获得的quote资产数量不超过开仓花费
+                let mut long_amm = amm(reserve, peg_multiplier);
+                let base_asset_amount =
+                    swap_quote(&mut long_amm, quote_asset_amount, SwapDirection::Add);
+                assert!(base_asset_amount > 0);
+                assert!(
+                    calculate_base_asset_value(base_asset_amount, &long_amm).unwrap()
+                        <= quote_asset_amount
+                );
+
+                // 做空后立即平仓，平仓需要支付的quote资产数量不少于开仓所得
+                let mut short_amm = amm(reserve, peg_multiplier);
+                let base_asset_amount =
+                    swap_quote(&mut short_amm, quote_asset_amount, SwapDirection::Remove);
+                assert!(base_asset_amount < 0);
+                assert!(
+                    calculate_base_asset_value(base_asset_amount, &short_amm).unwrap()
+                        >= quote_asset_amount
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_terminal_price() {
+        let mut market = Market::zeroed();
+        market.amm = amm(1_000_000 * AMM_RESERVE_PRECISION, 50 * PEG_PRECISION);
+        let initial_price = market.amm.mark_price().unwrap();
+        assert_eq!(initial_price, 50 * MARK_PRICE_PRECISION);
+
+        // 无净仓位时，终局价格即标记价格
+        assert_eq!(calculate_terminal_price(&market).unwrap(), initial_price);
+
+        // 净多头仓位使标记价格上升，但终局价格回到初始价格附近
+        market.base_asset_amount = swap_quote(
+            &mut market.amm,
+            10_000 * QUOTE_PRECISION,
+            SwapDirection::Add,
+        );
+        assert!(market.amm.mark_price().unwrap() > initial_price);
+        let terminal_price = calculate_terminal_price(&market).unwrap();
+        assert!(terminal_price.abs_diff(initial_price) <= 1);
+    }
+
+    #[test]
+    fn test_calculate_twap() {
+        // 新旧数据按时间加权
+        assert_eq!(calculate_twap(200, 100, 1, 3).unwrap(), 125);
+        assert_eq!(calculate_twap(-200, 100, 1, 1).unwrap(), -50);
+        // 新数据权重为0时TWAP不变
+        assert_eq!(calculate_twap(200, 100, 0, 3600).unwrap(), 100);
+    }
+
+    #[test]
+    fn test_update_mark_twap() {
+        let mut amm = amm(1_000_000 * AMM_RESERVE_PRECISION, 50 * PEG_PRECISION);
+        amm.funding_period = 3600;
+        amm.last_mark_price_twap = 40 * MARK_PRICE_PRECISION;
+        amm.last_mark_price_twap_ts = 0;
+
+        // 同一时间戳内的更新不改变TWAP
+        assert_eq!(
+            update_mark_twap(&mut amm, 0, None).unwrap(),
+            40 * MARK_PRICE_PRECISION
+        );
+
+        // 经过1/4个周期：(50*900 + 40*2700)/3600 = 42.5
+        assert_eq!(
+            update_mark_twap(&mut amm, 900, None).unwrap(),
+            425 * MARK_PRICE_PRECISION / 10
+        );
+        assert_eq!(amm.last_mark_price_twap_ts, 900);
+
+        // 使用预先计算的标记价格
+        assert_eq!(
+            calculate_new_mark_twap(&amm, 900 + 1800, Some(60 * MARK_PRICE_PRECISION)).unwrap(),
+            (60 * 1800 + 425 * 1800 / 10) * MARK_PRICE_PRECISION / 3600
+        );
+
+        // 超过一个周期未更新时，TWAP几乎完全由当前标记价格决定
+        let mark_twap = update_mark_twap(&mut amm, 900 + 10 * 3600, None).unwrap();
+        assert!(mark_twap.abs_diff(50 * MARK_PRICE_PRECISION) < MARK_PRICE_PRECISION / 1000);
+    }
+
+    #[test]
+    fn test_update_oracle_twap() {
+        let mut amm = amm(1_000_000 * AMM_RESERVE_PRECISION, 50 * PEG_PRECISION);
+        amm.funding_period = 3600;
+        amm.last_oracle_price_twap = 40 * MARK_PRICE_PRECISION as i128;
+        amm.last_oracle_price_twap_ts = 100;
+
+        let oracle_price = 48 * MARK_PRICE_PRECISION as i128;
+        // 经过半个周期：(48 + 40)/2 = 44
+        assert_eq!(
+            update_oracle_twap(&mut amm, 100 + 1800, oracle_price).unwrap(),
+            44 * MARK_PRICE_PRECISION as i128
+        );
+        assert_eq!(amm.last_oracle_price_twap_ts, 1900);
+        assert_eq!(amm.last_oracle_price, oracle_price);
+
+        // 时间戳回退时，TWAP和时间戳都不变
+        assert_eq!(
+            update_oracle_twap(&mut amm, 1000, oracle_price).unwrap(),
+            44 * MARK_PRICE_PRECISION as i128
+        );
+        assert_eq!(amm.last_oracle_price_twap_ts, 1900);
+    }
 }
@@ -0,0 +1,50 @@
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::{cast_to_i128, cast_to_u128};
+use crate::math_error;
+use anchor_lang::prelude::*;
+
+// 将pnl（可正可负）计入抵押品，返回更新后的抵押品数量
+// 注：当亏损大于现有抵押品时，抵押品归零（不会出现负数）
+pub fn calculate_updated_collateral(collateral: u128, pnl: i128) -> ClearingHouseResult<u128> {
+    Ok(if pnl.is_negative() && pnl.unsigned_abs() > collateral {
+        0
+    } else if pnl > 0 {
+        collateral
+            .checked_add(cast_to_u128(pnl)?)
+            .ok_or_else(math_error!())?
+    } else {
+        collateral
+            .checked_sub(pnl.unsigned_abs())
+            .ok_or_else(math_error!())?
+    })
+}
+
+// 将pnl（可正可负）计入抵押品，返回用户的权益
+// 注：与calculate_updated_collateral不同，亏损大于抵押品时返回负数（穿仓）
+pub fn calculate_equity(collateral: u128, pnl: i128) -> ClearingHouseResult<i128> {
+    cast_to_i128(collateral)?
+        .checked_add(pnl)
+        .ok_or_else(math_error!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calculate_updated_collateral() {
+        assert_eq!(calculate_updated_collateral(100, 50).unwrap(), 150);
+        assert_eq!(calculate_updated_collateral(100, -50).unwrap(), 50);
+        assert_eq!(calculate_updated_collateral(100, -100).unwrap(), 0);
+        // 亏损超过抵押品时，抵押品归零
+        assert_eq!(calculate_updated_collateral(100, -101).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_calculate_equity() {
+        assert_eq!(calculate_equity(100, 50).unwrap(), 150);
+        assert_eq!(calculate_equity(100, -100).unwrap(), 0);
+        // 亏损超过抵押品时，权益为负
+        assert_eq!(calculate_equity(100, -101).unwrap(), -1);
+    }
+}
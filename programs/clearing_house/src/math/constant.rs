@@ -20,6 +20,7 @@ pub const DEFAULT_REFEREE_DISCOUNT_DENOMINATOR: u128 = 100;
 
 // 精度
 pub const MARK_PRICE_PRECISION: u128 = 10_000_000_000; // 市场标记价格的精度，expo = -10
+pub const MARK_PRICE_PRECISION_EXPO: i32 = -10; // 市场标记价格精度对应的expo
 pub const PEG_PRECISION: u128 = 1_000; // 锚定价格（如稳定币挂钩）的精度，expo = -3
 pub const AMM_RESERVE_PRECISION: u128 = 10_000_000_000_000; // AMM资金池中代币储备量的精度，expo = -13
 pub const QUOTE_PRECISION: u128 = 1_000_000; // quote资产（如USDC）的精度，expo = -6
@@ -31,6 +32,16 @@ pub const BID_ASK_SPREAD_PRECISION: u128 = 1_000_000; // 订单簿买卖价差
 
 // 精度转换
 pub const PRICE_TO_PEG_PRECISION_RATIO: u128 = MARK_PRICE_PRECISION / PEG_PRECISION;
+pub const AMM_TO_QUOTE_PRECISION_RATIO: u128 = AMM_RESERVE_PRECISION / QUOTE_PRECISION; // 10^7
+pub const AMM_TO_QUOTE_PRECISION_RATIO_I128: i128 = AMM_TO_QUOTE_PRECISION_RATIO as i128; // 10^7
+pub const AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO: u128 =
+    AMM_RESERVE_PRECISION * PEG_PRECISION / QUOTE_PRECISION; // 10^10
+pub const MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO: u128 =
+    MARK_PRICE_PRECISION * AMM_TO_QUOTE_PRECISION_RATIO; // 10^17
+
+// 时间相关
+pub const ONE_HOUR: i64 = 60 * 60;
+pub const ONE_DAY: i64 = 24 * ONE_HOUR;
 
 // 保证金相关
 pub const MINIMUM_MARGIN_RATIO: u32 = MARGIN_PRECISION as u32 / 50; // 最小保证金率
@@ -0,0 +1,304 @@
+use crate::math::bn::ClearingHouseResult;
+use crate::math_error;
+use crate::state::{
+    DiscountTokenTier, FeeStructure, OrderDiscountTier, OrderFillerRewardStructure,
+};
+use anchor_lang::prelude::*;
+use std::cmp::{max, min};
+
+// 计算一笔交易的手续费，均为QUOTE_PRECISION
+// 基础手续费 = quote资产数量 * fee_numerator / fee_denominator
+// 用户实付手续费 = 基础手续费 - 持币折扣 - 被推荐人折扣
+// 计入市场的手续费 = 用户实付手续费 - 推荐人奖励
+// 返回值：(用户实付手续费, 计入市场的手续费, 持币折扣, 推荐人奖励, 被推荐人折扣)
+pub fn calculate_fee_for_trade(
+    quote_asset_amount: u128,
+    fee_structure: &FeeStructure,
+    // 用户持有discount_mint代币所对应的折扣等级
+    discount_tier: OrderDiscountTier,
+    // 用户是否有推荐人
+    has_referrer: bool,
+) -> ClearingHouseResult<(u128, u128, u128, u128, u128)> {
+    let fee = quote_asset_amount
+        .checked_mul(fee_structure.fee_numerator)
+        .ok_or_else(math_error!())?
+        .checked_div(fee_structure.fee_denominator)
+        .ok_or_else(math_error!())?;
+
+    let token_discount = calculate_token_discount(fee, fee_structure, discount_tier)?;
+
+    let (referrer_reward, referee_discount) = if has_referrer {
+        calculate_referrer_reward_and_referee_discount(fee, fee_structure)?
+    } else {
+        (0, 0)
+    };
+
+    let user_fee = fee
+        .checked_sub(token_discount)
+        .ok_or_else(math_error!())?
+        .checked_sub(referee_discount)
+        .ok_or_else(math_error!())?;
+
+    let fee_to_market = user_fee
+        .checked_sub(referrer_reward)
+        .ok_or_else(math_error!())?;
+
+    Ok((
+        user_fee,
+        fee_to_market,
+        token_discount,
+        referrer_reward,
+        referee_discount,
+    ))
+}
+
+// 根据用户持有的discount_mint代币数量，从高到低匹配满足最低持币量要求的折扣等级
+pub fn calculate_fee_tier(
+    fee_structure: &FeeStructure,
+    discount_token_balance: u64,
+) -> OrderDiscountTier {
+    let tiers = &fee_structure.discount_token_tiers;
+    if discount_token_balance == 0 {
+        OrderDiscountTier::None
+    } else if discount_token_balance >= tiers.first_tier.minimun_balance {
+        OrderDiscountTier::First
+    } else if discount_token_balance >= tiers.second_tier.minimun_balance {
+        OrderDiscountTier::Second
+    } else if discount_token_balance >= tiers.third_tier.minimun_balance {
+        OrderDiscountTier::Third
+    } else if discount_token_balance >= tiers.fourth_tier.minimun_balance {
+        OrderDiscountTier::Fourth
+    } else {
+        OrderDiscountTier::None
+    }
+}
+
+// 计算订单成交时填充者的奖励（QUOTE_PRECISION），从计入市场的手续费中支付
+// 奖励 = 计入市场的手续费 * reward_numerator / reward_denominator，且不低于time_based_reward_lower_bound
+// 注：奖励不超过计入市场的手续费
+pub fn calculate_filler_reward(
+    fee_to_market: u128,
+    filler_reward_structure: &OrderFillerRewardStructure,
+) -> ClearingHouseResult<u128> {
+    let size_based_reward = fee_to_market
+        .checked_mul(filler_reward_structure.reward_numerator)
+        .ok_or_else(math_error!())?
+        .checked_div(filler_reward_structure.reward_denominator)
+        .ok_or_else(math_error!())?;
+
+    let filler_reward = max(
+        size_based_reward,
+        filler_reward_structure.time_based_reward_lower_bound,
+    );
+
+    Ok(min(filler_reward, fee_to_market))
+}
+
+// 获得折扣等级对应的DiscountTokenTier
+fn get_discount_token_tier(
+    fee_structure: &FeeStructure,
+    discount_tier: OrderDiscountTier,
+) -> Option<&DiscountTokenTier> {
+    let tiers = &fee_structure.discount_token_tiers;
+    match discount_tier {
+        OrderDiscountTier::None => None,
+        OrderDiscountTier::First => Some(&tiers.first_tier),
+        OrderDiscountTier::Second => Some(&tiers.second_tier),
+        OrderDiscountTier::Third => Some(&tiers.third_tier),
+        OrderDiscountTier::Fourth => Some(&tiers.fourth_tier),
+    }
+}
+
+// 持币折扣 = 基础手续费 * 该等级的discount_numerator / discount_denominator
+fn calculate_token_discount(
+    fee: u128,
+    fee_structure: &FeeStructure,
+    discount_tier: OrderDiscountTier,
+) -> ClearingHouseResult<u128> {
+    match get_discount_token_tier(fee_structure, discount_tier) {
+        Some(tier) => fee
+            .checked_mul(tier.discount_numerator)
+            .ok_or_else(math_error!())?
+            .checked_div(tier.discount_denominator)
+            .ok_or_else(math_error!()),
+        None => Ok(0),
+    }
+}
+
+// 推荐人奖励和被推荐人折扣均按基础手续费的比例计算
+fn calculate_referrer_reward_and_referee_discount(
+    fee: u128,
+    fee_structure: &FeeStructure,
+) -> ClearingHouseResult<(u128, u128)> {
+    let referral_discount = &fee_structure.referral_discount;
+    let referrer_reward = fee
+        .checked_mul(referral_discount.referral_reward_numerator)
+        .ok_or_else(math_error!())?
+        .checked_div(referral_discount.referral_reward_denominator)
+        .ok_or_else(math_error!())?;
+
+    let referee_discount = fee
+        .checked_mul(referral_discount.referee_discount_numerator)
+        .ok_or_else(math_error!())?
+        .checked_div(referral_discount.referee_discount_denominator)
+        .ok_or_else(math_error!())?;
+
+    Ok((referrer_reward, referee_discount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::*;
+    use crate::state::{DiscountTokenTiers, ReferralDiscount};
+
+    fn default_fee_structure() -> FeeStructure {
+        let tier = |minimun_balance, discount_numerator, discount_denominator| DiscountTokenTier {
+            discount_numerator,
+            discount_denominator,
+            minimun_balance,
+            padding: [0; 8],
+        };
+        FeeStructure {
+            fee_numerator: DEFAULT_FEE_NUMERATOR,
+            fee_denominator: DEFAULT_FEE_DENOMINATOR,
+            discount_token_tiers: DiscountTokenTiers {
+                first_tier: tier(
+                    DEFAULT_DISCOUNT_TOKEN_FIRST_TIER_MINIMUM_BALANCE,
+                    DEFAULT_DISCOUNT_TOKEN_FIRST_TIER_DISCOUNT_NUMERATOR,
+                    DEFAULT_DISCOUNT_TOKEN_FIRST_TIER_DISCOUNT_DENOMINATOR,
+                ),
+                second_tier: tier(
+                    DEFAULT_DISCOUNT_TOKEN_SECOND_TIER_MINIMUM_BALANCE,
+                    DEFAULT_DISCOUNT_TOKEN_SECOND_TIER_DISCOUNT_NUMERATOR,
+                    DEFAULT_DISCOUNT_TOKEN_SECOND_TIER_DISCOUNT_DENOMINATOR,
+                ),
+                third_tier: tier(
+                    DEFAULT_DISCOUNT_TOKEN_THIRD_TIER_MINIMUM_BALANCE,
+                    DEFAULT_DISCOUNT_TOKEN_THIRD_TIER_DISCOUNT_NUMERATOR,
+                    DEFAULT_DISCOUNT_TOKEN_THIRD_TIER_DISCOUNT_DENOMINATOR,
+                ),
+                fourth_tier: tier(
+                    DEFAULT_DISCOUNT_TOKEN_FOURTH_TIER_MINIMUM_BALANCE,
+                    DEFAULT_DISCOUNT_TOKEN_FOURTH_TIER_DISCOUNT_NUMERATOR,
+                    DEFAULT_DISCOUNT_TOKEN_FOURTH_TIER_DISCOUNT_DENOMINATOR,
+                ),
+            },
+            referral_discount: ReferralDiscount {
+                referral_reward_numerator: DEFAULT_REFERRER_REWARD_NUMERATOR,
+                referral_reward_denominator: DEFAULT_REFERRER_REWARD_DENOMINATOR,
+                referee_discount_numerator: DEFAULT_REFEREE_DISCOUNT_NUMERATOR,
+                referee_discount_denominator: DEFAULT_REFEREE_DISCOUNT_DENOMINATOR,
+            },
+        }
+    }
+
+    #[test]
+    fn test_calculate_fee_tier() {
+        let fee_structure = default_fee_structure();
+        let tier = |balance| calculate_fee_tier(&fee_structure, balance);
+
+        assert!(matches!(tier(0), OrderDiscountTier::None));
+        assert!(matches!(
+            tier(DEFAULT_DISCOUNT_TOKEN_FOURTH_TIER_MINIMUM_BALANCE - 1),
+            OrderDiscountTier::None
+        ));
+        assert!(matches!(
+            tier(DEFAULT_DISCOUNT_TOKEN_FOURTH_TIER_MINIMUM_BALANCE),
+            OrderDiscountTier::Fourth
+        ));
+        assert!(matches!(
+            tier(DEFAULT_DISCOUNT_TOKEN_THIRD_TIER_MINIMUM_BALANCE),
+            OrderDiscountTier::Third
+        ));
+        assert!(matches!(
+            tier(DEFAULT_DISCOUNT_TOKEN_SECOND_TIER_MINIMUM_BALANCE),
+            OrderDiscountTier::Second
+        ));
+        assert!(matches!(
+            tier(DEFAULT_DISCOUNT_TOKEN_FIRST_TIER_MINIMUM_BALANCE * 2),
+            OrderDiscountTier::First
+        ));
+    }
+
+    #[test]
+    fn test_calculate_fee_for_trade() {
+        let fee_structure = default_fee_structure();
+        // 10000 USDC的交易，基础手续费为10 USDC
+        let quote_asset_amount = 10_000 * QUOTE_PRECISION;
+
+        // 无折扣、无推荐人
+        assert_eq!(
+            calculate_fee_for_trade(
+                quote_asset_amount,
+                &fee_structure,
+                OrderDiscountTier::None,
+                false
+            )
+            .unwrap(),
+            (10 * QUOTE_PRECISION, 10 * QUOTE_PRECISION, 0, 0, 0)
+        );
+
+        // 1级持币折扣（20%）
+        assert_eq!(
+            calculate_fee_for_trade(
+                quote_asset_amount,
+                &fee_structure,
+                OrderDiscountTier::First,
+                false
+            )
+            .unwrap(),
+            (
+                8 * QUOTE_PRECISION,
+                8 * QUOTE_PRECISION,
+                2 * QUOTE_PRECISION,
+                0,
+                0
+            )
+        );
+
+        // 4级持币折扣（5%）+ 推荐人：推荐人奖励5%，被推荐人折扣5%
+        let half_quote = QUOTE_PRECISION / 2;
+        assert_eq!(
+            calculate_fee_for_trade(
+                quote_asset_amount,
+                &fee_structure,
+                OrderDiscountTier::Fourth,
+                true
+            )
+            .unwrap(),
+            (
+                9 * QUOTE_PRECISION,
+                9 * QUOTE_PRECISION - half_quote,
+                half_quote,
+                half_quote,
+                half_quote
+            )
+        );
+    }
+
+    #[test]
+    fn test_calculate_filler_reward() {
+        let filler_reward_structure = OrderFillerRewardStructure {
+            reward_numerator: 1,
+            reward_denominator: 10,
+            time_based_reward_lower_bound: 10_000,
+        };
+
+        // 按手续费的10%计算
+        assert_eq!(
+            calculate_filler_reward(10 * QUOTE_PRECISION, &filler_reward_structure).unwrap(),
+            QUOTE_PRECISION
+        );
+        // 不低于0.01 USDC
+        assert_eq!(
+            calculate_filler_reward(50_000, &filler_reward_structure).unwrap(),
+            10_000
+        );
+        // 不超过手续费
+        assert_eq!(
+            calculate_filler_reward(5_000, &filler_reward_structure).unwrap(),
+            5_000
+        );
+    }
+}
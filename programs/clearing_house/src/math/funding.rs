@@ -0,0 +1,241 @@
+use crate::math::bn::{ClearingHouseResult, U192};
+use crate::math::cast::{cast, cast_to_i128};
+use crate::math::constant::{
+    AMM_TO_QUOTE_PRECISION_RATIO, FUNDING_PAYMENT_PRECISION, MARK_PRICE_PRECISION, ONE_DAY,
+    ONE_HOUR,
+};
+use crate::math_error;
+use crate::state::{MarketPosition, AMM};
+use anchor_lang::prelude::*;
+use std::cmp::max;
+
+// 计算一个资金费率周期的资金费率（精度为MARK_PRICE_PRECISION*FUNDING_PAYMENT_PRECISION）
+// 资金费率 = (标记价格TWAP - 预言机价格TWAP) / (一天内的资金费率周期数)
+// 即每天累计的资金费率等于标记价格与预言机价格的溢价，资金费率周期越短，每个周期的资金费率越低
+// 注：资金费率为正时多头向空头支付，为负时空头向多头支付
+pub fn calculate_funding_rate(
+    mark_price_twap: u128,
+    oracle_price_twap: i128,
+    funding_period: i64,
+) -> ClearingHouseResult<i128> {
+    // 一天内的资金费率周期数（资金费率周期最短按1小时计）
+    let period_adjustment = ONE_DAY
+        .checked_div(max(ONE_HOUR, funding_period))
+        .ok_or_else(math_error!())?;
+
+    let price_spread = cast_to_i128(mark_price_twap)?
+        .checked_sub(oracle_price_twap)
+        .ok_or_else(math_error!())?;
+
+    price_spread
+        .checked_mul(cast(FUNDING_PAYMENT_PRECISION)?)
+        .ok_or_else(math_error!())?
+        .checked_div(cast(max(1, period_adjustment))?)
+        .ok_or_else(math_error!())
+}
+
+// 计算某个仓位自上次结算以来应收/应付的资金费（精度为AMM_RESERVE_PRECISION）
+// 正数表示用户收到资金费，负数表示用户支付资金费
+pub fn calculate_funding_payment(
+    // 该仓位方向对应的AMM累计资金费率（精度为MARK_PRICE_PRECISION*FUNDING_PAYMENT_PRECISION）
+    amm_cumulative_funding_rate: i128,
+    market_position: &MarketPosition,
+) -> ClearingHouseResult<i128> {
+    let funding_rate_delta = amm_cumulative_funding_rate
+        .checked_sub(market_position.last_cumulative_funding_rate)
+        .ok_or_else(math_error!())?;
+
+    _calculate_funding_payment(funding_rate_delta, market_position.base_asset_amount)
+}
+
+// 计算仓位自上次结算以来尚未结算的资金费（精度为AMM_RESERVE_PRECISION）
+// 多头仓位使用多头累计资金费率，空头仓位使用空头累计资金费率
+pub fn calculate_pending_funding_payment(
+    market_position: &MarketPosition,
+    amm: &AMM,
+) -> ClearingHouseResult<i128> {
+    if market_position.base_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    let amm_cumulative_funding_rate = if market_position.base_asset_amount > 0 {
+        amm.cumulative_funding_rate_long
+    } else {
+        amm.cumulative_funding_rate_short
+    };
+
+    calculate_funding_payment(amm_cumulative_funding_rate, market_position)
+}
+
+fn _calculate_funding_payment(
+    funding_rate_delta: i128,
+    base_asset_amount: i128,
+) -> ClearingHouseResult<i128> {
+    let funding_rate_delta_sign: i128 = if funding_rate_delta > 0 { 1 } else { -1 };
+
+    // |资金费率变化量| * |仓位base资产数量| / MARK_PRICE_PRECISION / FUNDING_PAYMENT_PRECISION
+    let funding_rate_payment_magnitude = cast_to_i128(
+        U192::from(funding_rate_delta.unsigned_abs())
+            .checked_mul(U192::from(base_asset_amount.unsigned_abs()))
+            .ok_or_else(math_error!())?
+            .checked_div(U192::from(MARK_PRICE_PRECISION))
+            .ok_or_else(math_error!())?
+            .checked_div(U192::from(FUNDING_PAYMENT_PRECISION))
+            .ok_or_else(math_error!())?
+            .try_to_u128()?,
+    )?;
+
+    // 资金费率为正时，多头向空头支付资金费
+    let funding_rate_payment_sign: i128 = if base_asset_amount > 0 { -1 } else { 1 };
+
+    funding_rate_payment_magnitude
+        .checked_mul(funding_rate_payment_sign)
+        .ok_or_else(math_error!())?
+        .checked_mul(funding_rate_delta_sign)
+        .ok_or_else(math_error!())
+}
+
+// 计算将亏损loss（精度为QUOTE_PRECISION）分摊给数量为base_asset_amount的一方时，该方累计资金费率的变化量
+// base_asset_amount为正表示由多头承担（累计资金费率增加，多头支付），为负表示由空头承担（累计资金费率减少，空头支付）
+// 注：向上取整，保证分摊的资金费不少于loss
+pub fn calculate_socialized_loss_funding_rate_delta(
+    loss: u128,
+    base_asset_amount: i128,
+) -> ClearingHouseResult<i128> {
+    if base_asset_amount == 0 {
+        return Ok(0);
+    }
+
+    // loss * AMM_TO_QUOTE_PRECISION_RATIO * MARK_PRICE_PRECISION * FUNDING_PAYMENT_PRECISION / |base_asset_amount|
+    let numerator = U192::from(loss)
+        .checked_mul(U192::from(AMM_TO_QUOTE_PRECISION_RATIO))
+        .ok_or_else(math_error!())?
+        .checked_mul(U192::from(MARK_PRICE_PRECISION))
+        .ok_or_else(math_error!())?
+        .checked_mul(U192::from(FUNDING_PAYMENT_PRECISION))
+        .ok_or_else(math_error!())?;
+    let denominator = U192::from(base_asset_amount.unsigned_abs());
+    let funding_rate_delta_magnitude = cast_to_i128(
+        numerator
+            .checked_add(denominator)
+            .ok_or_else(math_error!())?
+            .checked_sub(U192::from(1))
+            .ok_or_else(math_error!())?
+            .checked_div(denominator)
+            .ok_or_else(math_error!())?
+            .try_to_u128()?,
+    )?;
+
+    if base_asset_amount > 0 {
+        Ok(funding_rate_delta_magnitude)
+    } else {
+        Ok(-funding_rate_delta_magnitude)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{AMM_RESERVE_PRECISION, QUOTE_PRECISION};
+
+    #[test]
+    fn test_calculate_funding_rate() {
+        let price = 100 * MARK_PRICE_PRECISION;
+        let premium = MARK_PRICE_PRECISION as i128;
+
+        // 1小时周期：每个周期收取1/24的溢价
+        assert_eq!(
+            calculate_funding_rate(price + MARK_PRICE_PRECISION, price as i128, ONE_HOUR).unwrap(),
+            premium * FUNDING_PAYMENT_PRECISION as i128 / 24
+        );
+        // 标记价格低于预言机价格时，资金费率为负
+        assert_eq!(
+            calculate_funding_rate(price - MARK_PRICE_PRECISION, price as i128, ONE_HOUR).unwrap(),
+            -premium * FUNDING_PAYMENT_PRECISION as i128 / 24
+        );
+        // 小于1小时的周期按1小时计
+        assert_eq!(
+            calculate_funding_rate(price + MARK_PRICE_PRECISION, price as i128, 60).unwrap(),
+            premium * FUNDING_PAYMENT_PRECISION as i128 / 24
+        );
+        // 1天周期：每个周期收取全部溢价
+        assert_eq!(
+            calculate_funding_rate(price + MARK_PRICE_PRECISION, price as i128, ONE_DAY).unwrap(),
+            premium * FUNDING_PAYMENT_PRECISION as i128
+        );
+        // 超过1天的周期同样收取全部溢价
+        assert_eq!(
+            calculate_funding_rate(price + MARK_PRICE_PRECISION, price as i128, 2 * ONE_DAY)
+                .unwrap(),
+            premium * FUNDING_PAYMENT_PRECISION as i128
+        );
+        assert_eq!(
+            calculate_funding_rate(price, price as i128, ONE_HOUR).unwrap(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_calculate_funding_payment() {
+        // 资金费率变化量为1（即价格差1）
+        let funding_rate_delta = (MARK_PRICE_PRECISION * FUNDING_PAYMENT_PRECISION) as i128;
+        let base_asset_amount = AMM_RESERVE_PRECISION as i128;
+
+        // 资金费率为正：多头支付，空头收取
+        assert_eq!(
+            _calculate_funding_payment(funding_rate_delta, base_asset_amount).unwrap(),
+            -(AMM_RESERVE_PRECISION as i128)
+        );
+        assert_eq!(
+            _calculate_funding_payment(funding_rate_delta, -base_asset_amount).unwrap(),
+            AMM_RESERVE_PRECISION as i128
+        );
+
+        // 资金费率为负：空头支付，多头收取
+        assert_eq!(
+            _calculate_funding_payment(-funding_rate_delta, base_asset_amount).unwrap(),
+            AMM_RESERVE_PRECISION as i128
+        );
+        assert_eq!(
+            _calculate_funding_payment(-funding_rate_delta, -base_asset_amount).unwrap(),
+            -(AMM_RESERVE_PRECISION as i128)
+        );
+    }
+
+    #[test]
+    fn test_calculate_socialized_loss_funding_rate_delta() {
+        let base_asset_amount = 2 * AMM_RESERVE_PRECISION as i128;
+        let loss = QUOTE_PRECISION;
+
+        // 由多头承担：累计资金费率增加，多头支付的资金费等于亏损
+        let funding_rate_delta =
+            calculate_socialized_loss_funding_rate_delta(loss, base_asset_amount).unwrap();
+        assert!(funding_rate_delta > 0);
+        assert_eq!(
+            _calculate_funding_payment(funding_rate_delta, base_asset_amount).unwrap(),
+            -((loss * AMM_TO_QUOTE_PRECISION_RATIO) as i128)
+        );
+
+        // 由空头承担：累计资金费率减少，空头支付的资金费等于亏损
+        let funding_rate_delta =
+            calculate_socialized_loss_funding_rate_delta(loss, -base_asset_amount).unwrap();
+        assert!(funding_rate_delta < 0);
+        assert_eq!(
+            _calculate_funding_payment(funding_rate_delta, -base_asset_amount).unwrap(),
+            -((loss * AMM_TO_QUOTE_PRECISION_RATIO) as i128)
+        );
+
+        // 无法整除时向上取整
+        assert_eq!(
+            calculate_socialized_loss_funding_rate_delta(1, 3).unwrap(),
+            (AMM_TO_QUOTE_PRECISION_RATIO * MARK_PRICE_PRECISION * FUNDING_PAYMENT_PRECISION)
+                .div_ceil(3) as i128
+        );
+
+        // 对手方没有仓位时无法分摊
+        assert_eq!(
+            calculate_socialized_loss_funding_rate_delta(loss, 0).unwrap(),
+            0
+        );
+    }
+}
@@ -0,0 +1,419 @@
+use crate::math::bn::ClearingHouseResult;
+use crate::math::collateral::calculate_updated_collateral;
+use crate::math::constant::{AMM_TO_QUOTE_PRECISION_RATIO_I128, MARGIN_PRECISION};
+use crate::math::funding::calculate_pending_funding_payment;
+use crate::math::position::calculate_base_asset_value_and_pnl;
+use crate::math_error;
+use crate::state::{Markets, User, UserPositions};
+use anchor_lang::prelude::*;
+
+// 保证金类型
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MarginType {
+    Init,    // 初始保证金（开仓、提款时使用）
+    Partial, // 部分清算保证金
+    Maint,   // 维持保证金
+}
+
+// 遍历用户的所有仓位，计算margin_type下的保证金要求、总抵押品和仓位总价值
+// 总抵押品 = 抵押品 + 所有仓位的未实现盈亏 + 所有仓位尚未结算的资金费（不低于0）
+// 返回值：(保证金要求, 总抵押品, 仓位总价值)，均为QUOTE_PRECISION
+pub fn calculate_margin_requirement_and_total_collateral_and_notional(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+    margin_type: MarginType,
+) -> ClearingHouseResult<(u128, u128, u128)> {
+    let ([margin_requirement], total_collateral, base_asset_notional, _) =
+        calculate_margin_requirements_and_total_collateral_and_notional(
+            user,
+            user_positions,
+            markets,
+            [margin_type],
+        )?;
+
+    Ok((margin_requirement, total_collateral, base_asset_notional))
+}
+
+// 只遍历一次用户的所有仓位，同时计算margin_types中每种保证金类型下的保证金要求，以及总抵押品、仓位总价值和未实现盈亏
+// 返回值：(与margin_types一一对应的保证金要求, 总抵押品, 仓位总价值, 所有仓位的未实现盈亏之和)，均为QUOTE_PRECISION
+fn calculate_margin_requirements_and_total_collateral_and_notional<const N: usize>(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+    margin_types: [MarginType; N],
+) -> ClearingHouseResult<([u128; N], u128, u128, i128)> {
+    let mut margin_requirements = [0_u128; N];
+    let mut base_asset_notional: u128 = 0;
+    let mut unrealized_pnl: i128 = 0;
+    // 尚未结算的资金费之和（精度为AMM_RESERVE_PRECISION）
+    let mut pending_funding_payment: i128 = 0;
+
+    for market_position in user_positions.positions.iter() {
+        if market_position.base_asset_amount == 0 {
+            continue;
+        }
+
+        let market = markets.get_market(market_position.market_index);
+        let (position_base_asset_value, position_unrealized_pnl) =
+            calculate_base_asset_value_and_pnl(market_position, &market.amm)?;
+
+        // 该仓位的保证金要求 = 仓位价值 * 保证金率 / MARGIN_PRECISION
+        for (margin_requirement, margin_type) in
+            margin_requirements.iter_mut().zip(margin_types.iter())
+        {
+            *margin_requirement = margin_requirement
+                .checked_add(
+                    position_base_asset_value
+                        .checked_mul(market.get_margin_ratio(*margin_type).into())
+                        .ok_or_else(math_error!())?
+                        .checked_div(MARGIN_PRECISION)
+                        .ok_or_else(math_error!())?,
+                )
+                .ok_or_else(math_error!())?;
+        }
+
+        base_asset_notional = base_asset_notional
+            .checked_add(position_base_asset_value)
+            .ok_or_else(math_error!())?;
+
+        unrealized_pnl = unrealized_pnl
+            .checked_add(position_unrealized_pnl)
+            .ok_or_else(math_error!())?;
+
+        pending_funding_payment = pending_funding_payment
+            .checked_add(calculate_pending_funding_payment(
+                market_position,
+                &market.amm,
+            )?)
+            .ok_or_else(math_error!())?;
+    }
+
+    // 与结算资金费时一致：先求和，再从AMM_RESERVE_PRECISION转换为QUOTE_PRECISION
+    let pending_funding_payment = pending_funding_payment
+        .checked_div(AMM_TO_QUOTE_PRECISION_RATIO_I128)
+        .ok_or_else(math_error!())?;
+
+    let total_collateral = calculate_updated_collateral(
+        user.collateral,
+        unrealized_pnl
+            .checked_add(pending_funding_payment)
+            .ok_or_else(math_error!())?,
+    )?;
+
+    Ok((
+        margin_requirements,
+        total_collateral,
+        base_asset_notional,
+        unrealized_pnl,
+    ))
+}
+
+// 计算用户在margin_type下的保证金要求和总抵押品
+// 返回值：(保证金要求, 总抵押品)，均为QUOTE_PRECISION
+pub fn calculate_margin_requirement_and_total_collateral(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+    margin_type: MarginType,
+) -> ClearingHouseResult<(u128, u128)> {
+    let (margin_requirement, total_collateral, _) =
+        calculate_margin_requirement_and_total_collateral_and_notional(
+            user,
+            user_positions,
+            markets,
+            margin_type,
+        )?;
+
+    Ok((margin_requirement, total_collateral))
+}
+
+// 计算用户的保证金率 = 总抵押品 / 仓位总价值（MARGIN_PRECISION），无持仓时为u128::MAX
+pub fn calculate_margin_ratio(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<u128> {
+    let (_, total_collateral, base_asset_notional) =
+        calculate_margin_requirement_and_total_collateral_and_notional(
+            user,
+            user_positions,
+            markets,
+            MarginType::Maint,
+        )?;
+
+    margin_ratio(total_collateral, base_asset_notional)
+}
+
+// 由总抵押品和仓位总价值计算保证金率
+fn margin_ratio(total_collateral: u128, base_asset_notional: u128) -> ClearingHouseResult<u128> {
+    if base_asset_notional == 0 {
+        return Ok(u128::MAX);
+    }
+
+    total_collateral
+        .checked_mul(MARGIN_PRECISION)
+        .ok_or_else(math_error!())?
+        .checked_div(base_asset_notional)
+        .ok_or_else(math_error!())
+}
+
+// 用户的总抵押品是否满足所有仓位在margin_type下的保证金要求
+fn meets_margin_requirement(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+    margin_type: MarginType,
+) -> ClearingHouseResult<bool> {
+    let (margin_requirement, total_collateral) = calculate_margin_requirement_and_total_collateral(
+        user,
+        user_positions,
+        markets,
+        margin_type,
+    )?;
+
+    Ok(total_collateral >= margin_requirement)
+}
+
+// 是否满足初始保证金要求（可能增加风险的交易、提款后需满足）
+pub fn meets_initial_margin(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<bool> {
+    meets_margin_requirement(user, user_positions, markets, MarginType::Init)
+}
+
+// 是否满足部分清算保证金要求（不满足时可被部分清算）
+pub fn meets_partial_margin(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<bool> {
+    meets_margin_requirement(user, user_positions, markets, MarginType::Partial)
+}
+
+// 是否满足维持保证金要求（不满足时可被完全清算）
+pub fn meets_maintenance_margin(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<bool> {
+    meets_margin_requirement(user, user_positions, markets, MarginType::Maint)
+}
+
+// 计算用户的可用抵押品，即总抵押品超出初始保证金要求的部分
+pub fn calculate_free_collateral(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<u128> {
+    let (margin_requirement, total_collateral) = calculate_margin_requirement_and_total_collateral(
+        user,
+        user_positions,
+        markets,
+        MarginType::Init,
+    )?;
+
+    Ok(total_collateral.saturating_sub(margin_requirement))
+}
+
+// 清算类型
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LiquidationType {
+    None,    // 满足部分清算保证金要求，无需清算
+    Partial, // 不满足部分清算保证金要求，部分清算
+    Full,    // 不满足维持保证金要求，完全清算
+}
+
+// 清算状态
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LiquidationStatus {
+    pub liquidation_type: LiquidationType,
+    pub total_collateral: u128, // 总抵押品（QUOTE_PRECISION）
+    pub base_asset_value: u128, // 仓位总价值（QUOTE_PRECISION）
+    pub margin_ratio: u128,     // 保证金率（MARGIN_PRECISION），无持仓时为u128::MAX
+    pub unrealized_pnl: i128,   // 所有仓位的未实现盈亏之和（QUOTE_PRECISION）
+}
+
+// 计算用户的清算状态：不满足维持保证金要求时完全清算，否则不满足部分清算保证金要求时部分清算
+// 只遍历一次用户的所有仓位，同时得到两种保证金要求、总抵押品和仓位总价值
+pub fn calculate_liquidation_status(
+    user: &User,
+    user_positions: &UserPositions,
+    markets: &Markets,
+) -> ClearingHouseResult<LiquidationStatus> {
+    let (
+        [maintenance_margin_requirement, partial_margin_requirement],
+        total_collateral,
+        base_asset_value,
+        unrealized_pnl,
+    ) = calculate_margin_requirements_and_total_collateral_and_notional(
+        user,
+        user_positions,
+        markets,
+        [MarginType::Maint, MarginType::Partial],
+    )?;
+
+    let liquidation_type = if total_collateral < maintenance_margin_requirement {
+        LiquidationType::Full
+    } else if total_collateral < partial_margin_requirement {
+        LiquidationType::Partial
+    } else {
+        LiquidationType::None
+    };
+
+    Ok(LiquidationStatus {
+        liquidation_type,
+        total_collateral,
+        base_asset_value,
+        margin_ratio: margin_ratio(total_collateral, base_asset_value)?,
+        unrealized_pnl,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::amm::calculate_base_asset_value;
+    use crate::math::constant::{
+        AMM_RESERVE_PRECISION, FUNDING_PAYMENT_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION,
+        QUOTE_PRECISION,
+    };
+    use bytemuck::Zeroable;
+
+    fn new_user(collateral: u128) -> User {
+        User {
+            authority: Pubkey::new_unique(),
+            collateral,
+            cumculative_deposits: 0,
+            total_fee_paid: 0,
+            total_fee_rebate: 0,
+            total_token_discount: 0,
+            total_referral_reward: 0,
+            total_referee_discount: 0,
+            positons: Pubkey::new_unique(),
+            settled_position_value: 0,
+            collateral_claimed: 0,
+            last_collateral_available_to_claim: 0,
+            forgo_position_settlement: 0,
+            has_settled_position: 0,
+            padding: [0; 14],
+            referrer: Pubkey::default(),
+        }
+    }
+
+    #[test]
+    fn test_margin() {
+        // 市场0：标记价格为50，多头累计资金费率为1（每单位仓位支付1个quote资产）
+        let mut markets = Box::new(Markets::zeroed());
+        let market = markets.get_market_mut(0);
+        let reserve = 1_000_000 * AMM_RESERVE_PRECISION;
+        market.amm.base_asset_reserve = reserve;
+        market.amm.quote_asset_reserve = reserve;
+        market.amm.sqrt_k = reserve;
+        market.amm.peg_multiplier = 50 * PEG_PRECISION;
+        market.amm.cumulative_funding_rate_long =
+            (MARK_PRICE_PRECISION * FUNDING_PAYMENT_PRECISION) as i128;
+        market.margin_ratio_initial = 2000;
+        market.margin_ratio_partial = 625;
+        market.margin_ratio_maintenance = 500;
+
+        // 以400个quote资产开出的10个单位多头
+        let mut user_positions = UserPositions::zeroed();
+        let base_asset_amount = 10 * AMM_RESERVE_PRECISION as i128;
+        user_positions.positions[0].base_asset_amount = base_asset_amount;
+        user_positions.positions[0].quote_asset_amount = 400 * QUOTE_PRECISION as i128;
+
+        let base_asset_value =
+            calculate_base_asset_value(base_asset_amount, &markets.get_market(0).amm).unwrap();
+        assert!(
+            base_asset_value > 499 * QUOTE_PRECISION && base_asset_value < 500 * QUOTE_PRECISION
+        );
+
+        // 总抵押品 = 抵押品100 + 未实现盈亏 - 待支付资金费10
+        let user = new_user(100 * QUOTE_PRECISION);
+        let (margin_requirement, total_collateral, base_asset_notional) =
+            calculate_margin_requirement_and_total_collateral_and_notional(
+                &user,
+                &user_positions,
+                &markets,
+                MarginType::Init,
+            )
+            .unwrap();
+        assert_eq!(
+            total_collateral,
+            base_asset_value - 400 * QUOTE_PRECISION + 90 * QUOTE_PRECISION
+        );
+        assert_eq!(base_asset_notional, base_asset_value);
+        assert_eq!(
+            margin_requirement,
+            base_asset_value * 2000 / MARGIN_PRECISION
+        );
+        assert_eq!(
+            calculate_margin_ratio(&user, &user_positions, &markets).unwrap(),
+            total_collateral * MARGIN_PRECISION / base_asset_value
+        );
+        assert!(meets_initial_margin(&user, &user_positions, &markets).unwrap());
+
+        // 无抵押品：保证金率约为18%，不满足初始保证金要求，但满足部分清算保证金要求
+        let user = new_user(0);
+        assert!(!meets_initial_margin(&user, &user_positions, &markets).unwrap());
+        assert!(meets_partial_margin(&user, &user_positions, &markets).unwrap());
+        assert!(meets_maintenance_margin(&user, &user_positions, &markets).unwrap());
+        assert_eq!(
+            calculate_liquidation_status(&user, &user_positions, &markets)
+                .unwrap()
+                .liquidation_type,
+            LiquidationType::None
+        );
+
+        // 开仓成本为462时：保证金率约为5.5%，不满足部分清算保证金要求，但满足维持保证金要求
+        user_positions.positions[0].quote_asset_amount = 462 * QUOTE_PRECISION as i128;
+        assert_eq!(
+            calculate_liquidation_status(&user, &user_positions, &markets)
+                .unwrap()
+                .liquidation_type,
+            LiquidationType::Partial
+        );
+
+        // 开仓成本为480时：保证金率约为2%，不满足部分清算和维持保证金要求
+        user_positions.positions[0].quote_asset_amount = 480 * QUOTE_PRECISION as i128;
+        assert!(!meets_partial_margin(&user, &user_positions, &markets).unwrap());
+        assert!(!meets_maintenance_margin(&user, &user_positions, &markets).unwrap());
+        let (_, total_collateral, _) =
+            calculate_margin_requirement_and_total_collateral_and_notional(
+                &user,
+                &user_positions,
+                &markets,
+                MarginType::Maint,
+            )
+            .unwrap();
+        let margin_ratio = total_collateral * MARGIN_PRECISION / base_asset_value;
+        assert_eq!(
+            calculate_margin_ratio(&user, &user_positions, &markets).unwrap(),
+            margin_ratio
+        );
+        assert_eq!(
+            calculate_liquidation_status(&user, &user_positions, &markets).unwrap(),
+            LiquidationStatus {
+                liquidation_type: LiquidationType::Full,
+                total_collateral,
+                base_asset_value,
+                margin_ratio,
+                unrealized_pnl: base_asset_value as i128 - 480 * QUOTE_PRECISION as i128,
+            }
+        );
+        assert_eq!(
+            calculate_free_collateral(&user, &user_positions, &markets).unwrap(),
+            0
+        );
+
+        // 无持仓时保证金率为u128::MAX
+        let user_positions = UserPositions::zeroed();
+        assert_eq!(
+            calculate_margin_ratio(&user, &user_positions, &markets).unwrap(),
+            u128::MAX
+        );
+    }
+}
@@ -1,4 +1,14 @@
 pub mod amm;
 pub mod bn;
 pub mod cast;
+pub mod collateral;
 pub mod constant;
+pub mod fees;
+pub mod funding;
+pub mod margin;
+pub mod oracle;
+pub mod orders;
+pub mod pnl;
+pub mod position;
+pub mod quote_asset;
+pub mod withdrawal;
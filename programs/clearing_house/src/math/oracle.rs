@@ -0,0 +1,233 @@
+use std::cmp::{max, min};
+
+use crate::errors::Errors;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::{cast_to_i128, cast_to_u128};
+use crate::math::constant::{MARK_PRICE_PRECISION, MARK_PRICE_PRECISION_EXPO};
+use crate::math_error;
+use crate::state::{OraclePriceData, PriceDivergenceGuardRails, ValidityGuardRails, AMM};
+use anchor_lang::prelude::*;
+
+// 将预言机给出的数值value * 10^exponent转换为MARK_PRICE_PRECISION精度
+// exponent可正可负：exponent > MARK_PRICE_PRECISION_EXPO时放大，否则缩小（截断多余的小数位）
+pub fn scale_to_mark_price_precision(value: i128, exponent: i32) -> ClearingHouseResult<i128> {
+    let expo_diff = exponent
+        .checked_sub(MARK_PRICE_PRECISION_EXPO)
+        .ok_or_else(math_error!())?;
+    let scale = 10_i128
+        .checked_pow(expo_diff.unsigned_abs())
+        .ok_or_else(math_error!())?;
+
+    if expo_diff >= 0 {
+        value.checked_mul(scale)
+    } else {
+        value.checked_div(scale)
+    }
+    .ok_or_else(math_error!())
+}
+
+// 根据State中的ValidityGuardRails检验从预言机读取的价格数据是否可用，不可用时返回对应的错误：
+//  - OraclePriceStale：价格延迟的slot数超过slots_before_stable
+//  - OracleInsufficientDataPoints：预言机没有足够的数据点支持
+//  - OraclePriceNonPositive：价格不为正
+//  - OracleConfidenceTooWide：价格/置信区间 < confidence_interval_max_size，即置信区间相对价格过宽
+//  - OraclePriceTooVolatile：价格与预言机价格TWAP中较大者/较小者 > too_volatile_ratio
+pub fn is_oracle_valid(
+    amm: &AMM,
+    oracle_price_data: &OraclePriceData,
+    validity_guard_rails: &ValidityGuardRails,
+) -> ClearingHouseResult {
+    let oracle_price = oracle_price_data.price;
+
+    if oracle_price_data.delay > validity_guard_rails.slots_before_stable {
+        return Err(Errors::OraclePriceStale);
+    }
+
+    if !oracle_price_data.has_sufficient_number_of_data_points {
+        return Err(Errors::OracleInsufficientDataPoints);
+    }
+
+    if oracle_price <= 0 {
+        return Err(Errors::OraclePriceNonPositive);
+    }
+
+    let conf_denom_of_price = oracle_price
+        .checked_div(cast_to_i128(max(oracle_price_data.confidence, 1))?)
+        .ok_or_else(math_error!())?;
+    if conf_denom_of_price < cast_to_i128(validity_guard_rails.confidence_interval_max_size)? {
+        return Err(Errors::OracleConfidenceTooWide);
+    }
+
+    let oracle_price_twap = amm.last_oracle_price_twap;
+    let volatility_ratio = max(oracle_price, oracle_price_twap)
+        .checked_div(max(1, min(oracle_price, oracle_price_twap)))
+        .ok_or_else(math_error!())?;
+    if volatility_ratio > validity_guard_rails.too_volatile_ratio {
+        return Err(Errors::OraclePriceTooVolatile);
+    }
+
+    Ok(())
+}
+
+// 计算标记价格相对预言机价格的偏离比例：(mark_price - oracle_price) / oracle_price（MARK_PRICE_PRECISION）
+// precomputed_mark_price为None时使用AMM当前的标记价格
+pub fn calculate_oracle_mark_spread_pct(
+    amm: &AMM,
+    oracle_price: i128,
+    precomputed_mark_price: Option<u128>,
+) -> ClearingHouseResult<i128> {
+    let mark_price = match precomputed_mark_price {
+        Some(mark_price) => mark_price,
+        None => amm.mark_price()?,
+    };
+
+    cast_to_i128(mark_price)?
+        .checked_sub(oracle_price)
+        .ok_or_else(math_error!())?
+        .checked_mul(cast_to_i128(MARK_PRICE_PRECISION)?)
+        .ok_or_else(math_error!())?
+        .checked_div(oracle_price)
+        .ok_or_else(math_error!())
+}
+
+// 标记价格与预言机价格的偏离比例是否超过PriceDivergenceGuardRails允许的最大偏离比例
+pub fn is_oracle_mark_too_divergent(
+    price_spread_pct: i128,
+    price_divergence_guard_rails: &PriceDivergenceGuardRails,
+) -> ClearingHouseResult<bool> {
+    let max_divergence = price_divergence_guard_rails
+        .mark_oracle_divergence_numerator
+        .checked_mul(MARK_PRICE_PRECISION)
+        .ok_or_else(math_error!())?
+        .checked_div(price_divergence_guard_rails.mark_oracle_divergence_denominator)
+        .ok_or_else(math_error!())?;
+
+    Ok(cast_to_u128(price_spread_pct.abs())? > max_divergence)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::MARK_PRICE_PRECISION;
+    use bytemuck::Zeroable;
+
+    const PRICE: i128 = 50 * MARK_PRICE_PRECISION as i128;
+
+    fn guard_rails() -> ValidityGuardRails {
+        ValidityGuardRails {
+            confidence_interval_max_size: 4,
+            too_volatile_ratio: 5,
+            slots_before_stable: 1000,
+            padding: [0; 8],
+        }
+    }
+
+    fn oracle_price_data(price: i128, confidence: u128, delay: i64) -> OraclePriceData {
+        OraclePriceData {
+            price,
+            confidence,
+            delay,
+            has_sufficient_number_of_data_points: true,
+        }
+    }
+
+    #[test]
+    fn test_scale_to_mark_price_precision() {
+        // pyth常见的负exponent：50.12345678（expo = -8）
+        assert_eq!(
+            scale_to_mark_price_precision(5_012_345_678, -8).unwrap(),
+            501_234_567_800
+        );
+        // 精度高于MARK_PRICE_PRECISION时截断
+        assert_eq!(
+            scale_to_mark_price_precision(50_123_456_789_012, -12).unwrap(),
+            501_234_567_890
+        );
+        // 正exponent：5 * 10^2 = 500
+        assert_eq!(
+            scale_to_mark_price_precision(5, 2).unwrap(),
+            500 * MARK_PRICE_PRECISION as i128
+        );
+        assert_eq!(
+            scale_to_mark_price_precision(-5, 0).unwrap(),
+            -5 * MARK_PRICE_PRECISION as i128
+        );
+        assert!(scale_to_mark_price_precision(i128::MAX, 0).is_err());
+    }
+
+    #[test]
+    fn test_is_oracle_mark_too_divergent() {
+        let guard_rails = PriceDivergenceGuardRails {
+            mark_oracle_divergence_numerator: 1,
+            mark_oracle_divergence_denominator: 10,
+        };
+        let amm = AMM::zeroed();
+
+        // 标记价格高于/低于预言机价格10%时恰好不超过上限
+        for mark_price in [55 * MARK_PRICE_PRECISION, 45 * MARK_PRICE_PRECISION] {
+            let spread_pct =
+                calculate_oracle_mark_spread_pct(&amm, PRICE, Some(mark_price)).unwrap();
+            assert_eq!(spread_pct.unsigned_abs(), MARK_PRICE_PRECISION / 10);
+            assert!(!is_oracle_mark_too_divergent(spread_pct, &guard_rails).unwrap());
+        }
+
+        let spread_pct =
+            calculate_oracle_mark_spread_pct(&amm, PRICE, Some(56 * MARK_PRICE_PRECISION)).unwrap();
+        assert!(is_oracle_mark_too_divergent(spread_pct, &guard_rails).unwrap());
+        let spread_pct =
+            calculate_oracle_mark_spread_pct(&amm, PRICE, Some(44 * MARK_PRICE_PRECISION)).unwrap();
+        assert!(is_oracle_mark_too_divergent(spread_pct, &guard_rails).unwrap());
+    }
+
+    #[test]
+    fn test_is_oracle_valid() {
+        let amm = AMM {
+            last_oracle_price_twap: PRICE,
+            ..AMM::zeroed()
+        };
+        let guard_rails = guard_rails();
+
+        assert!(is_oracle_valid(&amm, &oracle_price_data(PRICE, 1, 1000), &guard_rails).is_ok());
+        assert!(matches!(
+            is_oracle_valid(&amm, &oracle_price_data(PRICE, 1, 1001), &guard_rails),
+            Err(Errors::OraclePriceStale)
+        ));
+        assert!(matches!(
+            is_oracle_valid(
+                &amm,
+                &OraclePriceData {
+                    has_sufficient_number_of_data_points: false,
+                    ..oracle_price_data(PRICE, 1, 0)
+                },
+                &guard_rails
+            ),
+            Err(Errors::OracleInsufficientDataPoints)
+        ));
+        assert!(matches!(
+            is_oracle_valid(&amm, &oracle_price_data(0, 1, 0), &guard_rails),
+            Err(Errors::OraclePriceNonPositive)
+        ));
+        // 置信区间达到价格的1/4时仍可用，超过时过宽
+        let confidence = PRICE as u128 / 4;
+        assert!(
+            is_oracle_valid(&amm, &oracle_price_data(PRICE, confidence, 0), &guard_rails).is_ok()
+        );
+        assert!(matches!(
+            is_oracle_valid(
+                &amm,
+                &oracle_price_data(PRICE, confidence + 1, 0),
+                &guard_rails
+            ),
+            Err(Errors::OracleConfidenceTooWide)
+        ));
+        // 价格相对预言机价格TWAP上涨或下跌超过5倍
+        assert!(matches!(
+            is_oracle_valid(&amm, &oracle_price_data(6 * PRICE, 1, 0), &guard_rails),
+            Err(Errors::OraclePriceTooVolatile)
+        ));
+        assert!(matches!(
+            is_oracle_valid(&amm, &oracle_price_data(PRICE / 6, 1, 0), &guard_rails),
+            Err(Errors::OraclePriceTooVolatile)
+        ));
+    }
+}
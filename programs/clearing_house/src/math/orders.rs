@@ -0,0 +1,263 @@
+use crate::controller::position::PositionDirection;
+use crate::errors::Errors;
+use crate::math::bn::{ClearingHouseResult, U256};
+use crate::math::cast::cast_to_u128;
+use crate::math::constant::PRICE_TO_PEG_PRECISION_RATIO;
+use crate::math_error;
+use crate::state::{Order, OrderTriggerCondition, AMM};
+use anchor_lang::prelude::*;
+use std::cmp::min;
+
+// 计算订单成交时的有效限价（MARK_PRICE_PRECISION），为0表示不限价
+// 指定了oracle_price_offset的限价单，有效限价为成交时的预言机价格加上偏移量，否则为订单价格
+pub fn calculate_limit_price(order: &Order, oracle_price: i128) -> ClearingHouseResult<u128> {
+    if order.oracle_price_offset == 0 {
+        return Ok(order.price);
+    }
+
+    let limit_price = oracle_price
+        .checked_add(order.oracle_price_offset)
+        .ok_or_else(math_error!())?;
+    // 偏移后的价格必须为正
+    if limit_price <= 0 {
+        return Err(Errors::OrderCannotBeFilled);
+    }
+
+    cast_to_u128(limit_price)
+}
+
+// 计算按base资产数量下单的订单本次可成交的base资产数量
+// 未指定限价时成交全部剩余数量；指定限价时，最多成交到AMM的标记价格达到限价为止
+pub fn calculate_base_asset_amount_to_fill(
+    order: &Order,
+    amm: &AMM,
+    // 订单的有效限价
+    limit_price: u128,
+) -> ClearingHouseResult<u128> {
+    let base_asset_amount_unfilled = order
+        .base_asset_amount
+        .checked_sub(order.base_asset_amount_filled)
+        .ok_or_else(math_error!())?;
+
+    if limit_price == 0 {
+        return Ok(base_asset_amount_unfilled);
+    }
+
+    let max_base_asset_amount =
+        calculate_base_asset_amount_to_trade_to_price(amm, limit_price, order.direction)?;
+
+    Ok(min(base_asset_amount_unfilled, max_base_asset_amount))
+}
+
+// 计算沿direction方向交易、使AMM的标记价格恰好达到limit_price所需交换的base资产数量
+// 标记价格已经达到（或越过）limit_price时返回0
+pub fn calculate_base_asset_amount_to_trade_to_price(
+    amm: &AMM,
+    // MARK_PRICE_PRECISION
+    limit_price: u128,
+    direction: PositionDirection,
+) -> ClearingHouseResult<u128> {
+    let mark_price = amm.mark_price()?;
+    match direction {
+        PositionDirection::Long if mark_price >= limit_price => return Ok(0),
+        PositionDirection::Short if mark_price <= limit_price => return Ok(0),
+        _ => {}
+    }
+
+    // 标记价格 = quote_asset_reserve * peg_multiplier * PRICE_TO_PEG_PRECISION_RATIO / base_asset_reserve
+    // 且quote_asset_reserve * base_asset_reserve = k，所以标记价格为limit_price时：
+    // base_asset_reserve^2 = k * peg_multiplier * PRICE_TO_PEG_PRECISION_RATIO / limit_price
+    let invariant_sqrt = U256::from(amm.sqrt_k);
+    let new_base_asset_reserve_squared = invariant_sqrt
+        .checked_mul(invariant_sqrt)
+        .ok_or_else(math_error!())?
+        .checked_mul(U256::from(amm.peg_multiplier))
+        .ok_or_else(math_error!())?
+        .checked_mul(U256::from(PRICE_TO_PEG_PRECISION_RATIO))
+        .ok_or_else(math_error!())?
+        .checked_div(U256::from(limit_price))
+        .ok_or_else(math_error!())?;
+    let mut new_base_asset_reserve = new_base_asset_reserve_squared.integer_sqrt();
+
+    match direction {
+        PositionDirection::Long => {
+            // 做多时base资产储备量减少，向上取整使成交后的标记价格不超过limit_price
+            if new_base_asset_reserve * new_base_asset_reserve != new_base_asset_reserve_squared {
+                new_base_asset_reserve = new_base_asset_reserve
+                    .checked_add(U256::one())
+                    .ok_or_else(math_error!())?;
+            }
+            let new_base_asset_reserve = new_base_asset_reserve.try_to_u128()?;
+            if new_base_asset_reserve >= amm.base_asset_reserve {
+                return Ok(0);
+            }
+            amm.base_asset_reserve
+                .checked_sub(new_base_asset_reserve)
+                .ok_or_else(math_error!())
+        }
+        PositionDirection::Short => {
+            // 做空时base资产储备量增加，向下取整使成交后的标记价格不低于limit_price
+            let new_base_asset_reserve = new_base_asset_reserve.try_to_u128()?;
+            if new_base_asset_reserve <= amm.base_asset_reserve {
+                return Ok(0);
+            }
+            new_base_asset_reserve
+                .checked_sub(amm.base_asset_reserve)
+                .ok_or_else(math_error!())
+        }
+    }
+}
+
+// 仅减仓的订单最多可成交的base资产数量：与仓位反向时为剩余仓位，否则为0
+pub fn calculate_max_reduce_only_base_asset_amount(
+    direction: PositionDirection,
+    existing_base_asset_amount: i128,
+) -> u128 {
+    match direction {
+        PositionDirection::Long if existing_base_asset_amount < 0 => {
+            existing_base_asset_amount.unsigned_abs()
+        }
+        PositionDirection::Short if existing_base_asset_amount > 0 => {
+            existing_base_asset_amount.unsigned_abs()
+        }
+        _ => 0,
+    }
+}
+
+// 以预言机价格判断触发单是否满足触发条件
+pub fn is_order_triggered(order: &Order, oracle_price: i128) -> ClearingHouseResult<bool> {
+    let oracle_price = cast_to_u128(oracle_price)?;
+    Ok(match order.trigger_condition {
+        OrderTriggerCondition::Above => oracle_price > order.trigger_price,
+        OrderTriggerCondition::Below => oracle_price < order.trigger_price,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::controller::amm::{swap_base_asset, SwapDirection};
+    use crate::math::constant::{AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION};
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_calculate_base_asset_amount_to_trade_to_price() {
+        // 标记价格为100
+        let mut amm = AMM::zeroed();
+        amm.base_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        amm.quote_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        amm.sqrt_k = 1000 * AMM_RESERVE_PRECISION;
+        amm.peg_multiplier = 100 * PEG_PRECISION;
+
+        // 标记价格已经达到限价
+        let limit_price = 99 * MARK_PRICE_PRECISION;
+        assert_eq!(
+            calculate_base_asset_amount_to_trade_to_price(
+                &amm,
+                limit_price,
+                PositionDirection::Long
+            )
+            .unwrap(),
+            0
+        );
+
+        // 做多到121：base资产储备量从1000变为1000/1.1，约90.9个base资产
+        let limit_price = 121 * MARK_PRICE_PRECISION;
+        let base_asset_amount = calculate_base_asset_amount_to_trade_to_price(
+            &amm,
+            limit_price,
+            PositionDirection::Long,
+        )
+        .unwrap();
+        assert_eq!(base_asset_amount / AMM_RESERVE_PRECISION, 90);
+        let mut amm_after = amm;
+        swap_base_asset(&mut amm_after, base_asset_amount, SwapDirection::Remove, 0).unwrap();
+        let mark_price_after = amm_after.mark_price().unwrap();
+        assert!(mark_price_after <= limit_price);
+        assert!(mark_price_after > limit_price - MARK_PRICE_PRECISION / 1000);
+
+        // 做空到81：base资产储备量从1000变为1000/0.9，约111.1个base资产
+        let limit_price = 81 * MARK_PRICE_PRECISION;
+        let base_asset_amount = calculate_base_asset_amount_to_trade_to_price(
+            &amm,
+            limit_price,
+            PositionDirection::Short,
+        )
+        .unwrap();
+        assert_eq!(base_asset_amount / AMM_RESERVE_PRECISION, 111);
+        let mut amm_after = amm;
+        swap_base_asset(&mut amm_after, base_asset_amount, SwapDirection::Add, 0).unwrap();
+        let mark_price_after = amm_after.mark_price().unwrap();
+        assert!(mark_price_after >= limit_price);
+        assert!(mark_price_after < limit_price + MARK_PRICE_PRECISION / 1000);
+    }
+
+    #[test]
+    fn test_is_order_triggered() {
+        let mut order = Order::zeroed();
+        order.trigger_price = 100 * MARK_PRICE_PRECISION;
+
+        // 止盈（做多仓位）：价格高于100时触发
+        order.trigger_condition = OrderTriggerCondition::Above;
+        let oracle_price = (100 * MARK_PRICE_PRECISION) as i128;
+        assert!(!is_order_triggered(&order, oracle_price).unwrap());
+        assert!(is_order_triggered(&order, oracle_price + 1).unwrap());
+
+        // 止损（做多仓位）：价格低于100时触发
+        order.trigger_condition = OrderTriggerCondition::Below;
+        assert!(!is_order_triggered(&order, oracle_price).unwrap());
+        assert!(is_order_triggered(&order, oracle_price - 1).unwrap());
+    }
+
+    #[test]
+    fn test_calculate_limit_price() {
+        let oracle_price = (100 * MARK_PRICE_PRECISION) as i128;
+        let mut order = Order::zeroed();
+        order.price = 90 * MARK_PRICE_PRECISION;
+        assert_eq!(
+            calculate_limit_price(&order, oracle_price).unwrap(),
+            90 * MARK_PRICE_PRECISION
+        );
+
+        // 挂在预言机价格下方1的买单
+        order.price = 0;
+        order.oracle_price_offset = -(MARK_PRICE_PRECISION as i128);
+        assert_eq!(
+            calculate_limit_price(&order, oracle_price).unwrap(),
+            99 * MARK_PRICE_PRECISION
+        );
+
+        // 偏移后的价格为负
+        order.oracle_price_offset = -oracle_price;
+        assert!(calculate_limit_price(&order, oracle_price).is_err());
+    }
+
+    #[test]
+    fn test_calculate_max_reduce_only_base_asset_amount() {
+        let base_asset_amount = AMM_RESERVE_PRECISION as i128;
+        // 与仓位反向
+        assert_eq!(
+            calculate_max_reduce_only_base_asset_amount(
+                PositionDirection::Short,
+                base_asset_amount
+            ),
+            AMM_RESERVE_PRECISION
+        );
+        assert_eq!(
+            calculate_max_reduce_only_base_asset_amount(
+                PositionDirection::Long,
+                -base_asset_amount
+            ),
+            AMM_RESERVE_PRECISION
+        );
+        // 与仓位同向或无仓位
+        assert_eq!(
+            calculate_max_reduce_only_base_asset_amount(PositionDirection::Long, base_asset_amount),
+            0
+        );
+        assert_eq!(
+            calculate_max_reduce_only_base_asset_amount(PositionDirection::Short, 0),
+            0
+        );
+    }
+}
@@ -0,0 +1,25 @@
+use crate::controller::amm::SwapDirection;
+use crate::math::bn::ClearingHouseResult;
+use crate::math::cast::cast_to_i128;
+use crate::math_error;
+use anchor_lang::prelude::*;
+
+// 根据平仓价值和开仓成本计算盈亏
+pub fn calculate_pnl(
+    // 平仓可获得的quote资产数量
+    exit_value: u128,
+    // 开仓时花费的quote资产数量
+    entry_value: u128,
+    // 平仓时与AMM交换的方向
+    swap_direction_to_close: SwapDirection,
+) -> ClearingHouseResult<i128> {
+    let exit_value = cast_to_i128(exit_value)?;
+    let entry_value = cast_to_i128(entry_value)?;
+    match swap_direction_to_close {
+        // 平多仓：盈亏 = 平仓价值 - 开仓成本
+        SwapDirection::Add => exit_value.checked_sub(entry_value),
+        // 平空仓：盈亏 = 开仓成本 - 平仓价值
+        SwapDirection::Remove => entry_value.checked_sub(exit_value),
+    }
+    .ok_or_else(math_error!())
+}
@@ -0,0 +1,62 @@
+use crate::controller::amm::SwapDirection;
+use crate::controller::position::PositionDirection;
+use crate::math::amm::calculate_base_asset_value;
+use crate::math::bn::{ClearingHouseResult, U192};
+use crate::math::cast::cast_to_u128;
+use crate::math::constant::MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO;
+use crate::math::pnl::calculate_pnl;
+use crate::math_error;
+use crate::state::{MarketPosition, AMM};
+use anchor_lang::prelude::*;
+
+// 计算仓位当前的价值（即按AMM当前状态平仓可获得的quote资产数量）和未实现盈亏
+pub fn calculate_base_asset_value_and_pnl(
+    market_position: &MarketPosition,
+    amm: &AMM,
+) -> ClearingHouseResult<(u128, i128)> {
+    if market_position.base_asset_amount == 0 {
+        return Ok((0, 0));
+    }
+
+    let swap_direction = swap_direction_to_close_position(market_position.base_asset_amount);
+    let base_asset_value = calculate_base_asset_value(market_position.base_asset_amount, amm)?;
+    let pnl = calculate_pnl(
+        base_asset_value,
+        cast_to_u128(market_position.quote_asset_amount)?,
+        swap_direction,
+    )?;
+
+    Ok((base_asset_value, pnl))
+}
+
+// 平仓时与AMM交换base资产的方向：
+// 平多仓需要将base资产卖回AMM（Add），平空仓需要从AMM买回base资产（Remove）
+pub fn swap_direction_to_close_position(base_asset_amount: i128) -> SwapDirection {
+    if base_asset_amount >= 0 {
+        SwapDirection::Add
+    } else {
+        SwapDirection::Remove
+    }
+}
+
+// 平仓时的交易方向：平多仓需要做空，平空仓需要做多
+pub fn direction_to_close_position(base_asset_amount: i128) -> PositionDirection {
+    if base_asset_amount > 0 {
+        PositionDirection::Short
+    } else {
+        PositionDirection::Long
+    }
+}
+
+// 计算成交均价（MARK_PRICE_PRECISION）= quote资产数量 / base资产数量
+pub fn calculate_entry_price(
+    quote_asset_amount: u128,
+    base_asset_amount: u128,
+) -> ClearingHouseResult<u128> {
+    U192::from(quote_asset_amount)
+        .checked_mul(U192::from(MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO))
+        .ok_or_else(math_error!())?
+        .checked_div(U192::from(base_asset_amount))
+        .ok_or_else(math_error!())?
+        .try_to_u128()
+}
@@ -0,0 +1,92 @@
+use crate::math::bn::{ClearingHouseResult, U192};
+use crate::math::constant::AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO;
+use crate::math_error;
+use anchor_lang::prelude::*;
+
+// 将AMM中quote资产的储备量（AMM_RESERVE_PRECISION）转换为经过peg调整后的quote资产数量（QUOTE_PRECISION）
+// quote_asset_amount = quote_asset_reserve * (peg_multiplier / PEG_PRECISION) / (AMM_RESERVE_PRECISION / QUOTE_PRECISION)
+// round_up为true时向上取整，否则向下取整
+pub fn reserve_to_asset_amount(
+    quote_asset_reserve: u128,
+    peg_multiplier: u128,
+    round_up: bool,
+) -> ClearingHouseResult<u128> {
+    div(
+        U192::from(quote_asset_reserve)
+            .checked_mul(U192::from(peg_multiplier))
+            .ok_or_else(math_error!())?,
+        U192::from(AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO),
+        round_up,
+    )?
+    .try_to_u128()
+}
+
+// 将quote资产数量（QUOTE_PRECISION）转换为AMM中quote资产的储备量（AMM_RESERVE_PRECISION）
+// quote_asset_reserve = quote_asset_amount * (AMM_RESERVE_PRECISION / QUOTE_PRECISION) / (peg_multiplier / PEG_PRECISION)
+// round_up为true时向上取整，否则向下取整
+pub fn asset_to_reserve_amount(
+    quote_asset_amount: u128,
+    peg_multiplier: u128,
+    round_up: bool,
+) -> ClearingHouseResult<u128> {
+    div(
+        U192::from(quote_asset_amount)
+            .checked_mul(U192::from(AMM_TIMES_PEG_TO_QUOTE_PRECISION_RATIO))
+            .ok_or_else(math_error!())?,
+        U192::from(peg_multiplier),
+        round_up,
+    )?
+    .try_to_u128()
+}
+
+fn div(numerator: U192, denominator: U192, round_up: bool) -> ClearingHouseResult<U192> {
+    let quotient = numerator
+        .checked_div(denominator)
+        .ok_or_else(math_error!())?;
+
+    if round_up && !(numerator % denominator).is_zero() {
+        quotient.checked_add(U192::one()).ok_or_else(math_error!())
+    } else {
+        Ok(quotient)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION};
+
+    #[test]
+    fn test_reserve_to_asset_amount() {
+        // peg为1时，1单位储备量对应1单位quote资产
+        assert_eq!(
+            reserve_to_asset_amount(AMM_RESERVE_PRECISION, PEG_PRECISION, false).unwrap(),
+            QUOTE_PRECISION
+        );
+        // peg为10时，1单位储备量对应10单位quote资产
+        assert_eq!(
+            reserve_to_asset_amount(AMM_RESERVE_PRECISION, 10 * PEG_PRECISION, false).unwrap(),
+            10 * QUOTE_PRECISION
+        );
+        // 不足1个最小单位时，向下取整为0，向上取整为1
+        assert_eq!(reserve_to_asset_amount(1, PEG_PRECISION, false).unwrap(), 0);
+        assert_eq!(reserve_to_asset_amount(1, PEG_PRECISION, true).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_asset_to_reserve_amount() {
+        assert_eq!(
+            asset_to_reserve_amount(QUOTE_PRECISION, PEG_PRECISION, false).unwrap(),
+            AMM_RESERVE_PRECISION
+        );
+        // peg为3时无法整除
+        assert_eq!(
+            asset_to_reserve_amount(1, 3 * PEG_PRECISION, false).unwrap(),
+            3_333_333
+        );
+        assert_eq!(
+            asset_to_reserve_amount(1, 3 * PEG_PRECISION, true).unwrap(),
+            3_333_334
+        );
+    }
+}
@@ -0,0 +1,27 @@
+use crate::math::bn::ClearingHouseResult;
+use crate::math_error;
+use anchor_lang::prelude::*;
+use anchor_spl::token::TokenAccount;
+
+// 计算本次提款分别需要从collateral_vault和insurance_vault中转出的数量
+// 当collateral_vault余额不足时，差额由insurance_vault补足；两者都不足时，只能转出两者的全部余额
+// 返回值：(从collateral_vault转出的数量, 从insurance_vault转出的数量)
+pub fn calculate_withdrawal_amounts(
+    amount: u64,
+    collateral_vault: &TokenAccount,
+    insurance_vault: &TokenAccount,
+) -> ClearingHouseResult<(u64, u64)> {
+    if collateral_vault.amount >= amount {
+        return Ok((amount, 0));
+    }
+
+    let shortfall = amount
+        .checked_sub(collateral_vault.amount)
+        .ok_or_else(math_error!())?;
+
+    Ok(if insurance_vault.amount >= shortfall {
+        (collateral_vault.amount, shortfall)
+    } else {
+        (collateral_vault.amount, insurance_vault.amount)
+    })
+}
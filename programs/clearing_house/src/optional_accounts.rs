@@ -3,8 +3,16 @@ use anchor_spl::token::TokenAccount;
 
 use crate::{
     errors::Errors, handlers::InitializeUserOptionalAccounts, math::bn::ClearingHouseResult,
+    state::User,
 };
 
+// 开仓/平仓时可选传入的账户，按discount_token、referrer的顺序通过ctx.remaining_accounts传入
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct ManagePositionOptionalAccounts {
+    pub discount_token: bool, // 是否传入用户持有的discount_mint的token account（用于手续费持币折扣）
+    pub referrer: bool,       // 是否传入推荐人的User账户
+}
+
 pub fn get_whitelist_token(
     optional_accounts: &InitializeUserOptionalAccounts,
     remaining_accounts: &[AccountInfo],
@@ -16,12 +24,10 @@ pub fn get_whitelist_token(
         return Ok(None);
     }
 
-    // 如果通过ctx.remaining_accounts传入的account数量不为1，报错
-    if remaining_accounts.len() != 1 {
-        return Err(Errors::FailToFindWhitelistToken);
-    }
-
-    let token_account_info = &remaining_accounts[0];
+    // whitelist token account为ctx.remaining_accounts中的第一个account，未传入时报错
+    let token_account_info = remaining_accounts
+        .first()
+        .ok_or(Errors::FailToFindWhitelistToken)?;
     // 校验ctx.remaining_accounts传入的account的owner必须是Token Program
     if !token_account_info.owner.eq(&anchor_spl::token::ID) {
         return Err(Errors::InvalidWhitelistToken);
@@ -40,3 +46,102 @@ pub fn get_whitelist_token(
 
     Ok(Some(token_account))
 }
+
+// 初始化User时，从ctx.remaining_accounts中解析出推荐人的User账户，返回推荐人User账户的地址
+pub fn get_referrer_for_initialize_user(
+    optional_accounts: &InitializeUserOptionalAccounts,
+    remaining_accounts: &[AccountInfo],
+    authority: &Pubkey,
+) -> ClearingHouseResult<Option<Pubkey>> {
+    if !optional_accounts.referrer {
+        return Ok(None);
+    }
+
+    // 同时传入了whitelist token account时，推荐人账户为第二个account
+    let referrer_index = if optional_accounts.whitelist_token {
+        1
+    } else {
+        0
+    };
+    let referrer_account_info = remaining_accounts
+        .get(referrer_index)
+        .ok_or(Errors::InvalidReferrer)?;
+
+    // 推荐人账户必须是已经初始化的本program的User账户
+    if !referrer_account_info.owner.eq(&crate::ID) {
+        return Err(Errors::InvalidReferrer);
+    }
+    let referrer = User::try_deserialize(&mut &**referrer_account_info.data.borrow())
+        .map_err(|_| Errors::InvalidReferrer)?;
+
+    // 不能推荐自己
+    if referrer.authority.eq(authority) {
+        return Err(Errors::InvalidReferrer);
+    }
+
+    Ok(Some(referrer_account_info.key()))
+}
+
+// 从ctx.remaining_accounts中解析出用户持有的discount_mint的token account
+pub fn get_discount_token(
+    optional_accounts: ManagePositionOptionalAccounts,
+    remaining_accounts: &[AccountInfo],
+    discount_mint: &Pubkey,
+    authority: &Pubkey,
+) -> ClearingHouseResult<Option<TokenAccount>> {
+    // 未传入token account，或者state.discount_mint尚未设置时，无持币折扣
+    if !optional_accounts.discount_token || discount_mint.eq(&Pubkey::default()) {
+        return Ok(None);
+    }
+
+    // discount token account为ctx.remaining_accounts中的第一个account
+    let token_account_info = remaining_accounts
+        .first()
+        .ok_or(Errors::InvalidDiscountToken)?;
+    if !token_account_info.owner.eq(&anchor_spl::token::ID) {
+        return Err(Errors::InvalidDiscountToken);
+    }
+
+    let token_account =
+        TokenAccount::try_deserialize_unchecked(&mut &**token_account_info.data.borrow())
+            .map_err(|_| Errors::InvalidDiscountToken)?;
+
+    // token account的mint必须是state.discount_mint，且owner必须是用户本人
+    if !token_account.mint.eq(discount_mint) || !token_account.owner.eq(authority) {
+        return Err(Errors::InvalidDiscountToken);
+    }
+
+    Ok(Some(token_account))
+}
+
+// 从ctx.remaining_accounts中解析出用户在初始化时登记的推荐人的User账户
+pub fn get_referrer<'a>(
+    optional_accounts: ManagePositionOptionalAccounts,
+    remaining_accounts: &'a [AccountInfo<'a>],
+    // 用户登记的推荐人（User.referrer）
+    referrer: &Pubkey,
+) -> ClearingHouseResult<Option<Account<'a, User>>> {
+    // 未传入推荐人账户，或者用户没有登记推荐人
+    if !optional_accounts.referrer || referrer.eq(&Pubkey::default()) {
+        return Ok(None);
+    }
+
+    // 同时传入了discount token account时，推荐人账户为第二个account
+    let referrer_index = if optional_accounts.discount_token {
+        1
+    } else {
+        0
+    };
+    let referrer_account_info = remaining_accounts
+        .get(referrer_index)
+        .ok_or(Errors::InvalidReferrer)?;
+
+    // 传入的推荐人账户必须是用户登记的推荐人
+    if !referrer_account_info.key.eq(referrer) {
+        return Err(Errors::InvalidReferrer);
+    }
+    let referrer: Account<User> =
+        Account::try_from(referrer_account_info).map_err(|_| Errors::InvalidReferrer)?;
+
+    Ok(Some(referrer))
+}
@@ -0,0 +1,305 @@
+use crate::{
+    controller::position::PositionDirection,
+    errors::Errors,
+    math::{
+        bn::ClearingHouseResult, constant::MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO,
+        orders::calculate_limit_price,
+    },
+    math_error,
+    state::{Market, Order, OrderState, OrderType},
+};
+use anchor_lang::prelude::*;
+
+// oracle_price：下单时读取并通过有效性检验的预言机价格（MARK_PRICE_PRECISION）
+pub fn validate_order(
+    order: &Order,
+    market: &Market,
+    order_state: &OrderState,
+    oracle_price: i128,
+) -> ClearingHouseResult {
+    match order.order_type {
+        OrderType::Market => validate_market_order(order)?,
+        OrderType::Limit => validate_limit_order(order)?,
+        OrderType::TriggerMarket => validate_trigger_market_order(order)?,
+        OrderType::TriggerLimit => validate_trigger_limit_order(order)?,
+    }
+
+    if order.post_only == 1 {
+        validate_post_only_order(order, market, oracle_price)?;
+    }
+
+    // 按base资产数量下单时，不能低于市场的最小交易量
+    if order.base_asset_amount != 0
+        && order.base_asset_amount < market.amm.minimum_base_asset_trade_size
+    {
+        return Err(Errors::OrderAmountTooSmall);
+    }
+
+    // 订单的quote资产数量估计值不能低于order_state.min_order_quote_asset_amount
+    if calculate_estimated_quote_asset_amount(order, market)?
+        < order_state.min_order_quote_asset_amount
+    {
+        return Err(Errors::OrderAmountTooSmall);
+    }
+
+    Ok(())
+}
+
+// 只做Maker的订单只能是限价单（不能同时为IOC），且下单时不能与AMM立即成交
+// 挂钩预言机价格的限价单以下单时读取的预言机价格计算限价
+fn validate_post_only_order(
+    order: &Order,
+    market: &Market,
+    oracle_price: i128,
+) -> ClearingHouseResult {
+    if order.order_type != OrderType::Limit || order.immediate_or_cancel == 1 {
+        return Err(Errors::InvalidOrder);
+    }
+
+    let limit_price = calculate_limit_price(order, oracle_price)?;
+    let mark_price = market.amm.mark_price()?;
+    let would_cross = match order.direction {
+        PositionDirection::Long => limit_price > mark_price,
+        PositionDirection::Short => limit_price < mark_price,
+    };
+    if would_cross {
+        return Err(Errors::PostOnlyOrderWouldCross);
+    }
+
+    Ok(())
+}
+
+// 市价单必须且只能指定base资产数量和quote资产数量中的一个，且不能指定触发价格
+fn validate_market_order(order: &Order) -> ClearingHouseResult {
+    if order.trigger_price != 0 {
+        return Err(Errors::InvalidOrder);
+    }
+    validate_market_order_amounts(order)
+}
+
+// 限价单必须指定限价（或预言机价格偏移量）和base资产数量，且不能指定触发价格
+fn validate_limit_order(order: &Order) -> ClearingHouseResult {
+    if order.trigger_price != 0 {
+        return Err(Errors::InvalidOrder);
+    }
+    validate_limit_order_amounts(order)
+}
+
+// 触发市价单必须指定触发价格，触发后按市价单成交
+fn validate_trigger_market_order(order: &Order) -> ClearingHouseResult {
+    if order.trigger_price == 0 {
+        return Err(Errors::InvalidOrder);
+    }
+    validate_market_order_amounts(order)
+}
+
+// 触发限价单必须指定触发价格，触发后按限价单成交
+fn validate_trigger_limit_order(order: &Order) -> ClearingHouseResult {
+    if order.trigger_price == 0 {
+        return Err(Errors::InvalidOrder);
+    }
+    validate_limit_order_amounts(order)
+}
+
+// 市价单不能挂钩预言机价格
+fn validate_market_order_amounts(order: &Order) -> ClearingHouseResult {
+    if order.oracle_price_offset != 0 {
+        return Err(Errors::InvalidOrder);
+    }
+    if (order.base_asset_amount == 0) == (order.quote_asset_amount == 0) {
+        return Err(Errors::InvalidOrder);
+    }
+
+    Ok(())
+}
+
+// 限价单必须且只能指定限价和预言机价格偏移量中的一个
+fn validate_limit_order_amounts(order: &Order) -> ClearingHouseResult {
+    if (order.price == 0) == (order.oracle_price_offset == 0) {
+        return Err(Errors::InvalidOrder);
+    }
+    if order.base_asset_amount == 0 || order.quote_asset_amount != 0 {
+        return Err(Errors::InvalidOrder);
+    }
+
+    Ok(())
+}
+
+// 订单的quote资产数量估计值（QUOTE_PRECISION）
+// 按base资产数量下单时，以限价估算；未指定限价时，触发单以触发价格、其余（包括挂钩预言机价格的限价单）以当前标记价格估算
+fn calculate_estimated_quote_asset_amount(
+    order: &Order,
+    market: &Market,
+) -> ClearingHouseResult<u128> {
+    if order.quote_asset_amount != 0 {
+        return Ok(order.quote_asset_amount);
+    }
+
+    let price = if order.price != 0 {
+        order.price
+    } else if order.trigger_price != 0 {
+        order.trigger_price
+    } else {
+        market.amm.mark_price()?
+    };
+
+    order
+        .base_asset_amount
+        .checked_mul(price)
+        .ok_or_else(math_error!())?
+        .checked_div(MARK_PRICE_TIMES_AMM_TO_QUOTE_PRECISION_RATIO)
+        .ok_or_else(math_error!())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::{
+        AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION,
+    };
+    use crate::state::OrderFillerRewardStructure;
+    use bytemuck::Zeroable;
+
+    #[test]
+    fn test_validate_order() {
+        // 标记价格为100
+        let mut market = Market::zeroed();
+        market.amm.base_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        market.amm.quote_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        market.amm.peg_multiplier = 100 * PEG_PRECISION;
+        market.amm.minimum_base_asset_trade_size = 10000000;
+        // 预言机价格为100
+        let oracle_price = (100 * MARK_PRICE_PRECISION) as i128;
+        let order_state = OrderState {
+            order_history: Pubkey::default(),
+            order_filler_reward_structure: OrderFillerRewardStructure {
+                reward_numerator: 1,
+                reward_denominator: 10,
+                time_based_reward_lower_bound: 10_000,
+            },
+            min_order_quote_asset_amount: 500_000,
+            order_expiry_reward: 10_000,
+        };
+
+        // 市价单：按base资产数量下单（价值100 USDC）
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::Market;
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        // 同时指定quote资产数量
+        order.quote_asset_amount = 100 * QUOTE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        // 只按quote资产数量下单
+        order.base_asset_amount = 0;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        // quote资产数量低于0.5 USDC
+        order.quote_asset_amount = QUOTE_PRECISION / 4;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+
+        // 限价单
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::Limit;
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        order.price = 90 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        // 按限价估算的quote资产数量为0.45 USDC
+        order.base_asset_amount = AMM_RESERVE_PRECISION / 200;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        // 低于市场的最小交易量
+        order.base_asset_amount = 1;
+        order.price = 1_000_000_000 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        // 限价单不能指定触发价格
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        order.price = 90 * MARK_PRICE_PRECISION;
+        order.trigger_price = 95 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+
+        // 触发限价单
+        order.order_type = OrderType::TriggerLimit;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        order.trigger_price = 0;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+
+        // 挂钩预言机价格的限价单
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::Limit;
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        order.oracle_price_offset = -(MARK_PRICE_PRECISION as i128);
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        // 不能同时指定限价
+        order.price = 90 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        // 市价单不能挂钩预言机价格
+        order.order_type = OrderType::Market;
+        order.price = 0;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+
+        // 只做Maker的限价买单：限价不能高于标记价格
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::Limit;
+        order.post_only = 1;
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        order.price = 101 * MARK_PRICE_PRECISION;
+        assert!(matches!(
+            validate_order(&order, &market, &order_state, oracle_price),
+            Err(Errors::PostOnlyOrderWouldCross)
+        ));
+        order.price = 99 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+        // 不能同时为IOC
+        order.immediate_or_cancel = 1;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        // 挂钩预言机价格的限价卖单：预言机价格为98，限价为99
+        order.immediate_or_cancel = 0;
+        order.direction = PositionDirection::Short;
+        order.price = 0;
+        order.oracle_price_offset = MARK_PRICE_PRECISION as i128;
+        assert!(matches!(
+            validate_order(
+                &order,
+                &market,
+                &order_state,
+                (98 * MARK_PRICE_PRECISION) as i128
+            ),
+            Err(Errors::PostOnlyOrderWouldCross)
+        ));
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+
+        // 触发市价单：按触发价格估算的quote资产数量为0.45 USDC
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::TriggerMarket;
+        order.base_asset_amount = AMM_RESERVE_PRECISION / 200;
+        order.trigger_price = 90 * MARK_PRICE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_err());
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        assert!(validate_order(&order, &market, &order_state, oracle_price).is_ok());
+    }
+
+    #[test]
+    fn test_validate_post_only_order_with_oracle_price() {
+        // 标记价格为100，AMM记录的预言机价格为100
+        let mut market = Market::zeroed();
+        market.amm.base_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        market.amm.quote_asset_reserve = 1000 * AMM_RESERVE_PRECISION;
+        market.amm.peg_multiplier = 100 * PEG_PRECISION;
+        market.amm.last_oracle_price = (100 * MARK_PRICE_PRECISION) as i128;
+
+        // 挂钩预言机价格的只做Maker限价买单，限价 = 预言机价格 - 1
+        let mut order = Order::zeroed();
+        order.order_type = OrderType::Limit;
+        order.post_only = 1;
+        order.direction = PositionDirection::Long;
+        order.base_asset_amount = AMM_RESERVE_PRECISION;
+        order.oracle_price_offset = -(MARK_PRICE_PRECISION as i128);
+
+        // 以AMM记录的预言机价格计算时限价为99，不会立即成交
+        assert!(validate_post_only_order(&order, &market, market.amm.last_oracle_price).is_ok());
+        // 最新的预言机价格为102时限价为101，高于标记价格，会立即成交
+        assert!(matches!(
+            validate_post_only_order(&order, &market, (102 * MARK_PRICE_PRECISION) as i128),
+            Err(Errors::PostOnlyOrderWouldCross)
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+use anchor_lang::prelude::*;
+use static_assertions::const_assert_eq;
+
+use crate::PositionDirection;
+
+#[account(zero_copy)]
+pub struct BankruptcyHistory {
+    // 作为循环缓冲区的指针，指示下一个记录应该写入的位置
+    head: u64,
+    padding: [u8; 8],
+    bankruptcy_records: [BankruptcyRecord; 1024],
+}
+
+const_assert_eq!(std::mem::size_of::<BankruptcyHistory>(), 245776);
+
+// 用户穿仓后，每个市场上分摊的亏损是如何被弥补的
+#[zero_copy]
+pub struct BankruptcyRecord {
+    pub ts: i64,                             // 时间戳
+    pub market_index: u64,                   // 市场索引
+    pub record_id: u128,                     // 记录的唯一ID
+    pub user_authority: Pubkey,              // 穿仓用户的钱包地址
+    pub user: Pubkey,                        // 穿仓用户在协议中的账户地址
+    pub liquidator: Pubkey,                  // 执行清算操作的账户公钥
+    pub bad_debt: u128,                      // 用户穿仓的总亏损
+    pub loss: u128,                          // 分摊到该市场的亏损
+    pub insurance_fund_payment: u128,        // 由insurance_vault弥补的部分
+    pub fee_pool_payment: u128,              // 由该市场AMM的total_fee_minus_distributions弥补的部分
+    pub socialized_loss: u128,               // 通过累计资金费率分摊给对手方的部分
+    pub cumulative_funding_rate_delta: i128, // 对手方累计资金费率的变化量
+    pub direction: PositionDirection,        // 承担社会化亏损的一方（穿仓仓位的对手方）
+    pub padding: [u8; 15],
+}
+
+impl BankruptcyHistory {
+    // 增添bankruptcy_record
+    pub fn append(&mut self, bankruptcy_record: BankruptcyRecord) {
+        self.bankruptcy_records[Self::index(self.head)] = bankruptcy_record;
+        self.head = (self.head + 1) % 1024;
+    }
+
+    // 将u64安全转为usize
+    pub fn index(counter: u64) -> usize {
+        std::convert::TryInto::try_into(counter).unwrap()
+    }
+
+    // 下一个record的record_id
+    // 注： self.head会在0~1023之间来回递增，而每个record.record_id一直单向递增
+    pub fn next_record_id(&self) -> u128 {
+        let pre_record_id = if self.head == 0 { 1023 } else { self.head - 1 };
+        let pre_record = &self.bankruptcy_records[Self::index(pre_record_id)];
+        pre_record.record_id + 1
+    }
+}
@@ -1,3 +1,6 @@
+pub mod bankruptcy_history;
+pub use bankruptcy_history::*;
+
 pub mod curve_history;
 pub use curve_history::*;
 
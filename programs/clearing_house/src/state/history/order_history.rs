@@ -12,7 +12,7 @@ pub struct OrderHistory {
     order_records: [OrderRecord; 1024],
 }
 
-const_assert_eq!(std::mem::size_of::<OrderHistory>(), 458784);
+const_assert_eq!(std::mem::size_of::<OrderHistory>(), 475168);
 
 #[zero_copy]
 pub struct OrderRecord {
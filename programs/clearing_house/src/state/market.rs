@@ -1,18 +1,12 @@
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
-use pyth_solana_receiver_sdk::price_update::PriceUpdateV2;
 use static_assertions::const_assert_eq;
 use std::mem::size_of;
 
 use crate::{
     errors::Errors,
-    math::{
-        amm,
-        bn::ClearingHouseResult,
-        cast::{cast, cast_to_i128, cast_to_i64, cast_to_u128},
-        constant::MARK_PRICE_PRECISION,
-    },
-    math_error,
+    math::{amm, bn::ClearingHouseResult, margin::MarginType},
+    state::{load_oracle_reader, OraclePriceData, OracleReader},
 };
 
 #[account(zero_copy)]
@@ -21,7 +15,7 @@ pub struct Markets {
     pub markets: [Market; 64],
 }
 
-const_assert_eq!(size_of::<Markets>(), 31744);
+const_assert_eq!(size_of::<Markets>(), 35840);
 
 impl Markets {
     // 将u64转换成usize，作为Markets.markets的索引
@@ -64,6 +58,15 @@ impl Market {
             false
         }
     }
+
+    // 获得margin_type对应的保证金率
+    pub fn get_margin_ratio(&self, margin_type: MarginType) -> u32 {
+        match margin_type {
+            MarginType::Init => self.margin_ratio_initial,
+            MarginType::Partial => self.margin_ratio_partial,
+            MarginType::Maint => self.margin_ratio_maintenance,
+        }
+    }
 }
 
 #[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
@@ -71,6 +74,7 @@ impl Market {
 pub enum OracleSource {
     Pyth,
     Switchboard,
+    Admin, // 由管理员设置价格（AdminOracle账户）
 }
 
 unsafe impl Zeroable for OracleSource {}
@@ -84,12 +88,12 @@ pub struct AMM {
     // 资金费率与重新锚定
     pub cumulative_repeg_rebate_long: u128, // 多头累计重新锚定返利
     pub cumulative_repeg_rebate_short: u128, // 空头累计重新锚定返利
-    pub cumulative_funding_rate_long: u128, // 多头累计资金费率
-    pub cumulative_funding_rate_short: u128, // 空头累计资金费率
-    pub last_funding_rate: i128,            // 最近的资金费率
-    pub last_funding_rate_ts: i64,          // 最近更新资金费率的时间戳
-    pub funding_period: i64,                // 资金费率计算周期
-    pub peg_multiplier: u128,               // 锚定乘数，用于调整AMM价格与目标价格的偏差
+    pub cumulative_funding_rate_long: i128, // 多头累计资金费率（精度为MARK_PRICE_PRECISION*FUNDING_PAYMENT_PRECISION）
+    pub cumulative_funding_rate_short: i128, // 空头累计资金费率（精度为MARK_PRICE_PRECISION*FUNDING_PAYMENT_PRECISION）
+    pub last_funding_rate: i128,             // 最近的资金费率
+    pub last_funding_rate_ts: i64,           // 最近更新资金费率的时间戳
+    pub funding_period: i64,                 // 资金费率计算周期
+    pub peg_multiplier: u128,                // 锚定乘数，用于调整AMM价格与目标价格的偏差
     // fee相关
     pub total_fee: u128,                     // 累计总费用
     pub total_fee_minus_distributions: u128, // 总费用减去分配部分
@@ -104,10 +108,11 @@ pub struct AMM {
     pub last_oracle_price_twap_ts: i64, // 最近更新预言机TWAP的时间戳
     pub last_oracle_price_twap: i128,   // 最近的预言机价格的时间加权平均
     pub oracle: Pubkey,                 // oracle地址
+    pub oracle_program: Pubkey,         // oracle账户的owner program
+    pub pyth_feed_id: [u8; 32],         // pyth价格的feed id（非pyth预言机为全0）
     pub last_oracle_price: i128,        // 最新的预言机价格
     pub base_spread: u16,               // 基础点差(以基点表示)
     pub oracle_source: OracleSource,    // 预言机类型
-    // pub pyth_feed_id: [u8; 32],         //查所需的feed id
     pub padding: [u8; 13],
 }
 
@@ -121,114 +126,35 @@ impl AMM {
         )
     }
 
-    // 从pyth中获取价格信息
-    pub fn get_pyth_price(
+    // 校验预言机账户的owner program和pyth的feed id与该市场初始化时记录的一致后，加载对应的OracleReader
+    pub fn load_oracle_reader(
         &self,
         price_oracle: &AccountInfo,
-        clock_slot: u64,
-    ) -> ClearingHouseResult<OraclePriceData> {
-        let pyth_price_data = price_oracle
-            .try_borrow_data()
-            .map_err(|_| Errors::FailToLoadOracle)?;
-        let price_update_v2 = PriceUpdateV2::deserialize(&mut &pyth_price_data[8..])
-            .map_err(|_| Errors::FailToDeserialize)?;
-        // 从pyth取到的价格
-        let pyth_price = cast_to_i128(price_update_v2.price_message.price)?;
-        // 从pyth取到的价格的置信区间
-        let pyth_price_conf = cast_to_u128(price_update_v2.price_message.conf)?;
-        // 从pyth取到的价格精度
-        let pyth_price_precision =
-            10u128.pow(price_update_v2.price_message.exponent.unsigned_abs());
-
-        // 为了将pyth喂上来的价格精度统一到市场价格精度，pyth_scale_mul为需要扩大的倍数，pyth_scale_div为需要缩小的倍数
-        let mut pyth_scale_mul = 1;
-        let mut pyth_scale_div = 1;
-        if pyth_price_precision > MARK_PRICE_PRECISION {
-            // 如果pyth的给的价格精度>MARK_PRICE_PRECISION，
-            // 那么pyth价格精度需要缩小pyth_scale_div=pyth_price_precision/MARK_PRICE_PRECISION
-            pyth_scale_div = pyth_price_precision
-                .checked_div(MARK_PRICE_PRECISION)
-                .ok_or_else(math_error!())?;
-        } else {
-            // 如果pyth的给的价格精度<=MARK_PRICE_PRECISION，
-            // 那么pyth价格精度需要扩大pyth_scale_div=MARK_PRICE_PRECISION/pyth_price_precision
-            pyth_scale_mul = MARK_PRICE_PRECISION
-                .checked_div(pyth_price_precision)
-                .ok_or_else(math_error!())?;
+    ) -> ClearingHouseResult<Box<dyn OracleReader>> {
+        if !price_oracle.owner.eq(&self.oracle_program) {
+            return Err(Errors::InvalidOracleOwner);
         }
 
-        // 被提升到市场价格精度的价格，类型为i28
-        let pyth_price_scaled = pyth_price
-            // pyth_scale_mul和pyth_scale_div从u128变成i128
-            .checked_mul(cast(pyth_scale_mul)?)
-            .ok_or_else(math_error!())?
-            .checked_div(cast(pyth_scale_div)?)
-            .ok_or_else(math_error!())?;
-
-        // 从pyth取到的价格的置信区间也提升到市场价格精度
-        let pyth_price_conf_scaled = pyth_price_conf
-            // pyth_scale_mul和pyth_scale_div从u128变成i128
-            .checked_mul(pyth_scale_mul)
-            .ok_or_else(math_error!())?
-            .checked_div(pyth_scale_div)
-            .ok_or_else(math_error!())?;
-
-        // pyth价格发布的slot与当前slot的差值
-        let pyth_price_delay = cast_to_i64(clock_slot)?
-            .checked_sub(cast(price_update_v2.posted_slot)?)
-            .ok_or_else(math_error!())?;
-
-        Ok(OraclePriceData {
-            price: pyth_price_scaled,
-            confidence: pyth_price_conf_scaled,
-            delay: pyth_price_delay,
-            // 对于pyth的价格，该值永远为true
-            has_sufficient_number_of_data_points: true,
-        })
-    }
-
-    // 从pyth中获取ema价格
-    pub fn get_pyth_ema_price(&self, price_oracle: &AccountInfo) -> ClearingHouseResult<i128> {
-        let pyth_price_data = price_oracle
-            .try_borrow_data()
-            .map_err(|_| Errors::FailToLoadOracle)?;
-        let price_update_v2 = PriceUpdateV2::deserialize(&mut &pyth_price_data[8..])
-            .map_err(|_| Errors::FailToDeserialize)?;
-        // 从pyth取到的ema价格
-        let pyth_ema_price = cast_to_i128(price_update_v2.price_message.ema_price)?;
-        let pyth_price_precision =
-            10u128.pow(price_update_v2.price_message.exponent.unsigned_abs());
-
-        // 为了将pyth喂上来的价格精度统一到市场价格精度，pyth_scale_mul为需要扩大的倍数，pyth_scale_div为需要缩小的倍数
-        let mut pyth_scale_mul = 1;
-        let mut pyth_scale_div = 1;
-        if pyth_price_precision > MARK_PRICE_PRECISION {
-            // 如果pyth的给的价格精度>MARK_PRICE_PRECISION，
-            // 那么pyth价格精度需要缩小pyth_scale_div=pyth_price_precision/MARK_PRICE_PRECISION
-            pyth_scale_div = pyth_price_precision
-                .checked_div(MARK_PRICE_PRECISION)
-                .ok_or_else(math_error!())?;
-        } else {
-            // 如果pyth的给的价格精度<=MARK_PRICE_PRECISION，
-            // 那么pyth价格精度需要扩大pyth_scale_div=MARK_PRICE_PRECISION/pyth_price_precision
-            pyth_scale_mul = MARK_PRICE_PRECISION
-                .checked_div(pyth_price_precision)
-                .ok_or_else(math_error!())?;
+        let oracle_reader = load_oracle_reader(self.oracle_source, price_oracle)?;
+        if oracle_reader.get_feed_id() != self.pyth_feed_id {
+            return Err(Errors::InvalidOracleFeedId);
         }
 
-        let pyth_ema_price_scaled = pyth_ema_price
-            .checked_mul(cast(pyth_scale_mul)?)
-            .ok_or_else(math_error!())?
-            .checked_div(cast(pyth_scale_div)?)
-            .ok_or_else(math_error!())?;
+        Ok(oracle_reader)
+    }
 
-        Ok(pyth_ema_price_scaled)
+    // 根据预言机类型，从预言机中获取价格信息
+    pub fn get_oracle_price(
+        &self,
+        price_oracle: &AccountInfo,
+        clock_slot: u64,
+    ) -> ClearingHouseResult<OraclePriceData> {
+        self.load_oracle_reader(price_oracle)?
+            .get_price_data(clock_slot)
     }
-}
 
-pub struct OraclePriceData {
-    pub price: i128, // 从预言机获取的资产价格（已根据MARK_PRICE_PRECISION提升了精度）
-    pub confidence: u128, // 价格的可信度/置信区间，值越小表示价格越可信
-    pub delay: i64,  // 表示价格数据的延迟时间(以slot为单位)，即当前slot-价格数据最后更新的slot
-    pub has_sufficient_number_of_data_points: bool, // 表示预言机数据是否有足够的数据点支持。对于Switchboard而言，检查确认的轮次是否达到最小预言机结果数。对于Pyth预言机，这个值总是true
+    // 根据预言机类型，从预言机中获取价格TWAP
+    pub fn get_oracle_twap(&self, price_oracle: &AccountInfo) -> ClearingHouseResult<i128> {
+        self.load_oracle_reader(price_oracle)?.get_twap()
+    }
 }
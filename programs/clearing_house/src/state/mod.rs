@@ -4,12 +4,18 @@ pub use history::*;
 pub mod market;
 pub use market::*;
 
+pub mod oracle;
+pub use oracle::*;
+
 pub mod order_state;
 pub use order_state::*;
 
 pub mod state;
 pub use state::*;
 
+pub mod switchboard;
+pub use switchboard::*;
+
 pub mod user;
 pub use user::*;
 
@@ -0,0 +1,249 @@
+use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::{PriceUpdateV2, VerificationLevel};
+
+use crate::{
+    errors::Errors,
+    math::{
+        bn::ClearingHouseResult,
+        cast::{cast, cast_to_i64},
+        oracle::scale_to_mark_price_precision,
+    },
+    math_error,
+    state::{AggregatorAccountData, OracleSource},
+};
+
+pub struct OraclePriceData {
+    pub price: i128, // 从预言机获取的资产价格（已根据MARK_PRICE_PRECISION提升了精度）
+    pub confidence: u128, // 价格的可信度/置信区间，值越小表示价格越可信
+    pub delay: i64,  // 表示价格数据的延迟时间(以slot为单位)，即当前slot-价格数据最后更新的slot
+    pub has_sufficient_number_of_data_points: bool, // 表示预言机数据是否有足够的数据点支持。对于Switchboard而言，检查确认的轮次是否达到最小预言机结果数。对于Pyth预言机，这个值总是true
+}
+
+// 预言机读取接口，每种预言机类型（OracleSource）各有一个实现，读出的价格均已提升到MARK_PRICE_PRECISION
+pub trait OracleReader {
+    // 价格、置信区间和延迟等价格信息
+    fn get_price_data(&self, clock_slot: u64) -> ClearingHouseResult<OraclePriceData>;
+    // 价格的时间加权平均，用作AMM中预言机价格TWAP的初始值
+    fn get_twap(&self) -> ClearingHouseResult<i128>;
+    // 价格的feed id，只有pyth的价格有feed id，其余返回全0
+    fn get_feed_id(&self) -> [u8; 32] {
+        [0; 32]
+    }
+}
+
+// 各类预言机账户的owner program，本地测试（mock-oracles）时为对应的mock程序
+#[cfg(not(feature = "mock-oracles"))]
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey = pyth_solana_receiver_sdk::ID;
+#[cfg(feature = "mock-oracles")]
+pub const PYTH_RECEIVER_PROGRAM_ID: Pubkey =
+    pubkey!("GxGELgceihUaxGqhfCYbuPwgwyFcaBM2GPPzR75997E4");
+#[cfg(not(feature = "mock-oracles"))]
+pub const SWITCHBOARD_PROGRAM_ID: Pubkey = pubkey!("SW1TCH7qEPTdLsDHRgPuMQjbQxKdH2aBStViMFnt64f");
+#[cfg(feature = "mock-oracles")]
+pub const SWITCHBOARD_PROGRAM_ID: Pubkey = pubkey!("5GYT96XpWBHooztcvkdaTJAeZkgNtYXQ2jmQt6BW6QDw");
+
+// 校验预言机账户的owner program与预言机类型匹配
+pub fn validate_oracle_owner(
+    oracle_source: OracleSource,
+    price_oracle: &AccountInfo,
+) -> ClearingHouseResult {
+    let expected_owner = match oracle_source {
+        OracleSource::Pyth => PYTH_RECEIVER_PROGRAM_ID,
+        OracleSource::Switchboard => SWITCHBOARD_PROGRAM_ID,
+        OracleSource::Admin => crate::ID,
+    };
+
+    if !price_oracle.owner.eq(&expected_owner) {
+        return Err(Errors::InvalidOracleOwner);
+    }
+
+    Ok(())
+}
+
+// 根据预言机类型，从预言机账户中加载对应的OracleReader
+pub fn load_oracle_reader(
+    oracle_source: OracleSource,
+    price_oracle: &AccountInfo,
+) -> ClearingHouseResult<Box<dyn OracleReader>> {
+    let oracle_data = price_oracle
+        .try_borrow_data()
+        .map_err(|_| Errors::FailToLoadOracle)?;
+
+    let oracle_reader: Box<dyn OracleReader> = match oracle_source {
+        OracleSource::Pyth => {
+            // 跳过8字节的账户discriminator，账户数据不足8字节时返回错误
+            let mut price_update_data = oracle_data.get(8..).ok_or(Errors::FailToLoadOracle)?;
+            let price_update = PriceUpdateV2::deserialize(&mut price_update_data)
+                .map_err(|_| Errors::FailToDeserialize)?;
+            // 只接受已完成全部Wormhole guardian签名验证的价格
+            if price_update.verification_level != VerificationLevel::Full {
+                return Err(Errors::OracleNotFullyVerified);
+            }
+            Box::new(price_update)
+        }
+        OracleSource::Switchboard => Box::new(AggregatorAccountData::load(&oracle_data)?),
+        OracleSource::Admin => {
+            // 管理员设置的价格账户必须由本program创建
+            if !price_oracle.owner.eq(&crate::ID) {
+                return Err(Errors::InvalidOracle);
+            }
+            Box::new(
+                AdminOracle::try_deserialize(&mut &oracle_data[..])
+                    .map_err(|_| Errors::FailToDeserialize)?,
+            )
+        }
+    };
+
+    Ok(oracle_reader)
+}
+
+impl OracleReader for PriceUpdateV2 {
+    fn get_price_data(&self, clock_slot: u64) -> ClearingHouseResult<OraclePriceData> {
+        let exponent = self.price_message.exponent;
+        let price = scale_to_mark_price_precision(cast(self.price_message.price)?, exponent)?;
+        let confidence =
+            scale_to_mark_price_precision(cast(self.price_message.conf)?, exponent)?.unsigned_abs();
+
+        // pyth价格发布的slot与当前slot的差值
+        let delay = cast_to_i64(clock_slot)?
+            .checked_sub(cast(self.posted_slot)?)
+            .ok_or_else(math_error!())?;
+
+        Ok(OraclePriceData {
+            price,
+            confidence,
+            delay,
+            // 对于pyth的价格，该值永远为true
+            has_sufficient_number_of_data_points: true,
+        })
+    }
+
+    // pyth的ema价格
+    fn get_twap(&self) -> ClearingHouseResult<i128> {
+        scale_to_mark_price_precision(
+            cast(self.price_message.ema_price)?,
+            self.price_message.exponent,
+        )
+    }
+
+    fn get_feed_id(&self) -> [u8; 32] {
+        self.price_message.feed_id
+    }
+}
+
+impl OracleReader for AggregatorAccountData {
+    // 从switchboard聚合器最新确认的轮次中获取价格信息
+    fn get_price_data(&self, clock_slot: u64) -> ClearingHouseResult<OraclePriceData> {
+        let round = self.latest_confirmed_round;
+
+        // 置信区间取各预言机结果的标准差
+        let price = round.result.to_mark_price_precision()?;
+        let confidence = round
+            .std_deviation
+            .to_mark_price_precision()?
+            .unsigned_abs();
+
+        // 最新确认轮次开启的slot与当前slot的差值
+        let delay = cast_to_i64(clock_slot)?
+            .checked_sub(cast(round.round_open_slot)?)
+            .ok_or_else(math_error!())?;
+
+        Ok(OraclePriceData {
+            price,
+            confidence,
+            delay,
+            // 成功返回结果的预言机数量需达到聚合器要求的最少预言机结果数
+            has_sufficient_number_of_data_points: round.num_success >= self.min_oracle_results,
+        })
+    }
+
+    // switchboard聚合器不提供TWAP，取最新确认轮次的价格
+    fn get_twap(&self) -> ClearingHouseResult<i128> {
+        let round = self.latest_confirmed_round;
+        round.result.to_mark_price_precision()
+    }
+}
+
+// 由管理员设置的价格（紧急情况下由管理员控制价格）
+#[account]
+pub struct AdminOracle {
+    pub price: i128,           // 价格（MARK_PRICE_PRECISION）
+    pub confidence: u128,      // 置信区间（MARK_PRICE_PRECISION）
+    pub last_update_slot: u64, // 最近一次设置价格的slot
+}
+
+impl OracleReader for AdminOracle {
+    fn get_price_data(&self, clock_slot: u64) -> ClearingHouseResult<OraclePriceData> {
+        Ok(OraclePriceData {
+            price: self.price,
+            confidence: self.confidence,
+            delay: cast_to_i64(clock_slot)?
+                .checked_sub(cast(self.last_update_slot)?)
+                .ok_or_else(math_error!())?,
+            has_sufficient_number_of_data_points: true,
+        })
+    }
+
+    // 管理员设置的价格不提供TWAP，取当前价格
+    fn get_twap(&self) -> ClearingHouseResult<i128> {
+        Ok(self.price)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_oracle_reader_with_short_data() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [0u8; 4];
+        let price_oracle = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &owner,
+            false,
+            0,
+        );
+
+        // 账户数据不足8字节时返回错误而不是panic
+        assert!(matches!(
+            load_oracle_reader(OracleSource::Pyth, &price_oracle),
+            Err(Errors::FailToLoadOracle)
+        ));
+        assert!(load_oracle_reader(OracleSource::Switchboard, &price_oracle).is_err());
+    }
+
+    #[test]
+    fn test_validate_oracle_owner() {
+        let key = Pubkey::new_unique();
+        let mut lamports = 0;
+        let mut data = [0u8; 0];
+        let price_oracle = AccountInfo::new(
+            &key,
+            false,
+            false,
+            &mut lamports,
+            &mut data,
+            &SWITCHBOARD_PROGRAM_ID,
+            false,
+            0,
+        );
+
+        assert!(validate_oracle_owner(OracleSource::Switchboard, &price_oracle).is_ok());
+        // owner program与预言机类型不匹配
+        assert!(matches!(
+            validate_oracle_owner(OracleSource::Pyth, &price_oracle),
+            Err(Errors::InvalidOracleOwner)
+        ));
+        assert!(matches!(
+            validate_oracle_owner(OracleSource::Admin, &price_oracle),
+            Err(Errors::InvalidOracleOwner)
+        ));
+    }
+}
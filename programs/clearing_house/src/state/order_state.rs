@@ -7,9 +7,10 @@ pub struct OrderState {
     pub order_history: Pubkey, // 存储order历史记录的账户
     pub order_filler_reward_structure: OrderFillerRewardStructure, // order填充者的奖励结构
     pub min_order_quote_asset_amount: u128, // 订单成功放置所需的最小quote资产金额估计值
+    pub order_expiry_reward: u128, // keeper每移除一个过期订单获得的奖励，从用户的可用抵押品中支付
 }
 
-const_assert_eq!(std::mem::size_of::<OrderState>(), 96);
+const_assert_eq!(std::mem::size_of::<OrderState>(), 112);
 
 #[derive(Clone, AnchorSerialize, AnchorDeserialize)]
 pub struct OrderFillerRewardStructure {
@@ -42,8 +42,9 @@ pub struct State {
     pub max_deposit: u128,                                      // 最大存款限额
     pub extended_curve_history: Pubkey,                         // 扩展的曲线历史记录账户地址
     pub order_state: Pubkey,                                    // 订单状态账户地址
+    pub bankruptcy_history: Pubkey,                             // 穿仓历史记录账户地址
     // Upgrade ability
-    pub padding1: [u128; 4],
+    pub padding1: [u128; 2],
 }
 
 const_assert_eq!(size_of::<State>(), 1200);
@@ -0,0 +1,134 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::hash;
+use bytemuck::{Pod, Zeroable};
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
+
+use crate::{
+    errors::Errors,
+    math::{bn::ClearingHouseResult, cast::cast, oracle::scale_to_mark_price_precision},
+};
+
+// Switchboard V2的AggregatorAccountData前部字段的内存布局（repr(packed)），只包含读取价格所需的字段及其之前的字段
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct AggregatorAccountData {
+    pub name: [u8; 32],
+    pub metadata: [u8; 128],
+    pub reserved1: [u8; 32],
+    pub queue_pubkey: Pubkey,
+    pub oracle_request_batch_size: u32,
+    pub min_oracle_results: u32, // 一个轮次被接受所需的最少预言机结果数
+    pub min_job_results: u32,
+    pub min_update_delay_seconds: u32,
+    pub start_after: i64,
+    pub variance_threshold: SwitchboardDecimal,
+    pub force_report_period: i64,
+    pub expiration: i64,
+    pub consecutive_failure_count: u64,
+    pub next_allowed_update_time: i64,
+    pub is_locked: u8,
+    pub crank_pubkey: Pubkey,
+    pub latest_confirmed_round: AggregatorRound, // 最新确认的轮次
+}
+
+const_assert_eq!(size_of::<AggregatorAccountData>(), 398);
+
+unsafe impl Zeroable for AggregatorAccountData {}
+unsafe impl Pod for AggregatorAccountData {}
+
+impl AggregatorAccountData {
+    // 从账户数据中读取AggregatorAccountData：前8字节为anchor的discriminator，即sha256("account:AggregatorAccountData")的前8字节
+    pub fn load(data: &[u8]) -> ClearingHouseResult<AggregatorAccountData> {
+        let discriminator = &hash(b"account:AggregatorAccountData").to_bytes()[..8];
+        if data.len() < 8 + size_of::<AggregatorAccountData>() || data[..8] != *discriminator {
+            return Err(Errors::FailToDeserialize);
+        }
+
+        Ok(*bytemuck::from_bytes(
+            &data[8..8 + size_of::<AggregatorAccountData>()],
+        ))
+    }
+}
+
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct AggregatorRound {
+    pub num_success: u32, // 本轮次成功返回结果的预言机数量
+    pub num_error: u32,
+    pub is_closed: u8,
+    pub round_open_slot: u64, // 本轮次开启时的slot
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,        // 本轮次的结果（价格）
+    pub std_deviation: SwitchboardDecimal, // 本轮次各预言机结果的标准差
+}
+
+// 十进制数：mantissa / 10^scale
+#[derive(Clone, Copy)]
+#[repr(C, packed)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
+
+impl SwitchboardDecimal {
+    // 转换为MARK_PRICE_PRECISION精度
+    pub fn to_mark_price_precision(&self) -> ClearingHouseResult<i128> {
+        let exponent = -cast::<u32, i32>(self.scale)?;
+        scale_to_mark_price_precision(self.mantissa, exponent)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::math::constant::MARK_PRICE_PRECISION;
+
+    #[test]
+    fn test_to_mark_price_precision() {
+        // 123.456
+        let decimal = SwitchboardDecimal {
+            mantissa: 123_456,
+            scale: 3,
+        };
+        assert_eq!(
+            decimal.to_mark_price_precision().unwrap(),
+            123_456 * MARK_PRICE_PRECISION as i128 / 1000
+        );
+
+        // 精度高于MARK_PRICE_PRECISION时截断多余的小数位
+        let decimal = SwitchboardDecimal {
+            mantissa: -1_234_567_890_123,
+            scale: 12,
+        };
+        assert_eq!(decimal.to_mark_price_precision().unwrap(), -12_345_678_901);
+    }
+
+    #[test]
+    fn test_load() {
+        let mut aggregator = AggregatorAccountData::zeroed();
+        aggregator.min_oracle_results = 3;
+        aggregator.latest_confirmed_round.result = SwitchboardDecimal {
+            mantissa: 42,
+            scale: 0,
+        };
+
+        let mut data = hash(b"account:AggregatorAccountData").to_bytes()[..8].to_vec();
+        data.extend_from_slice(bytemuck::bytes_of(&aggregator));
+        let loaded = AggregatorAccountData::load(&data).unwrap();
+        assert_eq!({ loaded.min_oracle_results }, 3);
+        assert_eq!(
+            loaded
+                .latest_confirmed_round
+                .result
+                .to_mark_price_precision()
+                .unwrap(),
+            42 * MARK_PRICE_PRECISION as i128
+        );
+
+        // discriminator不匹配或数据长度不足
+        data[0] ^= 1;
+        assert!(AggregatorAccountData::load(&data).is_err());
+        assert!(AggregatorAccountData::load(&data[..100]).is_err());
+    }
+}
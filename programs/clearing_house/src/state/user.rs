@@ -20,9 +20,10 @@ pub struct User {
     pub forgo_position_settlement: u8, // 标志位，表示用户是否放弃持仓结算
     pub has_settled_position: u8, // 标志位，表示是否有已结算的持仓
     pub padding: [u8; 14],
+    pub referrer: Pubkey, // 推荐人的User账户地址（初始化User时登记，无推荐人时为Pubkey::default()）
 }
 
-const_assert_eq!(size_of::<User>(), 208);
+const_assert_eq!(size_of::<User>(), 240);
 
 #[account(zero_copy)]
 pub struct UserPositions {
@@ -42,8 +43,7 @@ pub struct MarketPosition {
     // 用户在该市场的base资产持仓量。
     // 正数表示多头头寸（买入并持有基础资产），负数表示空头头寸（借入并卖出基础资产），0表示无持仓
     pub base_asset_amount: i128,
-    // 用户在该市场的quote资产持仓量
-    // 正数表示多头头寸（买入并持有基础资产），负数表示空头头寸（借入并卖出基础资产），0表示无持仓
+    // 用户在该市场的仓位的开仓成本（quote资产数量，非负），用于计算未实现盈亏
     pub quote_asset_amount: i128,
     // 最后一次更新头寸时的累计资金费率
     pub last_cumulative_funding_rate: i128,
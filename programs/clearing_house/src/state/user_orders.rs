@@ -1,7 +1,47 @@
 use anchor_lang::prelude::*;
 use bytemuck::{Pod, Zeroable};
+use static_assertions::const_assert_eq;
+use std::mem::size_of;
 
 use crate::controller::position::PositionDirection;
+use crate::optional_accounts::ManagePositionOptionalAccounts;
+
+// 每个用户的订单账户（pda，seeds为[b"user_orders", user地址]），最多同时存在32个订单
+#[account(zero_copy)]
+pub struct UserOrders {
+    // user key
+    pub user: Pubkey,
+    pub orders: [Order; 32],
+}
+
+const_assert_eq!(size_of::<UserOrders>(), 7712);
+
+impl UserOrders {
+    // 找到一个可用的订单槽的索引
+    pub fn get_available_order_index(&self) -> Option<usize> {
+        self.orders.iter().position(|order| order.is_available())
+    }
+
+    // 找到order_id对应的订单的索引
+    pub fn get_order_index(&self, order_id: u128) -> Option<usize> {
+        if order_id == 0 {
+            return None;
+        }
+        self.orders
+            .iter()
+            .position(|order| order.order_id == order_id)
+    }
+
+    // 找到用户自定义的user_order_id对应的订单的索引
+    pub fn get_order_index_by_user_order_id(&self, user_order_id: u8) -> Option<usize> {
+        if user_order_id == 0 {
+            return None;
+        }
+        self.orders
+            .iter()
+            .position(|order| !order.is_available() && order.user_order_id == user_order_id)
+    }
+}
 
 #[zero_copy]
 pub struct Order {
@@ -15,8 +55,10 @@ pub struct Order {
     pub discount_tier: OrderDiscountTier,         // 手续费折扣等级（从无折扣到4级折扣）
     pub trigger_condition: OrderTriggerCondition, // 订单触发条件
     pub padding: [u8; 7],
-    pub ts: i64,                         // 订单创建时间戳
-    pub market_index: u64,               // 交易对的市场索引（如 BTC/USDC=0）
+    pub ts: i64,           // 订单创建时间戳
+    pub market_index: u64, // 交易对的市场索引（如 BTC/USDC=0）
+    pub max_ts: i64,       // 订单的过期时间戳，为0时永不过期
+    pub padding1: [u8; 8],
     pub order_id: u128,                  // 全局唯一订单ID
     pub price: u128,                     // 订单限价（原始价格）
     pub user_base_asset_amount: i128,    // 用户期望的base资产数量（可正负）
@@ -30,7 +72,42 @@ pub struct Order {
     pub oracle_price_offset: i128,       // 相对于预言机价格的偏移量（动态定价）
 }
 
-#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize)]
+const_assert_eq!(size_of::<Order>(), 240);
+
+impl Order {
+    // 订单槽是否可用（全局订单ID从1开始，未被占用的订单槽order_id为0）
+    pub fn is_available(&self) -> bool {
+        self.order_id == 0
+    }
+
+    // 订单是否已过期（max_ts为0的订单永不过期）
+    pub fn is_expired(&self, now: i64) -> bool {
+        !self.is_available() && self.max_ts != 0 && now > self.max_ts
+    }
+}
+
+// 下单参数
+#[derive(Clone, Copy, AnchorSerialize, AnchorDeserialize)]
+pub struct OrderParams {
+    pub order_type: OrderType,
+    pub direction: PositionDirection,
+    pub user_order_id: u8,
+    pub quote_asset_amount: u128, // 市价单可按quote资产数量下单（QUOTE_PRECISION）
+    pub base_asset_amount: u128,  // 按base资产数量下单（AMM_RESERVE_PRECISION）
+    pub price: u128,              // 限价（MARK_PRICE_PRECISION），市价单为0时不限价
+    pub market_index: u64,
+    pub trigger_price: u128, // 触发单的触发价格（MARK_PRICE_PRECISION），非触发单为0
+    pub trigger_condition: OrderTriggerCondition,
+    pub reduce_only: bool, // 仅减仓：成交不能增加仓位，成交数量不超过剩余仓位
+    pub post_only: bool,   // 只做Maker：仅限限价单，下单时不能与AMM立即成交
+    pub immediate_or_cancel: bool, // IOC：成交后立即撤销未成交的剩余部分
+    pub max_ts: i64,       // 订单的过期时间戳，为0时永不过期
+    pub oracle_price_offset: i128, // 限价单的有效限价为成交时的预言机价格加上该偏移量（MARK_PRICE_PRECISION），此时price需为0
+    // 下单时只使用其中的discount_token（用于确定订单的手续费折扣等级），推荐人取自User.referrer
+    pub optional_accounts: ManagePositionOptionalAccounts,
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
 #[repr(u8)]
 pub enum OrderStatus {
     Init, // 订单已创建但未开放（如触发单等待条件）
@@ -40,7 +117,7 @@ pub enum OrderStatus {
 unsafe impl Zeroable for OrderStatus {}
 unsafe impl Pod for OrderStatus {}
 
-#[derive(Copy, Clone, AnchorSerialize, AnchorDeserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, AnchorSerialize, AnchorDeserialize)]
 #[repr(u8)]
 pub enum OrderType {
     Market,        // 市价单
@@ -74,3 +151,24 @@ pub enum OrderTriggerCondition {
 
 unsafe impl Zeroable for OrderTriggerCondition {}
 unsafe impl Pod for OrderTriggerCondition {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_expired() {
+        let mut order = Order::zeroed();
+        order.max_ts = 100;
+        // 空订单槽不会过期
+        assert!(!order.is_expired(101));
+
+        order.order_id = 1;
+        assert!(!order.is_expired(100));
+        assert!(order.is_expired(101));
+
+        // max_ts为0的订单永不过期
+        order.max_ts = 0;
+        assert!(!order.is_expired(i64::MAX));
+    }
+}
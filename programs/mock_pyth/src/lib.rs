@@ -1,5 +1,6 @@
 #![allow(unexpected_cfgs)]
 use anchor_lang::prelude::*;
+use pyth_solana_receiver_sdk::price_update::VerificationLevel;
 use state::PriceUpdate;
 
 mod state;
@@ -19,6 +20,9 @@ pub mod mock_pyth {
         ema_conf: u64,
     ) -> Result<()> {
         let price_update = &mut ctx.accounts.price;
+        // 模拟已完成全部guardian签名验证的价格
+        price_update.verification_level = VerificationLevel::Full;
+        price_update.posted_slot = Clock::get()?.slot;
         price_update.price_message.price = price;
         price_update.price_message.conf = conf;
         price_update.price_message.exponent = exponent;
@@ -30,6 +34,7 @@ pub mod mock_pyth {
 
     pub fn set_price(ctx: Context<SetPrice>, price: i64) -> Result<()> {
         ctx.accounts.price.price_message.price = price;
+        ctx.accounts.price.posted_slot = Clock::get()?.slot;
         Ok(())
     }
 
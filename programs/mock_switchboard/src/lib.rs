@@ -0,0 +1,80 @@
+#![allow(unexpected_cfgs)]
+use anchor_lang::prelude::*;
+use state::{AggregatorAccountData, SwitchboardDecimal};
+
+mod state;
+
+declare_id!("5GYT96XpWBHooztcvkdaTJAeZkgNtYXQ2jmQt6BW6QDw");
+
+#[program]
+pub mod mock_switchboard {
+    use super::*;
+
+    pub fn initialize_aggregator(
+        ctx: Context<InitializeAggregator>,
+        // 价格 = price / 10^scale
+        price: i128,
+        scale: u32,
+        // 标准差，与价格使用相同的scale
+        std_deviation: i128,
+        min_oracle_results: u32,
+        num_success: u32,
+    ) -> Result<()> {
+        let aggregator = &mut ctx.accounts.aggregator.load_init()?;
+        aggregator.min_oracle_results = min_oracle_results;
+        aggregator.latest_confirmed_round.num_success = num_success;
+        aggregator.latest_confirmed_round.round_open_slot = Clock::get()?.slot;
+        aggregator.latest_confirmed_round.round_open_timestamp = Clock::get()?.unix_timestamp;
+        aggregator.latest_confirmed_round.result = SwitchboardDecimal {
+            mantissa: price,
+            scale,
+        };
+        aggregator.latest_confirmed_round.std_deviation = SwitchboardDecimal {
+            mantissa: std_deviation,
+            scale,
+        };
+
+        Ok(())
+    }
+
+    // 设置价格（scale不变），并将最新确认轮次的开启slot更新为当前slot
+    pub fn set_price(ctx: Context<SetAggregator>, price: i128) -> Result<()> {
+        let aggregator = &mut ctx.accounts.aggregator.load_mut()?;
+        let scale = aggregator.latest_confirmed_round.result.scale;
+        aggregator.latest_confirmed_round.result = SwitchboardDecimal {
+            mantissa: price,
+            scale,
+        };
+        aggregator.latest_confirmed_round.round_open_slot = Clock::get()?.slot;
+        aggregator.latest_confirmed_round.round_open_timestamp = Clock::get()?.unix_timestamp;
+        Ok(())
+    }
+
+    pub fn set_num_success(ctx: Context<SetAggregator>, num_success: u32) -> Result<()> {
+        ctx.accounts
+            .aggregator
+            .load_mut()?
+            .latest_confirmed_round
+            .num_success = num_success;
+        Ok(())
+    }
+}
+
+#[derive(Accounts)]
+pub struct SetAggregator<'info> {
+    #[account(mut)]
+    pub aggregator: AccountLoader<'info, AggregatorAccountData>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeAggregator<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + std::mem::size_of::<AggregatorAccountData>(),
+    )]
+    pub aggregator: AccountLoader<'info, AggregatorAccountData>,
+    pub system_program: Program<'info, System>,
+}
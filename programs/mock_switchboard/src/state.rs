@@ -0,0 +1,45 @@
+use anchor_lang::prelude::*;
+
+// 与Switchboard V2的AggregatorAccountData前部字段的内存布局完全一致（repr(C, packed)），
+// 且账户名相同，因此anchor生成的discriminator也与Switchboard V2一致。clearing_house只读取这些字段
+#[account(zero_copy(unsafe))]
+#[repr(C, packed)]
+pub struct AggregatorAccountData {
+    pub name: [u8; 32],
+    pub metadata: [u8; 128],
+    pub reserved1: [u8; 32],
+    pub queue_pubkey: Pubkey,
+    pub oracle_request_batch_size: u32,
+    pub min_oracle_results: u32, // 一个轮次被接受所需的最少预言机结果数
+    pub min_job_results: u32,
+    pub min_update_delay_seconds: u32,
+    pub start_after: i64,
+    pub variance_threshold: SwitchboardDecimal,
+    pub force_report_period: i64,
+    pub expiration: i64,
+    pub consecutive_failure_count: u64,
+    pub next_allowed_update_time: i64,
+    pub is_locked: u8,
+    pub crank_pubkey: Pubkey,
+    pub latest_confirmed_round: AggregatorRound, // 最新确认的轮次
+}
+
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+pub struct AggregatorRound {
+    pub num_success: u32, // 本轮次成功返回结果的预言机数量
+    pub num_error: u32,
+    pub is_closed: u8,
+    pub round_open_slot: u64, // 本轮次开启时的slot
+    pub round_open_timestamp: i64,
+    pub result: SwitchboardDecimal,        // 本轮次的结果（价格）
+    pub std_deviation: SwitchboardDecimal, // 本轮次各预言机结果的标准差
+}
+
+// 十进制数：mantissa / 10^scale
+#[zero_copy(unsafe)]
+#[repr(C, packed)]
+pub struct SwitchboardDecimal {
+    pub mantissa: i128,
+    pub scale: u32,
+}
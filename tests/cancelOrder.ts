@@ -0,0 +1,129 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: cancel_order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    let userAuthority: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+
+        // 订单1、3做多，订单2做空，user_order_id与订单ID相同
+        const orders = [
+            [PositionDirection.LONG, 99],
+            [PositionDirection.SHORT, 101],
+            [PositionDirection.LONG, 98],
+        ];
+        for (let i = 0; i < orders.length; i++) {
+            const [direction, limitPrice] = orders[i];
+            await testCli.placeOrder(getOrderParams({
+                orderType: OrderType.LIMIT,
+                direction,
+                userOrderId: i + 1,
+                baseAssetAmount: AMM_RESERVE_PRECISION,
+                price: MARK_PRICE_PRECISION.muln(limitPrice as number),
+                marketIndex,
+            }));
+        }
+    });
+
+    it('Fail if order does not exist', async () => {
+        await requireCustomError(
+            testCli.cancelOrder(new BN(100)),
+            'OrderDoesNotExist'
+        );
+        await requireCustomError(
+            testCli.cancelOrderByUserId(100),
+            'OrderDoesNotExist'
+        );
+    });
+
+    it('Pass cancel_order', async () => {
+        await testCli.cancelOrder(new BN(1));
+
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[0].orderId, ZERO);
+        requireBNEq(orders[1].orderId, new BN(2));
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(2));
+
+        // 3条下单记录之后为撤单记录
+        const orderHistory = await testCli.getOrderHistory();
+        requireBNEq(orderHistory.head, new BN(4));
+        const record = orderHistory.orderRecords[3];
+        expect(record.action).deep.eq(OrderAction.CANCEL);
+        requireBNEq(record.order.orderId, new BN(1));
+    });
+
+    it('Fail if order already cancelled', async () => {
+        await requireCustomError(
+            testCli.cancelOrder(new BN(1)),
+            'OrderDoesNotExist'
+        );
+        await requireCustomError(
+            testCli.cancelOrderByUserId(1),
+            'OrderDoesNotExist'
+        );
+    });
+
+    it('Pass cancel_order_by_user_id', async () => {
+        await testCli.cancelOrderByUserId(2);
+
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[1].orderId, ZERO);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(1));
+
+        const record = (await testCli.getOrderHistory()).orderRecords[4];
+        expect(record.action).deep.eq(OrderAction.CANCEL);
+        requireBNEq(record.order.orderId, new BN(2));
+    });
+
+    it('Pass cancel_all_orders', async () => {
+        // 只撤销做空的订单时，剩余的做多订单不受影响
+        await testCli.cancelAllOrders(null, PositionDirection.SHORT);
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[2].orderId, new BN(3));
+        requireBNEq((await testCli.getOrderHistory()).head, new BN(5));
+
+        await testCli.cancelAllOrders(marketIndex, null);
+
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[2].orderId, ZERO);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, ZERO);
+
+        const record = (await testCli.getOrderHistory()).orderRecords[5];
+        expect(record.action).deep.eq(OrderAction.CANCEL);
+        requireBNEq(record.order.orderId, new BN(3));
+    });
+});
@@ -0,0 +1,87 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { requireBNEq, requireCustomError } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { PositionDirection } from "./utils/types";
+
+describe("clearing house: close_position", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const quoteAssetAmount = new BN(200).mul(QUOTE_PRECISION);
+    // 手续费率为10/10000
+    const fee = quoteAssetAmount.muln(10).divn(10000);
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if user has no position in market', async () => {
+        await requireCustomError(
+            testCli.closePosition(marketIndex),
+            'UserHasNoPositionInMarket'
+        );
+    });
+
+    it('Pass', async () => {
+        const signer = testCli.getCurrentSigner();
+        await testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex);
+        const baseAssetAmount = (await testCli.getUserPositions(signer.publicKey)).positions[0].baseAssetAmount;
+
+        await testCli.closePosition(marketIndex);
+
+        const position = (await testCli.getUserPositions(signer.publicKey)).positions[0];
+        requireBNEq(position.baseAssetAmount, ZERO);
+        requireBNEq(position.quoteAssetAmount, ZERO);
+
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        requireBNEq(market.baseAssetAmountLong, ZERO);
+        requireBNEq(market.baseAssetAmount, ZERO);
+        requireBNEq(market.openInterest, ZERO);
+
+        // 开仓和平仓各收取一次手续费
+        const user = await testCli.getUser(signer.publicKey);
+        expect(user.totalFeePaid.gt(fee)).eq(true);
+        expect(user.collateral.lt(collateral)).eq(true);
+        requireBNEq(market.amm.totalFee, user.totalFeePaid);
+
+        const record = (await testCli.getTradeHistory()).tradeRecord[1];
+        requireBNEq(record.recordId, new BN(2));
+        expect(record.direction).deep.eq(PositionDirection.SHORT);
+        requireBNEq(record.baseAssetAmount, baseAssetAmount);
+        requireBNEq(record.fee, user.totalFeePaid.sub(fee));
+        expect(record.liquidation).eq(0);
+    });
+
+    it('Fail if position already closed', async () => {
+        await requireCustomError(
+            testCli.closePosition(marketIndex),
+            'UserHasNoPositionInMarket'
+        );
+    });
+});
@@ -2,6 +2,8 @@ import { BN } from "@coral-xyz/anchor";
 
 export const PEG_PRECISION = new BN(10 ** 3);
 export const MARK_PRICE_PRECISION = new BN(10 ** 10);
+export const AMM_RESERVE_PRECISION = new BN(10 ** 13);
+export const QUOTE_PRECISION = new BN(10 ** 6);
 export const MARGIN_PRECISION = 10000;
 export const MAXIMUM_MARGIN_RATIO = MARGIN_PRECISION;
 export const MINIMUM_MARGIN_RATIO = MARGIN_PRECISION / 50;
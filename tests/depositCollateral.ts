@@ -0,0 +1,81 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { getAccount } from '@solana/spl-token';
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+
+describe("clearing house: deposit_collateral", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const amount = new BN(100).mul(QUOTE_PRECISION);
+    let userCollateralAccount;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2, true, false);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        userCollateralAccount = await testCli.createCollateralAccount(amount);
+    });
+
+    it('Fail if amount is 0', async () => {
+        await requireCustomError(
+            testCli.depositCollateral(ZERO, userCollateralAccount),
+            'InsufficientDeposit'
+        );
+    });
+
+    it('Fail if signer not user authority', async () => {
+        const signer = testCli.getSignerByIndex(0);
+        const userAuthority = testCli.getSignerByIndex(1).publicKey;
+        const user = await testCli.getUser(userAuthority);
+        await requireCustomError(
+            testCli.clearingHouse.methods.depositCollateral(amount)
+                .accounts({
+                    authority: signer.publicKey,
+                    state: testCli.state,
+                    user: testCli.getUserAddress(userAuthority),
+                    collateralVault: testCli.collateralVault,
+                    userCollateralAccount,
+                    markets: testCli.markets,
+                    userPositions: user.positons,
+                    fundingPaymentHistory: testCli.fundingPaymentHistory,
+                    depositHistory: testCli.depositHistory,
+                } as any)
+                .signers([signer])
+                .rpc(),
+            'ConstraintHasOne'
+        );
+    });
+
+    it('Pass', async () => {
+        const signer = testCli.getCurrentSigner();
+        await testCli.depositCollateral(amount, userCollateralAccount);
+
+        const user = await testCli.getUser(signer.publicKey);
+        requireBNEq(user.collateral, amount);
+        requireBNEq(user.cumculativeDeposits, amount);
+
+        expect((await getAccount(provider.connection, userCollateralAccount)).amount).eq(BigInt(0));
+        expect((await getAccount(provider.connection, testCli.collateralVault)).amount).eq(BigInt(amount.toString()));
+
+        const depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(1));
+        const record = depositHistory.depositRecords[0];
+        requireBNEq(record.recordId, new BN(1));
+        requireBNEq(record.amount, amount);
+        requirePublickeyEq(record.userAuthority, signer.publicKey);
+        requirePublickeyEq(record.user, testCli.getUserAddress(signer.publicKey));
+        requireBNEq(record.collateralBefore, ZERO);
+        requireBNEq(record.cumulativeDepositsBefore, ZERO);
+        expect(record.direction).deep.eq({ deposit: {} });
+    });
+});
@@ -0,0 +1,119 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError, requirePublickeyEq, sleep } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: expire_orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    // 每个过期订单的keeper奖励
+    const orderExpiryReward = new BN(10_000);
+
+    // signers[1]为下单的用户，signers[2]为keeper
+    let userAuthority: web3.PublicKey;
+
+    // 链上的当前时间
+    async function getChainTime(): Promise<number> {
+        return await provider.connection.getBlockTime(await provider.connection.getSlot());
+    }
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+
+        // 订单1在3秒后过期，订单2不会过期
+        const maxTs = new BN((await getChainTime()) + 3);
+        const limitOrderParams = getOrderParams({
+            orderType: OrderType.LIMIT,
+            direction: PositionDirection.LONG,
+            baseAssetAmount: AMM_RESERVE_PRECISION,
+            price: MARK_PRICE_PRECISION.muln(99),
+            marketIndex,
+        });
+        await testCli.placeOrder({ ...limitOrderParams, maxTs });
+        await testCli.placeOrder(limitOrderParams);
+
+        // 等待链上时间超过订单1的过期时间
+        while ((await getChainTime()) <= maxTs.toNumber()) {
+            await sleep(1000);
+        }
+    });
+
+    it('Fail if keeper expires own orders', async () => {
+        await requireCustomError(
+            testCli.expireOrders(userAuthority),
+            'ConstraintRaw'
+        );
+    });
+
+    it('Fail to fill expired order', async () => {
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1)),
+            'OrderExpired'
+        );
+    });
+
+    it('Pass', async () => {
+        const keeper = testCli.getCurrentSigner().publicKey;
+        await testCli.expireOrders(userAuthority);
+
+        // 只移除过期的订单
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        requireBNEq(orders[0].orderId, ZERO);
+        requireBNEq(orders[1].orderId, new BN(2));
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(1));
+
+        // keeper奖励从用户的抵押品中支付
+        requireBNEq((await testCli.getUser(userAuthority)).collateral, collateral.sub(orderExpiryReward));
+        requireBNEq((await testCli.getUser(keeper)).collateral, orderExpiryReward);
+
+        const orderHistory = await testCli.getOrderHistory();
+        requireBNEq(orderHistory.head, new BN(3));
+        const record = orderHistory.orderRecords[2];
+        expect(record.action).deep.eq(OrderAction.EXPIRE);
+        requireBNEq(record.order.orderId, new BN(1));
+        requirePublickeyEq(record.filler, testCli.getUserAddress(keeper));
+        requireBNEq(record.fillerReward, orderExpiryReward);
+    });
+
+    it('Pass without expired orders', async () => {
+        await testCli.expireOrders(userAuthority);
+
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[1].orderId, new BN(2));
+        requireBNEq((await testCli.getUser(userAuthority)).collateral, collateral.sub(orderExpiryReward));
+        requireBNEq((await testCli.getOrderHistory()).head, new BN(3));
+    });
+});
@@ -0,0 +1,142 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: fill_order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const quoteAssetAmount = new BN(200).mul(QUOTE_PRECISION);
+    // 手续费率为10/10000，填充者奖励为手续费的1/10
+    const fee = quoteAssetAmount.muln(10).divn(10000);
+    const fillerReward = fee.divn(10);
+
+    // signers[1]为下单的用户，signers[2]为填充者
+    let userAuthority: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+
+        // 订单1：按quote资产数量做多的市价单
+        await testCli.placeOrder(getOrderParams({
+            orderType: OrderType.MARKET,
+            direction: PositionDirection.LONG,
+            quoteAssetAmount,
+            marketIndex,
+        }));
+        // 订单2：以99的价格做多的限价单，标记价格为100时无法成交
+        await testCli.placeOrder(getOrderParams({
+            orderType: OrderType.LIMIT,
+            direction: PositionDirection.LONG,
+            baseAssetAmount: AMM_RESERVE_PRECISION,
+            price: MARK_PRICE_PRECISION.muln(99),
+            marketIndex,
+        }));
+    });
+
+    it('Fail if filler fills own order', async () => {
+        testCli.changeCurrentSigner(1);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1)),
+            'ConstraintRaw'
+        );
+    });
+
+    it('Fail if order does not exist', async () => {
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(100)),
+            'OrderDoesNotExist'
+        );
+    });
+
+    it('Fail with invalid oracle', async () => {
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1), null, web3.Keypair.generate().publicKey),
+            'InvalidOracle'
+        );
+    });
+
+    it('Fail if limit price not reached', async () => {
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(2)),
+            'OrderCannotBeFilled'
+        );
+    });
+
+    it('Pass', async () => {
+        const filler = testCli.getCurrentSigner().publicKey;
+        await testCli.fillOrder(userAuthority, new BN(1));
+
+        // 订单全部成交后释放订单槽
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.quoteAssetAmount, quoteAssetAmount);
+        expect(position.baseAssetAmount.gt(ZERO)).eq(true);
+        requireBNEq(position.openOrders, new BN(1));
+
+        const user = await testCli.getUser(userAuthority);
+        requireBNEq(user.collateral, collateral.sub(fee));
+        requireBNEq(user.totalFeePaid, fee);
+
+        // 填充者奖励从计入市场的手续费中支付
+        requireBNEq((await testCli.getUser(filler)).collateral, fillerReward);
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        requireBNEq(market.amm.totalFee, fee.sub(fillerReward));
+
+        const tradeRecord = (await testCli.getTradeHistory()).tradeRecord[0];
+        requireBNEq(tradeRecord.quoteAssetAmount, quoteAssetAmount);
+        requireBNEq(tradeRecord.baseAssetAmount, position.baseAssetAmount);
+        requireBNEq(tradeRecord.fee, fee);
+
+        // 2条下单记录之后为成交记录
+        const record = (await testCli.getOrderHistory()).orderRecords[2];
+        expect(record.action).deep.eq(OrderAction.FILL);
+        requireBNEq(record.order.orderId, new BN(1));
+        requirePublickeyEq(record.filler, testCli.getUserAddress(filler));
+        requireBNEq(record.tradeRecordId, tradeRecord.recordId);
+        requireBNEq(record.quoteAssetAmountFilled, quoteAssetAmount);
+        requireBNEq(record.fee, fee);
+        requireBNEq(record.fillerReward, fillerReward);
+    });
+
+    it('Fail if order already filled', async () => {
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1)),
+            'OrderDoesNotExist'
+        );
+    });
+});
@@ -62,7 +62,7 @@ describe("clearing house: initialize", () => {
     it('Fail if initialize again with another state and markets', async () => {
         const [otherState, otherMarkets] = await createAccounts(
             provider,
-            [8 + 1200, 8 + 31744],
+            [8 + 1200, 8 + 35840],
             testCli.clearingHouse.programId
         );
 
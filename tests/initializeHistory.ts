@@ -34,6 +34,7 @@ describe("clearing house: initialize_history", () => {
         requirePublickeyEq(state.fundingPaymentHistory, web3.PublicKey.default);
         requirePublickeyEq(state.liquidationHistory, web3.PublicKey.default);
         requirePublickeyEq(state.curveHistory, web3.PublicKey.default);
+        requirePublickeyEq(state.bankruptcyHistory, web3.PublicKey.default);
 
         await testCli.initializeHistory();
         state = await testCli.getState();
@@ -43,6 +44,7 @@ describe("clearing house: initialize_history", () => {
         requirePublickeyEq(state.fundingPaymentHistory, testCli.fundingPaymentHistory);
         requirePublickeyEq(state.liquidationHistory, testCli.liquidationHistory);
         requirePublickeyEq(state.curveHistory, testCli.curveHistory);
+        requirePublickeyEq(state.bankruptcyHistory, testCli.bankruptcyHistory);
 
         const tradeHistory = await testCli.getTradeHistory();
         requireBNEq(tradeHistory.head, ZERO);
@@ -67,6 +69,10 @@ describe("clearing house: initialize_history", () => {
         const curveHistory = await testCli.getCurveHistory();
         requireBNEq(curveHistory.head, ZERO);
         expect(curveHistory.curveRecords.length).eq(1024);
+
+        const bankruptcyHistory = await testCli.getBankruptcyHistory();
+        requireBNEq(bankruptcyHistory.head, ZERO);
+        expect(bankruptcyHistory.bankruptcyRecords.length).eq(1024);
     });
 
     it('Fail if reinitialize', async () => {
@@ -75,6 +81,11 @@ describe("clearing house: initialize_history", () => {
             [8 + 262160, 8 + 147472, 8 + 262160, 8 + 196624, 8 + 114704, 8 + 311312],
             testCli.clearingHouse.programId
         );
+        const [newBankruptcyHistory] = await createAccounts(
+            provider,
+            [8 + 245776],
+            testCli.clearingHouse.programId
+        );
         const signer = testCli.getCurrentSigner();
         await requireCustomError(
             testCli.clearingHouse.methods.intializeHistory()
@@ -87,6 +98,7 @@ describe("clearing house: initialize_history", () => {
                     depositHistory: newDepositHistory,
                     fundingRateHistory: newFundingRateHistory,
                     curveHistory: newCurveHistory,
+                    bankruptcyHistory: newBankruptcyHistory,
                 } as any)
                 .signers([signer])
                 .rpc(),
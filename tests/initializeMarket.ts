@@ -311,6 +311,8 @@ describe("clearing house: initialize_market", () => {
         expect(market.marginRatioPartial).eq(marginRatioPartial);
         expect(market.marginRatioMaintenance).eq(marginRatioMaintenance);
         expect(market.amm.oracleSource).deep.eq(OracleSource.PYTH);
+        expect(market.amm.oracle).deep.eq(testCli.pythPriceFeed);
+        expect(market.amm.oracleProgram).deep.eq(testCli.mockPyth.programId);
         requireBNEq(market.amm.baseAssetReserve, ammBaseAssetReserve);
         requireBNEq(market.amm.quoteAssetReserve, ammQuoteAssetReserve);
         requireBNEq(market.amm.sqrtK, ammBaseAssetReserve);
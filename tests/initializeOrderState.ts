@@ -40,6 +40,7 @@ describe("clearing house: initialize_order_state", () => {
         requireBNEq(orderState.orderFillerRewardStructure.rewardDenominator, new BN(10));
         requireBNEq(orderState.orderFillerRewardStructure.timeBasedRewardLowerBound, new BN(10000));
         requireBNEq(orderState.minOrderQuoteAssetAmount, new BN(500000));
+        requireBNEq(orderState.orderExpiryReward, new BN(10000));
 
         const orderHistory = await testCli.getOrderHistory();
         requireBNEq(orderHistory.head, ZERO);
@@ -50,7 +51,7 @@ describe("clearing house: initialize_order_state", () => {
     it('Fail if reinitialize', async () => {
         const [newOrderHistory] = await createAccounts(
             provider,
-            [8 + 458784],
+            [8 + 475168],
             testCli.clearingHouse.programId
         );
         const signer = testCli.getCurrentSigner();
@@ -0,0 +1,115 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getAccount } from '@solana/spl-token';
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { PositionDirection } from "./utils/types";
+
+describe("clearing house: liquidate", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    // signers[1]为被清算的用户，signers[2]为推动标记价格的对手方，signers[3]为清算人
+    let userAuthority: web3.PublicKey;
+    let liquidatorAccount: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 4);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        // 用户以4倍杠杆做多
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        const userCollateral = new BN(10).mul(QUOTE_PRECISION);
+        let userCollateralAccount = await testCli.createCollateralAccount(userCollateral);
+        await testCli.depositCollateral(userCollateral, userCollateralAccount);
+        await testCli.openPosition(PositionDirection.LONG, new BN(40).mul(QUOTE_PRECISION), marketIndex);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+        const counterpartyCollateral = new BN(5000).mul(QUOTE_PRECISION);
+        userCollateralAccount = await testCli.createCollateralAccount(counterpartyCollateral);
+        await testCli.depositCollateral(counterpartyCollateral, userCollateralAccount);
+
+        testCli.changeCurrentSigner(3);
+        liquidatorAccount = await testCli.createCollateralAccount(ZERO);
+    });
+
+    it('Fail if oracle not passed in remaining accounts', async () => {
+        await requireCustomError(
+            testCli.liquidate(userAuthority, liquidatorAccount, []),
+            'OracleNotFound'
+        );
+    });
+
+    it('Fail if user has sufficient collateral', async () => {
+        await requireCustomError(
+            testCli.liquidate(userAuthority, liquidatorAccount),
+            'SufficientCollateral'
+        );
+    });
+
+    it('Pass full liquidation', async () => {
+        // 预言机价格下跌到84，对手方做空将标记价格压低到约78（与预言机价格的偏离不超过10%）
+        await testCli.pythSetPrice(new BN(84 * web3.LAMPORTS_PER_SOL));
+        testCli.changeCurrentSigner(2);
+        await testCli.openPosition(PositionDirection.SHORT, new BN(11700).mul(QUOTE_PRECISION), marketIndex);
+
+        testCli.changeCurrentSigner(3);
+        const liquidator = testCli.getCurrentSigner().publicKey;
+        await testCli.liquidate(userAuthority, liquidatorAccount);
+
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, ZERO);
+        requireBNEq(position.quoteAssetAmount, ZERO);
+
+        const liquidationHistory = await testCli.getLiquidationHistory();
+        requireBNEq(liquidationHistory.head, new BN(1));
+        const record = liquidationHistory.liquidationRecords[0];
+        requireBNEq(record.recordId, new BN(1));
+        requirePublickeyEq(record.userAuthority, userAuthority);
+        requirePublickeyEq(record.user, testCli.getUserAddress(userAuthority));
+        requirePublickeyEq(record.liquidator, liquidator);
+        expect(record.partial).eq(0);
+        expect(record.unrealizedPnl.isNeg()).eq(true);
+        expect(record.baseAssetValueClosed.gt(ZERO)).eq(true);
+        expect(record.feeToLiquidator.gt(ZERO)).eq(true);
+        requireBNEq(record.liquidationFee, record.feeToLiquidator.add(record.feeToInsuranceFund));
+
+        // 清算人获得清算罚金中的份额，其余转入insurance_vault
+        expect((await getAccount(provider.connection, liquidatorAccount)).amount).eq(BigInt(record.feeToLiquidator.toString()));
+        expect((await getAccount(provider.connection, testCli.insuranceVault)).amount).eq(BigInt(record.feeToInsuranceFund.toString()));
+
+        // 清算产生的交易记录
+        const tradeRecord = (await testCli.getTradeHistory()).tradeRecord[2];
+        expect(tradeRecord.liquidation).eq(1);
+        expect(tradeRecord.direction).deep.eq(PositionDirection.SHORT);
+        requireBNEq(tradeRecord.fee, ZERO);
+    });
+
+    it('Fail if user has no position', async () => {
+        await requireCustomError(
+            testCli.liquidate(userAuthority, liquidatorAccount),
+            'SufficientCollateral'
+        );
+    });
+});
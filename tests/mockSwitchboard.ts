@@ -0,0 +1,51 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { TestClient } from "./utils/testClient";
+import { requireBNEq } from "./utils/utils";
+import { expect } from "chai";
+
+describe("mock switchboard", () => {
+  const provider = anchor.AnchorProvider.env();
+  anchor.setProvider(provider);
+  let testCli: TestClient;
+
+  let price = new BN(100_000_000);
+  let scale = 6;
+  let stdDeviation = new BN(1_000_000);
+  let minOracleResults = 2;
+  let numSuccess = 3;
+
+  before(async () => {
+    testCli = await TestClient.create(provider, 1, false, false, true);
+  });
+
+
+  it("initialize_aggregator", async () => {
+    await testCli.switchboardInitializeAggregator(price, scale, stdDeviation, minOracleResults, numSuccess);
+    let aggregator = await testCli.getSwitchboardAggregator();
+    expect(minOracleResults).eq(aggregator.minOracleResults);
+    expect(numSuccess).eq(aggregator.latestConfirmedRound.numSuccess);
+    requireBNEq(price, aggregator.latestConfirmedRound.result.mantissa);
+    expect(scale).eq(aggregator.latestConfirmedRound.result.scale);
+    requireBNEq(stdDeviation, aggregator.latestConfirmedRound.stdDeviation.mantissa);
+    expect(scale).eq(aggregator.latestConfirmedRound.stdDeviation.scale);
+  });
+
+  it("set_price", async () => {
+    const roundOpenSlotBefore = (await testCli.getSwitchboardAggregator()).latestConfirmedRound.roundOpenSlot;
+    price = price.addn(1);
+    await testCli.switchboardSetPrice(price);
+    let aggregator = await testCli.getSwitchboardAggregator();
+    requireBNEq(price, aggregator.latestConfirmedRound.result.mantissa);
+    expect(scale).eq(aggregator.latestConfirmedRound.result.scale);
+    expect(aggregator.latestConfirmedRound.roundOpenSlot.gte(roundOpenSlotBefore)).eq(true);
+  });
+
+  it("set_num_success", async () => {
+    numSuccess = 1;
+    await testCli.switchboardSetNumSuccess(numSuccess);
+    let aggregator = await testCli.getSwitchboardAggregator();
+    expect(numSuccess).eq(aggregator.latestConfirmedRound.numSuccess);
+    expect(minOracleResults).eq(aggregator.minOracleResults);
+  });
+});
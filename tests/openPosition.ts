@@ -0,0 +1,111 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { PositionDirection } from "./utils/types";
+
+describe("clearing house: open_position", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const quoteAssetAmount = new BN(200).mul(QUOTE_PRECISION);
+    // 手续费率为10/10000
+    const fee = quoteAssetAmount.muln(10).divn(10000);
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if quote asset amount is 0', async () => {
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, ZERO, marketIndex),
+            'TradeSizeTooSmall'
+        );
+    });
+
+    it('Fail if market not initialized', async () => {
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, new BN(1)),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Fail with invalid oracle', async () => {
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex, ZERO, null, web3.Keypair.generate().publicKey),
+            'InvalidOracle'
+        );
+    });
+
+    it('Fail if entry price worse than limit price', async () => {
+        // 做多时成交均价会高于当前标记价格
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex, MARK_PRICE_PRECISION.muln(100)),
+            'SlippageOutsideLimit'
+        );
+    });
+
+    it('Pass', async () => {
+        const signer = testCli.getCurrentSigner();
+        await testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex, MARK_PRICE_PRECISION.muln(101));
+
+        const user = await testCli.getUser(signer.publicKey);
+        requireBNEq(user.collateral, collateral.sub(fee));
+        requireBNEq(user.totalFeePaid, fee);
+
+        const position = (await testCli.getUserPositions(signer.publicKey)).positions[0];
+        requireBNEq(position.marketIndex, marketIndex);
+        requireBNEq(position.quoteAssetAmount, quoteAssetAmount);
+        expect(position.baseAssetAmount.gt(ZERO)).eq(true);
+
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        requireBNEq(market.baseAssetAmountLong, position.baseAssetAmount);
+        requireBNEq(market.baseAssetAmount, position.baseAssetAmount);
+        requireBNEq(market.openInterest, new BN(1));
+        requireBNEq(market.amm.totalFee, fee);
+        requireBNEq(market.amm.totalFeeMinusDistributions, fee);
+
+        const record = (await testCli.getTradeHistory()).tradeRecord[0];
+        requireBNEq(record.recordId, new BN(1));
+        requirePublickeyEq(record.user, testCli.getUserAddress(signer.publicKey));
+        expect(record.direction).deep.eq(PositionDirection.LONG);
+        requireBNEq(record.baseAssetAmount, position.baseAssetAmount);
+        requireBNEq(record.quoteAssetAmount, quoteAssetAmount);
+        requireBNEq(record.fee, fee);
+        expect(record.liquidation).eq(0);
+        expect(record.markPriceAfter.gt(record.markPriceBefore)).eq(true);
+    });
+
+    it('Fail if initial margin not met', async () => {
+        // 总仓位价值约为1200，初始保证金要求约为240，超过抵押品
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, new BN(1000).mul(QUOTE_PRECISION), marketIndex),
+            'InsufficientCollateral'
+        );
+    });
+});
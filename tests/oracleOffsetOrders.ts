@@ -0,0 +1,110 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: oracle price offset orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const baseAssetAmount = AMM_RESERVE_PRECISION.divn(2);
+    // 以低于预言机价格5的价格做多0.5个base资产
+    const offsetOrderParams = getOrderParams({
+        orderType: OrderType.LIMIT,
+        direction: PositionDirection.LONG,
+        baseAssetAmount,
+        oraclePriceOffset: MARK_PRICE_PRECISION.muln(5).neg(),
+        marketIndex,
+    });
+
+    // signers[1]为下单的用户，signers[2]为填充者
+    let userAuthority: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if limit order specifies both price and oracle price offset', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...offsetOrderParams, price: MARK_PRICE_PRECISION.muln(95) }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Fail if market order specifies oracle price offset', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...offsetOrderParams, orderType: OrderType.MARKET }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Pass place', async () => {
+        await testCli.placeOrder(offsetOrderParams);
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders[0];
+        requireBNEq(order.orderId, new BN(1));
+        requireBNEq(order.price, ZERO);
+        requireBNEq(order.oraclePriceOffset, offsetOrderParams.oraclePriceOffset);
+    });
+
+    it('Fail if offset price below mark price', async () => {
+        // 有效限价为95，低于标记价格100
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1)),
+            'OrderCannotBeFilled'
+        );
+    });
+
+    it('Pass fill after oracle price moves', async () => {
+        // 预言机价格上涨到106，有效限价为101，高于标记价格
+        await testCli.pythSetPrice(new BN(106 * web3.LAMPORTS_PER_SOL));
+        await testCli.fillOrder(userAuthority, new BN(1));
+
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, baseAssetAmount);
+        requireBNEq(position.openOrders, ZERO);
+
+        // 成交后的标记价格不高于有效限价
+        const tradeRecord = (await testCli.getTradeHistory()).tradeRecord[0];
+        requireBNEq(tradeRecord.baseAssetAmount, baseAssetAmount);
+        expect(tradeRecord.markPriceAfter.lte(MARK_PRICE_PRECISION.muln(101))).eq(true);
+
+        const record = (await testCli.getOrderHistory()).orderRecords[1];
+        expect(record.action).deep.eq(OrderAction.FILL);
+        requireBNEq(record.baseAssetAmountFilled, baseAssetAmount);
+    });
+});
@@ -0,0 +1,156 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: post only, immediate or cancel and reduce only orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    // 以99的价格做多1个base资产，标记价格为100时无法成交
+    const limitOrderParams = getOrderParams({
+        orderType: OrderType.LIMIT,
+        direction: PositionDirection.LONG,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(99),
+        marketIndex,
+    });
+    // 仅减仓的做空1个base资产的市价单
+    const reduceOnlyParams = getOrderParams({
+        orderType: OrderType.MARKET,
+        direction: PositionDirection.SHORT,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        reduceOnly: true,
+        marketIndex,
+    });
+
+    // signers[1]为下单的用户，signers[2]为填充者
+    let userAuthority: web3.PublicKey;
+
+    // 订单ID为orderId的订单是否仍占用订单槽
+    async function hasOrder(orderId: number): Promise<boolean> {
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        return orders.some((order) => order.orderId.eq(new BN(orderId)));
+    }
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if post only order would cross', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, price: MARK_PRICE_PRECISION.muln(101), postOnly: true }),
+            'PostOnlyOrderWouldCross'
+        );
+    });
+
+    it('Fail if post only order is not a limit order or is immediate or cancel', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, postOnly: true, immediateOrCancel: true }),
+            'InvalidOrder'
+        );
+        await requireCustomError(
+            testCli.placeOrder({ ...reduceOnlyParams, reduceOnly: false, postOnly: true }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Pass place post only order', async () => {
+        await testCli.placeOrder({ ...limitOrderParams, postOnly: true });
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders[0];
+        requireBNEq(order.orderId, new BN(1));
+        expect(order.postOnly).eq(1);
+    });
+
+    it('Pass cancel immediate or cancel order that cannot be filled', async () => {
+        await testCli.placeOrder({ ...limitOrderParams, immediateOrCancel: true });
+
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(2));
+
+        expect(await hasOrder(2)).eq(false);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(1));
+        const record = (await testCli.getOrderHistory()).orderRecords[2];
+        expect(record.action).deep.eq(OrderAction.CANCEL);
+        requireBNEq(record.order.orderId, new BN(2));
+    });
+
+    it('Fail if reduce only order has no position to reduce', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder(reduceOnlyParams);
+
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(3)),
+            'OrderCannotBeFilled'
+        );
+    });
+
+    it('Pass cancel immediate or cancel reduce only order without position', async () => {
+        testCli.changeCurrentSigner(1);
+        await testCli.placeOrder({ ...reduceOnlyParams, immediateOrCancel: true });
+
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(4));
+
+        expect(await hasOrder(4)).eq(false);
+        const record = (await testCli.getOrderHistory()).orderRecords[5];
+        expect(record.action).deep.eq(OrderAction.CANCEL);
+        requireBNEq(record.order.orderId, new BN(4));
+    });
+
+    it('Pass cap reduce only order at position size', async () => {
+        // 做多约0.5个base资产，小于仅减仓订单的1个base资产
+        testCli.changeCurrentSigner(1);
+        await testCli.openPosition(PositionDirection.LONG, new BN(50).mul(QUOTE_PRECISION), marketIndex);
+        const baseAssetAmount = (await testCli.getUserPositions(userAuthority)).positions[0].baseAssetAmount;
+        await testCli.placeOrder(reduceOnlyParams);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.fillOrder(userAuthority, new BN(5));
+
+        // 仓位平掉后订单视为全部成交
+        expect(await hasOrder(5)).eq(false);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.baseAssetAmount, ZERO);
+        requireBNEq(position.openOrders, new BN(2));
+
+        const record = (await testCli.getOrderHistory()).orderRecords[7];
+        expect(record.action).deep.eq(OrderAction.FILL);
+        requireBNEq(record.order.orderId, new BN(5));
+        requireBNEq(record.baseAssetAmountFilled, baseAssetAmount);
+    });
+});
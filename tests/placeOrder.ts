@@ -0,0 +1,139 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderStatus, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: place_order", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    // 以99的价格做多1个base资产
+    const limitOrderParams = getOrderParams({
+        orderType: OrderType.LIMIT,
+        direction: PositionDirection.LONG,
+        userOrderId: 1,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(99),
+        marketIndex,
+    });
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if market order specifies both base and quote asset amount', async () => {
+        await requireCustomError(
+            testCli.placeOrder(getOrderParams({
+                orderType: OrderType.MARKET,
+                baseAssetAmount: AMM_RESERVE_PRECISION,
+                quoteAssetAmount: new BN(100).mul(QUOTE_PRECISION),
+                marketIndex,
+            })),
+            'InvalidOrder'
+        );
+    });
+
+    it('Fail if limit order has no price', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, price: ZERO }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Fail if order amount too small', async () => {
+        // 0.001个base资产，约为0.1个quote资产，低于最小订单金额0.5
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, baseAssetAmount: AMM_RESERVE_PRECISION.divn(1000) }),
+            'OrderAmountTooSmall'
+        );
+    });
+
+    it('Fail if max ts not later than now', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, maxTs: new BN(1) }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Fail if market not initialized', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...limitOrderParams, marketIndex: new BN(1) }),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Fail with invalid oracle', async () => {
+        await requireCustomError(
+            testCli.placeOrder(limitOrderParams, web3.Keypair.generate().publicKey),
+            'InvalidOracle'
+        );
+    });
+
+    it('Pass', async () => {
+        const signer = testCli.getCurrentSigner();
+        await testCli.placeOrder(limitOrderParams);
+
+        const order = (await testCli.getUserOrders(signer.publicKey)).orders[0];
+        requireBNEq(order.orderId, new BN(1));
+        expect(order.status).deep.eq(OrderStatus.OPEN);
+        expect(order.orderType).deep.eq(OrderType.LIMIT);
+        expect(order.direction).deep.eq(PositionDirection.LONG);
+        expect(order.userOrderId).eq(1);
+        requireBNEq(order.marketIndex, marketIndex);
+        requireBNEq(order.price, limitOrderParams.price);
+        requireBNEq(order.baseAssetAmount, limitOrderParams.baseAssetAmount);
+        requireBNEq(order.baseAssetAmountFilled, ZERO);
+
+        // 下单时占用仓位槽，但不成交也不收取手续费
+        const position = (await testCli.getUserPositions(signer.publicKey)).positions[0];
+        requireBNEq(position.marketIndex, marketIndex);
+        requireBNEq(position.openOrders, new BN(1));
+        requireBNEq(position.baseAssetAmount, ZERO);
+        requireBNEq((await testCli.getUser(signer.publicKey)).collateral, collateral);
+
+        const orderHistory = await testCli.getOrderHistory();
+        requireBNEq(orderHistory.lastOrderId, new BN(1));
+        requireBNEq(orderHistory.head, new BN(1));
+        const record = orderHistory.orderRecords[0];
+        requireBNEq(record.recordId, new BN(1));
+        expect(record.action).deep.eq(OrderAction.PLACE);
+        requirePublickeyEq(record.user, testCli.getUserAddress(signer.publicKey));
+        requirePublickeyEq(record.authority, signer.publicKey);
+        requireBNEq(record.order.orderId, order.orderId);
+    });
+
+    it('Fail if user order id duplicated', async () => {
+        await requireCustomError(
+            testCli.placeOrder(limitOrderParams),
+            'DuplicateUserOrderId'
+        );
+    });
+});
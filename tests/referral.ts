@@ -0,0 +1,108 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { PositionDirection } from "./utils/types";
+
+describe("clearing house: referral", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const quoteAssetAmount = new BN(200).mul(QUOTE_PRECISION);
+    // 手续费率为10/10000，被推荐人折扣和推荐人奖励均为手续费的5/100
+    const fee = quoteAssetAmount.muln(10).divn(10000);
+    const referralAmount = fee.muln(5).divn(100);
+
+    // signers[1]为推荐人，signers[2]为被推荐人
+    let referrer: web3.PublicKey;
+    let refereeAuthority: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(1);
+        referrer = await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(2);
+        refereeAuthority = testCli.getCurrentSigner().publicKey;
+    });
+
+    it('Fail if referrer is not a user account', async () => {
+        await requireCustomError(
+            testCli.initializeUser(web3.Keypair.generate().publicKey),
+            'InvalidReferrer'
+        );
+        await requireCustomError(
+            testCli.initializeUser(testCli.state),
+            'InvalidReferrer'
+        );
+    });
+
+    it('Fail if refer self', async () => {
+        await requireCustomError(
+            testCli.initializeUser(testCli.getUserAddress(refereeAuthority)),
+            'InvalidReferrer'
+        );
+    });
+
+    it('Pass register referrer', async () => {
+        await testCli.initializeUser(referrer);
+
+        const referee = await testCli.getUser(refereeAuthority);
+        requirePublickeyEq(referee.referrer, referrer);
+        // 未登记推荐人的用户
+        requirePublickeyEq((await testCli.getUser(testCli.getSignerByIndex(1).publicKey)).referrer, web3.PublicKey.default);
+
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if referrer account on trade is not the registered referrer', async () => {
+        await requireCustomError(
+            testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex, ZERO, testCli.getUserAddress(refereeAuthority)),
+            'InvalidReferrer'
+        );
+    });
+
+    it('Pass credit referrer on trade', async () => {
+        await testCli.openPosition(PositionDirection.LONG, quoteAssetAmount, marketIndex, ZERO, referrer);
+
+        // 被推荐人享受手续费折扣
+        const referee = await testCli.getUser(refereeAuthority);
+        requireBNEq(referee.totalRefereeDiscount, referralAmount);
+        requireBNEq(referee.totalFeePaid, fee.sub(referralAmount));
+        requireBNEq(referee.collateral, collateral.sub(fee).add(referralAmount));
+
+        // 推荐人奖励计入推荐人的抵押品
+        const referrerUser = await testCli.getUser(testCli.getSignerByIndex(1).publicKey);
+        requireBNEq(referrerUser.totalReferralReward, referralAmount);
+        requireBNEq(referrerUser.collateral, referralAmount);
+
+        // 计入市场的手续费不包含推荐人奖励
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        requireBNEq(market.amm.totalFee, fee.sub(referralAmount).sub(referralAmount));
+
+        const record = (await testCli.getTradeHistory()).tradeRecord[0];
+        requireBNEq(record.fee, fee.sub(referralAmount));
+        requireBNEq(record.refereeDiscount, referralAmount);
+    });
+});
@@ -0,0 +1,99 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { requireBNEq, requireCustomError, requirePublickeyEq, sleep } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, PEG_PRECISION, QUOTE_PRECISION } from "./constants/numericConstants";
+import { PositionDirection } from "./utils/types";
+
+describe("clearing house: settle_funding_payment", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    // 资金费率周期为1秒
+    const ammPeriodicity = new BN(1);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    // AMM_RESERVE_PRECISION / QUOTE_PRECISION
+    const ammToQuotePrecisionRatio = new BN(10 ** 7);
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+        await testCli.openPosition(PositionDirection.LONG, new BN(200).mul(QUOTE_PRECISION), marketIndex);
+    });
+
+    it('Fail if user positions not belong to user', async () => {
+        const userAuthority = testCli.getSignerByIndex(1).publicKey;
+        const otherUser = await testCli.getUser(testCli.getSignerByIndex(2).publicKey);
+        await requireCustomError(
+            testCli.clearingHouse.methods.settleFundingPayment()
+                .accounts({
+                    state: testCli.state,
+                    user: testCli.getUserAddress(userAuthority),
+                    markets: testCli.markets,
+                    userPositions: otherUser.positons,
+                    fundingPaymentHistory: testCli.fundingPaymentHistory,
+                } as any)
+                .rpc(),
+            'ConstraintRaw'
+        );
+    });
+
+    it('Pass', async () => {
+        const userAuthority = testCli.getSignerByIndex(1).publicKey;
+        // 预言机价格低于标记价格，多头支付资金费
+        await testCli.pythSetPrice(new BN(99 * web3.LAMPORTS_PER_SOL));
+        await sleep(2000);
+        await testCli.updateFundingRate(marketIndex);
+
+        const collateralBefore = (await testCli.getUser(userAuthority)).collateral;
+        const positionBefore = (await testCli.getUserPositions(userAuthority)).positions[0];
+        await testCli.settleFundingPayment(userAuthority);
+
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.lastCumulativeFundingRate, market.amm.cumulativeFundingRateLong);
+        requireBNEq(position.lastFundingRateTs, market.amm.lastFundingRateTs);
+
+        const fundingPaymentHistory = await testCli.getFundingPaymentHistory();
+        requireBNEq(fundingPaymentHistory.head, new BN(1));
+        const record = fundingPaymentHistory.fundingPaymentRecords[0];
+        requireBNEq(record.recordId, new BN(1));
+        requireBNEq(record.marketIndex, marketIndex);
+        requirePublickeyEq(record.userAuthority, userAuthority);
+        requireBNEq(record.baseAssetAmount, positionBefore.baseAssetAmount);
+        requireBNEq(record.userLastCumulativeFunding, positionBefore.lastCumulativeFundingRate);
+        requireBNEq(record.ammCumulativeFundingLong, market.amm.cumulativeFundingRateLong);
+        expect(record.fundingPayment.isZero()).eq(false);
+
+        // 资金费从AMM_RESERVE_PRECISION转换为QUOTE_PRECISION后计入抵押品
+        const user = await testCli.getUser(userAuthority);
+        requireBNEq(user.collateral, collateralBefore.add(record.fundingPayment.div(ammToQuotePrecisionRatio)));
+
+        // 已结算后再次结算不会增添记录
+        await testCli.settleFundingPayment(userAuthority);
+        requireBNEq((await testCli.getFundingPaymentHistory()).head, new BN(1));
+    });
+});
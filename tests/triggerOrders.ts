@@ -0,0 +1,150 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { getOrderParams, requireBNEq, requireCustomError } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, MARK_PRICE_PRECISION, PEG_PRECISION, QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+import { OrderAction, OrderStatus, OrderTriggerCondition, OrderType, PositionDirection } from "./utils/types";
+
+describe("clearing house: trigger orders", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const marketIndex = new BN(0);
+    // 1000个base资产，标记价格为100
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPeriodicity = new BN(60 * 60);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    const collateral = new BN(100).mul(QUOTE_PRECISION);
+    const quoteAssetAmount = new BN(50).mul(QUOTE_PRECISION);
+    // 预言机价格低于95时触发，按quote资产数量做空的触发市价单
+    const triggerMarketParams = getOrderParams({
+        orderType: OrderType.TRIGGER_MARKET,
+        direction: PositionDirection.SHORT,
+        quoteAssetAmount,
+        triggerPrice: MARK_PRICE_PRECISION.muln(95),
+        triggerCondition: OrderTriggerCondition.BELOW,
+        marketIndex,
+    });
+    // 预言机价格低于95时触发，以90的价格做多的触发限价单，触发后标记价格仍高于限价
+    const triggerLimitParams = getOrderParams({
+        orderType: OrderType.TRIGGER_LIMIT,
+        direction: PositionDirection.LONG,
+        baseAssetAmount: AMM_RESERVE_PRECISION,
+        price: MARK_PRICE_PRECISION.muln(90),
+        triggerPrice: MARK_PRICE_PRECISION.muln(95),
+        triggerCondition: OrderTriggerCondition.BELOW,
+        marketIndex,
+    });
+
+    // signers[1]为下单的用户，signers[2]为填充者
+    let userAuthority: web3.PublicKey;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 3);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeOrderState();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, ammPeriodicity, ammPegMultiplier);
+
+        testCli.changeCurrentSigner(2);
+        await testCli.initializeUser();
+
+        testCli.changeCurrentSigner(1);
+        userAuthority = testCli.getCurrentSigner().publicKey;
+        await testCli.initializeUser();
+        await testCli.initializeUserOrders();
+        const userCollateralAccount = await testCli.createCollateralAccount(collateral);
+        await testCli.depositCollateral(collateral, userCollateralAccount);
+    });
+
+    it('Fail if trigger order has no trigger price', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...triggerMarketParams, triggerPrice: ZERO }),
+            'InvalidOrder'
+        );
+        await requireCustomError(
+            testCli.placeOrder({ ...triggerLimitParams, triggerPrice: ZERO }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Fail if non-trigger order has trigger price', async () => {
+        await requireCustomError(
+            testCli.placeOrder({ ...triggerMarketParams, orderType: OrderType.MARKET }),
+            'InvalidOrder'
+        );
+    });
+
+    it('Pass place trigger orders', async () => {
+        await testCli.placeOrder(triggerMarketParams);
+        await testCli.placeOrder(triggerLimitParams);
+
+        // 触发单下单后处于未触发状态
+        const orders = (await testCli.getUserOrders(userAuthority)).orders;
+        expect(orders[0].status).deep.eq(OrderStatus.INIT);
+        expect(orders[0].orderType).deep.eq(OrderType.TRIGGER_MARKET);
+        requireBNEq(orders[0].triggerPrice, triggerMarketParams.triggerPrice);
+        expect(orders[1].status).deep.eq(OrderStatus.INIT);
+        requireBNEq((await testCli.getUserPositions(userAuthority)).positions[0].openOrders, new BN(2));
+    });
+
+    it('Fail if trigger condition not met', async () => {
+        testCli.changeCurrentSigner(2);
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(1)),
+            'OrderNotTriggered'
+        );
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(2)),
+            'OrderNotTriggered'
+        );
+    });
+
+    it('Pass fill triggered market order', async () => {
+        // 预言机价格下跌到94，满足触发条件
+        await testCli.pythSetPrice(new BN(94 * web3.LAMPORTS_PER_SOL));
+        await testCli.fillOrder(userAuthority, new BN(1));
+
+        requireBNEq((await testCli.getUserOrders(userAuthority)).orders[0].orderId, ZERO);
+        const position = (await testCli.getUserPositions(userAuthority)).positions[0];
+        requireBNEq(position.quoteAssetAmount, quoteAssetAmount);
+        expect(position.baseAssetAmount.isNeg()).eq(true);
+        requireBNEq(position.openOrders, new BN(1));
+
+        // 成交记录中的订单已转为市价单
+        const record = (await testCli.getOrderHistory()).orderRecords[2];
+        expect(record.action).deep.eq(OrderAction.FILL);
+        requireBNEq(record.order.orderId, new BN(1));
+        expect(record.order.status).deep.eq(OrderStatus.OPEN);
+        expect(record.order.orderType).deep.eq(OrderType.MARKET);
+        requireBNEq(record.quoteAssetAmountFilled, quoteAssetAmount);
+    });
+
+    it('Pass trigger limit order without fill', async () => {
+        // 触发后限价未达到，保存触发状态而不报错
+        await testCli.fillOrder(userAuthority, new BN(2));
+
+        const order = (await testCli.getUserOrders(userAuthority)).orders[1];
+        requireBNEq(order.orderId, new BN(2));
+        expect(order.status).deep.eq(OrderStatus.OPEN);
+        expect(order.orderType).deep.eq(OrderType.LIMIT);
+        requireBNEq(order.baseAssetAmountFilled, ZERO);
+        requireBNEq((await testCli.getOrderHistory()).head, new BN(3));
+
+        // 已触发的限价单之后按普通限价单处理
+        await requireCustomError(
+            testCli.fillOrder(userAuthority, new BN(2)),
+            'OrderCannotBeFilled'
+        );
+    });
+});
@@ -0,0 +1,86 @@
+import * as anchor from "@coral-xyz/anchor";
+import { web3, BN } from "@coral-xyz/anchor";
+import { requireBNEq, requireCustomError, sleep } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { AMM_RESERVE_PRECISION, PEG_PRECISION, ZERO } from "./constants/numericConstants";
+
+describe("clearing house: update_funding_rate", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    // 市场0的资金费率周期为1秒，市场1的资金费率周期为1小时
+    const marketIndex = new BN(0);
+    const hourlyMarketIndex = new BN(1);
+    const ammReserve = new BN(1000).mul(AMM_RESERVE_PRECISION);
+    const ammPegMultiplier = new BN(100).mul(PEG_PRECISION);
+    // oracle
+    let price = new BN(100 * web3.LAMPORTS_PER_SOL);
+    let conf = new BN(web3.LAMPORTS_PER_SOL / 10);
+    let exponent = -9;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 1);
+        await testCli.pythInitializePrice(price, conf, exponent, price, conf);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+        await testCli.initializeMarket(marketIndex, ammReserve, ammReserve, new BN(1), ammPegMultiplier);
+        await testCli.initializeMarket(hourlyMarketIndex, ammReserve, ammReserve, new BN(60 * 60), ammPegMultiplier);
+    });
+
+    it('Fail if market not initialized', async () => {
+        await requireCustomError(
+            testCli.updateFundingRate(new BN(2)),
+            'MarketIndexNotInitialized'
+        );
+    });
+
+    it('Fail with invalid oracle', async () => {
+        await requireCustomError(
+            testCli.updateFundingRate(marketIndex, web3.Keypair.generate().publicKey),
+            'InvalidOracle'
+        );
+    });
+
+    it('Fail if funding period not elapsed', async () => {
+        await requireCustomError(
+            testCli.updateFundingRate(hourlyMarketIndex),
+            'FundingPeriodNotElapsed'
+        );
+    });
+
+    it('Pass', async () => {
+        const lastFundingRateTs = (await testCli.getMarkets()).markets[marketIndex.toNumber()].amm.lastFundingRateTs;
+        // 预言机价格低于标记价格
+        await testCli.pythSetPrice(new BN(99 * web3.LAMPORTS_PER_SOL));
+        await sleep(2000);
+
+        await testCli.updateFundingRate(marketIndex);
+
+        const market = (await testCli.getMarkets()).markets[marketIndex.toNumber()];
+        expect(market.amm.lastFundingRateTs.gt(lastFundingRateTs)).eq(true);
+        requireBNEq(market.amm.lastMarkPriceTwapTs, market.amm.lastFundingRateTs);
+        requireBNEq(market.amm.lastOraclePriceTwapTs, market.amm.lastFundingRateTs);
+        requireBNEq(market.amm.cumulativeFundingRateLong, market.amm.lastFundingRate);
+        requireBNEq(market.amm.cumulativeFundingRateShort, market.amm.lastFundingRate);
+
+        const fundingRateHistory = await testCli.getFundingRateHistory();
+        requireBNEq(fundingRateHistory.head, new BN(1));
+        const record = fundingRateHistory.fundingRateRecord[0];
+        requireBNEq(record.recordId, new BN(1));
+        requireBNEq(record.marketIndex, marketIndex);
+        requireBNEq(record.ts, market.amm.lastFundingRateTs);
+        requireBNEq(record.fundingRate, market.amm.lastFundingRate);
+        requireBNEq(record.cumulativeFundingRateLong, market.amm.cumulativeFundingRateLong);
+        requireBNEq(record.cumulativeFundingRateShort, market.amm.cumulativeFundingRateShort);
+        requireBNEq(record.markPriceTwap, market.amm.lastMarkPriceTwap);
+        requireBNEq(record.oraclePriceTwap, market.amm.lastOraclePriceTwap);
+
+        // 资金费率周期为1小时的市场不受影响
+        const hourlyMarket = (await testCli.getMarkets()).markets[hourlyMarketIndex.toNumber()];
+        requireBNEq(hourlyMarket.amm.cumulativeFundingRateLong, ZERO);
+    });
+});
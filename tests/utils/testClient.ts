@@ -1,11 +1,12 @@
 import * as anchor from "@coral-xyz/anchor";
 import { AnchorProvider, web3, Program, IdlTypes, BN } from "@coral-xyz/anchor";
-import { createMint } from '@solana/spl-token';
+import { createMint, getOrCreateAssociatedTokenAccount, mintTo } from '@solana/spl-token';
 import { createAccounts, getSeedFromNumber } from './utils';
 import { ClearingHouse } from "../../target/types/clearing_house";
 import { MockPyth } from "../../target/types/mock_pyth";
+import { MockSwitchboard } from "../../target/types/mock_switchboard";
 import { OracleSource } from "./types";
-import { PEG_PRECISION } from "../constants/numericConstants";
+import { PEG_PRECISION, ZERO } from "../constants/numericConstants";
 type PublicKey = web3.PublicKey;
 
 export class TestClient {
@@ -14,15 +15,18 @@ export class TestClient {
     currentSignerIndex: number;
     clearingHouse: Program<ClearingHouse>;
     mockPyth: Program<MockPyth>;
+    mockSwitchboard: Program<MockSwitchboard>;
 
     state: PublicKey;
     collateralMint: PublicKey;
+    collateralMintAuthority: web3.Keypair;
     collateralVault: PublicKey;
     collateralVaultAuthority: PublicKey;
     insuranceVault: PublicKey;
     insuranceVaultAuthority: PublicKey;
     markets: PublicKey;
     pythPriceFeed: PublicKey;
+    switchboardAggregator: PublicKey;
 
     fundingPaymentHistory: PublicKey;
     tradeHistory: PublicKey;
@@ -31,11 +35,12 @@ export class TestClient {
     fundingRateHistory: PublicKey;
     curveHistory: PublicKey;
     orderHistory: PublicKey;
+    bankruptcyHistory: PublicKey;
 
     orderState: PublicKey;
 
 
-    static async create(provider: AnchorProvider, signersNum: number, hasClearingHouse = true, hasMockPyth = true): Promise<TestClient> {
+    static async create(provider: AnchorProvider, signersNum: number, hasClearingHouse = true, hasMockPyth = true, hasMockSwitchboard = false): Promise<TestClient> {
         const tc = new TestClient();
         tc.provider = provider;
         if (hasClearingHouse) {
@@ -44,6 +49,9 @@ export class TestClient {
         if (hasMockPyth) {
             tc.mockPyth = anchor.workspace.MockPyth as Program<MockPyth>;
         }
+        if (hasMockSwitchboard) {
+            tc.mockSwitchboard = anchor.workspace.MockSwitchboard as Program<MockSwitchboard>;
+        }
         tc.signers = new Array<web3.Keypair>(signersNum);
         tc.currentSignerIndex = 0;
         for (let index = 0; index < signersNum; index++) {
@@ -67,7 +75,13 @@ export class TestClient {
 
         [this.orderHistory] = await createAccounts(
             this.provider,
-            [8 + 458784],
+            [8 + 475168],
+            this.clearingHouse.programId
+        );
+
+        [this.bankruptcyHistory] = await createAccounts(
+            this.provider,
+            [8 + 245776],
             this.clearingHouse.programId
         );
 
@@ -78,12 +92,14 @@ liquidationHistory: ${this.liquidationHistory}
 fundingPaymentHistory: ${this.fundingPaymentHistory}
 fundingRateHistory: ${this.fundingRateHistory}
 curveHistory: ${this.curveHistory}
-orderHistory: ${this.orderHistory}`);
+orderHistory: ${this.orderHistory}
+bankruptcyHistory: ${this.bankruptcyHistory}`);
         }
     }
 
     async initializeRelevantAccounts(mintDecimal: number, logAddrs = false) {
         this.collateralMint = await this.createMint(mintDecimal);
+        this.collateralMintAuthority = this.getCurrentSigner();
         [this.collateralVault,] = web3.PublicKey.findProgramAddressSync([Buffer.from('collateral_vault')], this.clearingHouse.programId);
         [this.collateralVaultAuthority,] = web3.PublicKey.findProgramAddressSync([this.collateralVault.toBuffer()], this.clearingHouse.programId);
         [this.insuranceVault,] = web3.PublicKey.findProgramAddressSync([Buffer.from('insurance_vault')], this.clearingHouse.programId);
@@ -93,7 +109,7 @@ orderHistory: ${this.orderHistory}`);
         // create state && markets accounts
         [this.state, this.markets] = await createAccounts(
             this.provider,
-            [8 + 1200, 8 + 31744],
+            [8 + 1200, 8 + 35840],
             this.clearingHouse.programId
         );
 
@@ -133,6 +149,7 @@ order state: ${this.orderState}`);
                 depositHistory: this.depositHistory,
                 fundingRateHistory: this.fundingRateHistory,
                 curveHistory: this.curveHistory,
+                bankruptcyHistory: this.bankruptcyHistory,
             } as any)
             .signers([signer])
             .rpc();
@@ -185,6 +202,10 @@ order state: ${this.orderState}`);
         return await this.clearingHouse.account.curveHistory.fetch(this.curveHistory);
     }
 
+    async getBankruptcyHistory(): Promise<IdlTypes<ClearingHouse>['bankruptcyHistory']> {
+        return await this.clearingHouse.account.bankruptcyHistory.fetch(this.bankruptcyHistory);
+    }
+
     async getOrderHistory(): Promise<IdlTypes<ClearingHouse>['orderHistory']> {
         return await this.clearingHouse.account.orderHistory.fetch(this.orderHistory);
     }
@@ -227,6 +248,40 @@ order state: ${this.orderState}`);
         return (await this.mockPyth.account.priceUpdate.fetch(this.pythPriceFeed))[0];
     }
 
+    async switchboardInitializeAggregator(price: BN, scale: number, stdDeviation: BN, minOracleResults: number, numSuccess: number) {
+        const acckey = web3.Keypair.generate();
+        this.switchboardAggregator = acckey.publicKey;
+
+        const signer = this.getCurrentSigner();
+        await this.mockSwitchboard.methods.initializeAggregator(price, scale, stdDeviation, minOracleResults, numSuccess)
+            .accounts({
+                authority: signer.publicKey,
+                aggregator: this.switchboardAggregator
+            })
+            .signers([signer, acckey])
+            .rpc();
+    }
+
+    async switchboardSetPrice(price: BN) {
+        await this.mockSwitchboard.methods.setPrice(price)
+            .accounts({
+                aggregator: this.switchboardAggregator
+            })
+            .rpc();
+    }
+
+    async switchboardSetNumSuccess(numSuccess: number) {
+        await this.mockSwitchboard.methods.setNumSuccess(numSuccess)
+            .accounts({
+                aggregator: this.switchboardAggregator
+            })
+            .rpc();
+    }
+
+    async getSwitchboardAggregator(): Promise<IdlTypes<MockSwitchboard>['aggregatorAccountData']> {
+        return await this.mockSwitchboard.account.aggregatorAccountData.fetch(this.switchboardAggregator);
+    }
+
     async initializeMarket(
         marketIndex: BN,
         ammBaseAssetReserve: BN,
@@ -264,6 +319,325 @@ order state: ${this.orderState}`);
             .rpc();
     }
 
+    getUserAddress(authority: PublicKey): PublicKey {
+        return web3.PublicKey.findProgramAddressSync([Buffer.from('user'), authority.toBuffer()], this.clearingHouse.programId)[0];
+    }
+
+    async getUser(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['user']> {
+        return await this.clearingHouse.account.user.fetch(this.getUserAddress(authority));
+    }
+
+    async getUserPositions(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['userPositions']> {
+        const user = await this.getUser(authority);
+        return await this.clearingHouse.account.userPositions.fetch(user.positons);
+    }
+
+    // 以当前signer初始化User和UserPositions，referrer为推荐人的User账户地址
+    async initializeUser(referrer: PublicKey = null): Promise<PublicKey> {
+        const signer = this.getCurrentSigner();
+        const userPositions = web3.Keypair.generate();
+        const remainingAccounts = referrer == null ? [] : [{ pubkey: referrer, isWritable: false, isSigner: false }];
+        await this.clearingHouse.methods.initializeUser({ whitelistToken: false, referrer: referrer != null })
+            .accounts({
+                signer: signer.publicKey,
+                state: this.state,
+                userPostions: userPositions.publicKey,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer, userPositions])
+            .rpc();
+
+        return this.getUserAddress(signer.publicKey);
+    }
+
+    // 为当前signer创建collateral mint的token account，并铸造amount数量的抵押品
+    async createCollateralAccount(amount: BN): Promise<PublicKey> {
+        const signer = this.getCurrentSigner();
+        const tokenAccount = await getOrCreateAssociatedTokenAccount(
+            this.provider.connection,
+            signer,
+            this.collateralMint,
+            signer.publicKey
+        );
+        if (amount.gtn(0)) {
+            await mintTo(
+                this.provider.connection,
+                signer,
+                this.collateralMint,
+                tokenAccount.address,
+                this.collateralMintAuthority,
+                BigInt(amount.toString())
+            );
+        }
+
+        return tokenAccount.address;
+    }
+
+    async depositCollateral(amount: BN, userCollateralAccount: PublicKey) {
+        const signer = this.getCurrentSigner();
+        const user = await this.getUser(signer.publicKey);
+        await this.clearingHouse.methods.depositCollateral(amount)
+            .accounts({
+                authority: signer.publicKey,
+                state: this.state,
+                user: this.getUserAddress(signer.publicKey),
+                collateralVault: this.collateralVault,
+                userCollateralAccount,
+                markets: this.markets,
+                userPositions: user.positons,
+                fundingPaymentHistory: this.fundingPaymentHistory,
+                depositHistory: this.depositHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async withdrawCollateral(amount: BN, userCollateralAccount: PublicKey) {
+        const signer = this.getCurrentSigner();
+        const user = await this.getUser(signer.publicKey);
+        await this.clearingHouse.methods.withdrawCollateral(amount)
+            .accounts({
+                authority: signer.publicKey,
+                state: this.state,
+                user: this.getUserAddress(signer.publicKey),
+                collateralVault: this.collateralVault,
+                collateralVaultAuthority: this.collateralVaultAuthority,
+                insuranceVault: this.insuranceVault,
+                insuranceVaultAuthority: this.insuranceVaultAuthority,
+                userCollateralAccount,
+                markets: this.markets,
+                userPositions: user.positons,
+                fundingPaymentHistory: this.fundingPaymentHistory,
+                depositHistory: this.depositHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    // 以当前signer开仓，referrer为用户登记的推荐人的User账户地址（通过remaining_accounts传入）
+    async openPosition(direction: object, quoteAssetAmount: BN, marketIndex: BN, limitPrice = ZERO, referrer: PublicKey = null, oracle = this.pythPriceFeed) {
+        const signer = this.getCurrentSigner();
+        const user = await this.getUser(signer.publicKey);
+        const remainingAccounts = referrer == null ? [] : [{ pubkey: referrer, isWritable: true, isSigner: false }];
+        await this.clearingHouse.methods.openPosition(
+            direction as any,
+            quoteAssetAmount,
+            marketIndex,
+            limitPrice,
+            { discountToken: false, referrer: referrer != null }
+        ).accounts({
+            authority: signer.publicKey,
+            state: this.state,
+            user: this.getUserAddress(signer.publicKey),
+            markets: this.markets,
+            userPositions: user.positons,
+            tradeHistory: this.tradeHistory,
+            fundingPaymentHistory: this.fundingPaymentHistory,
+            oracle,
+        } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async closePosition(marketIndex: BN, referrer: PublicKey = null, oracle = this.pythPriceFeed) {
+        const signer = this.getCurrentSigner();
+        const user = await this.getUser(signer.publicKey);
+        const remainingAccounts = referrer == null ? [] : [{ pubkey: referrer, isWritable: true, isSigner: false }];
+        await this.clearingHouse.methods.closePosition(
+            marketIndex,
+            { discountToken: false, referrer: referrer != null }
+        ).accounts({
+            authority: signer.publicKey,
+            state: this.state,
+            user: this.getUserAddress(signer.publicKey),
+            markets: this.markets,
+            userPositions: user.positons,
+            tradeHistory: this.tradeHistory,
+            fundingPaymentHistory: this.fundingPaymentHistory,
+            oracle,
+        } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    async updateFundingRate(marketIndex: BN, oracle = this.pythPriceFeed) {
+        await this.clearingHouse.methods.updateFundingRate(marketIndex)
+            .accounts({
+                state: this.state,
+                markets: this.markets,
+                fundingRateHistory: this.fundingRateHistory,
+                oracle,
+            } as any)
+            .rpc();
+    }
+
+    // 结算authority对应的User所有持仓的资金费
+    async settleFundingPayment(authority: PublicKey) {
+        const user = await this.getUser(authority);
+        await this.clearingHouse.methods.settleFundingPayment()
+            .accounts({
+                state: this.state,
+                user: this.getUserAddress(authority),
+                markets: this.markets,
+                userPositions: user.positons,
+                fundingPaymentHistory: this.fundingPaymentHistory,
+            } as any)
+            .rpc();
+    }
+
+    // 以当前signer作为清算人清算authority对应的User，oracles为该User各持仓市场的预言机（通过remaining_accounts传入）
+    async liquidate(authority: PublicKey, liquidatorAccount: PublicKey, oracles = [this.pythPriceFeed]) {
+        const signer = this.getCurrentSigner();
+        const user = await this.getUser(authority);
+        await this.clearingHouse.methods.liquidate()
+            .accounts({
+                liquidator: signer.publicKey,
+                state: this.state,
+                user: this.getUserAddress(authority),
+                collateralVault: this.collateralVault,
+                collateralVaultAuthority: this.collateralVaultAuthority,
+                insuranceVault: this.insuranceVault,
+                insuranceVaultAuthority: this.insuranceVaultAuthority,
+                liquidatorAccount,
+                markets: this.markets,
+                userPositions: user.positons,
+                tradeHistory: this.tradeHistory,
+                liquidationHistory: this.liquidationHistory,
+                bankruptcyHistory: this.bankruptcyHistory,
+                fundingPaymentHistory: this.fundingPaymentHistory,
+            } as any)
+            .remainingAccounts(oracles.map(oracle => ({ pubkey: oracle, isWritable: false, isSigner: false })))
+            .signers([signer])
+            .rpc();
+    }
+
+    getUserOrdersAddress(user: PublicKey): PublicKey {
+        return web3.PublicKey.findProgramAddressSync([Buffer.from('user_orders'), user.toBuffer()], this.clearingHouse.programId)[0];
+    }
+
+    async getUserOrders(authority: PublicKey): Promise<IdlTypes<ClearingHouse>['userOrders']> {
+        return await this.clearingHouse.account.userOrders.fetch(this.getUserOrdersAddress(this.getUserAddress(authority)));
+    }
+
+    async initializeUserOrders() {
+        const signer = this.getCurrentSigner();
+        await this.clearingHouse.methods.initializeUserOrders()
+            .accounts({
+                authority: signer.publicKey,
+                user: this.getUserAddress(signer.publicKey),
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async placeOrder(params: object, oracle = this.pythPriceFeed) {
+        const signer = this.getCurrentSigner();
+        const userAddress = this.getUserAddress(signer.publicKey);
+        const user = await this.getUser(signer.publicKey);
+        await this.clearingHouse.methods.placeOrder(params as any)
+            .accounts({
+                authority: signer.publicKey,
+                state: this.state,
+                user: userAddress,
+                markets: this.markets,
+                userPositions: user.positons,
+                userOrders: this.getUserOrdersAddress(userAddress),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+                oracle,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelOrder(orderId: BN) {
+        const signer = this.getCurrentSigner();
+        await this.clearingHouse.methods.cancelOrder(orderId)
+            .accounts(await this.getCancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    async cancelOrderByUserId(userOrderId: number) {
+        const signer = this.getCurrentSigner();
+        await this.clearingHouse.methods.cancelOrderByUserId(userOrderId)
+            .accounts(await this.getCancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    // marketIndex和direction为null时不筛选
+    async cancelAllOrders(marketIndex: BN = null, direction: object = null) {
+        const signer = this.getCurrentSigner();
+        await this.clearingHouse.methods.cancelAllOrders(marketIndex, direction as any)
+            .accounts(await this.getCancelOrderAccounts(signer.publicKey))
+            .signers([signer])
+            .rpc();
+    }
+
+    async getCancelOrderAccounts(authority: PublicKey): Promise<any> {
+        const userAddress = this.getUserAddress(authority);
+        const user = await this.getUser(authority);
+        return {
+            authority,
+            state: this.state,
+            user: userAddress,
+            userPositions: user.positons,
+            userOrders: this.getUserOrdersAddress(userAddress),
+            orderState: this.orderState,
+            orderHistory: this.orderHistory,
+        };
+    }
+
+    // 以当前signer作为填充者成交authority对应的User的订单，referrer为该User登记的推荐人的User账户地址
+    async fillOrder(authority: PublicKey, orderId: BN, referrer: PublicKey = null, oracle = this.pythPriceFeed) {
+        const signer = this.getCurrentSigner();
+        const userAddress = this.getUserAddress(authority);
+        const user = await this.getUser(authority);
+        const remainingAccounts = referrer == null ? [] : [{ pubkey: referrer, isWritable: true, isSigner: false }];
+        await this.clearingHouse.methods.fillOrder(orderId)
+            .accounts({
+                authority: signer.publicKey,
+                state: this.state,
+                filler: this.getUserAddress(signer.publicKey),
+                user: userAddress,
+                markets: this.markets,
+                userPositions: user.positons,
+                userOrders: this.getUserOrdersAddress(userAddress),
+                orderState: this.orderState,
+                tradeHistory: this.tradeHistory,
+                orderHistory: this.orderHistory,
+                fundingPaymentHistory: this.fundingPaymentHistory,
+                oracle,
+            } as any)
+            .remainingAccounts(remainingAccounts)
+            .signers([signer])
+            .rpc();
+    }
+
+    // 以当前signer作为keeper移除authority对应的User的过期订单
+    async expireOrders(authority: PublicKey) {
+        const signer = this.getCurrentSigner();
+        const userAddress = this.getUserAddress(authority);
+        const user = await this.getUser(authority);
+        await this.clearingHouse.methods.expireOrders()
+            .accounts({
+                authority: signer.publicKey,
+                state: this.state,
+                keeper: this.getUserAddress(signer.publicKey),
+                user: userAddress,
+                userPositions: user.positons,
+                markets: this.markets,
+                userOrders: this.getUserOrdersAddress(userAddress),
+                orderState: this.orderState,
+                orderHistory: this.orderHistory,
+            } as any)
+            .signers([signer])
+            .rpc();
+    }
+
     changeCurrentSigner(index: number) {
         this.currentSignerIndex = index;
     }
@@ -2,4 +2,34 @@
 export class OracleSource {
     static readonly PYTH = { pyth: {} }
     static readonly SWITCHBOARD = { switchboard: {} }
+    static readonly ADMIN = { admin: {} }
+}
+
+export class PositionDirection {
+    static readonly LONG = { long: {} }
+    static readonly SHORT = { short: {} }
+}
+
+export class OrderType {
+    static readonly MARKET = { market: {} }
+    static readonly LIMIT = { limit: {} }
+    static readonly TRIGGER_MARKET = { triggerMarket: {} }
+    static readonly TRIGGER_LIMIT = { triggerLimit: {} }
+}
+
+export class OrderTriggerCondition {
+    static readonly ABOVE = { above: {} }
+    static readonly BELOW = { below: {} }
+}
+
+export class OrderStatus {
+    static readonly INIT = { init: {} }
+    static readonly OPEN = { open: {} }
+}
+
+export class OrderAction {
+    static readonly PLACE = { place: {} }
+    static readonly CANCEL = { cancel: {} }
+    static readonly FILL = { fill: {} }
+    static readonly EXPIRE = { expire: {} }
 }
\ No newline at end of file
@@ -1,6 +1,7 @@
 import { AnchorError, AnchorProvider, BN, Wallet, web3 } from "@coral-xyz/anchor";
 import { expect } from "chai";
-import { TEN } from "../constants/numericConstants";
+import { TEN, ZERO } from "../constants/numericConstants";
+import { OrderTriggerCondition, OrderType, PositionDirection } from "./types";
 type PublicKey = web3.PublicKey;
 
 function requireBNEq(a: BN, b: BN) {
@@ -88,4 +89,29 @@ function takeTenToPower(exponent: number): BN {
     return TEN.pow(new BN(exponent));
 }
 
-export { requireBNEq, requirePublickeyEq, createAccounts, requireNativeError, requireCustomError, getSeedFromNumber, takeTenToPower };
\ No newline at end of file
+// 下单参数，未指定的字段取默认值
+function getOrderParams(params: object): any {
+    return Object.assign({
+        orderType: OrderType.MARKET,
+        direction: PositionDirection.LONG,
+        userOrderId: 0,
+        quoteAssetAmount: ZERO,
+        baseAssetAmount: ZERO,
+        price: ZERO,
+        marketIndex: ZERO,
+        triggerPrice: ZERO,
+        triggerCondition: OrderTriggerCondition.ABOVE,
+        reduceOnly: false,
+        postOnly: false,
+        immediateOrCancel: false,
+        maxTs: ZERO,
+        oraclePriceOffset: ZERO,
+        optionalAccounts: { discountToken: false, referrer: false },
+    }, params);
+}
+
+function sleep(ms: number): Promise<void> {
+    return new Promise(resolve => setTimeout(resolve, ms));
+}
+
+export { requireBNEq, requirePublickeyEq, createAccounts, requireNativeError, requireCustomError, getSeedFromNumber, takeTenToPower, getOrderParams, sleep };
\ No newline at end of file
@@ -0,0 +1,67 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { getAccount } from '@solana/spl-token';
+import { requireBNEq, requireCustomError, requirePublickeyEq } from "./utils/utils";
+import { expect } from "chai";
+import { TestClient } from "./utils/testClient";
+import { QUOTE_PRECISION, ZERO } from "./constants/numericConstants";
+
+describe("clearing house: withdraw_collateral", () => {
+    const provider = anchor.AnchorProvider.env();
+    anchor.setProvider(provider);
+    let testCli: TestClient;
+
+    const depositAmount = new BN(100).mul(QUOTE_PRECISION);
+    const withdrawAmount = new BN(40).mul(QUOTE_PRECISION);
+    let userCollateralAccount;
+
+    before(async () => {
+        testCli = await TestClient.create(provider, 2, true, false);
+        await testCli.initializeRelevantAccounts(6, true);
+        await testCli.initializeHistoriesAccounts(true);
+        await testCli.initialize(true);
+        await testCli.initializeHistory();
+
+        testCli.changeCurrentSigner(1);
+        await testCli.initializeUser();
+        userCollateralAccount = await testCli.createCollateralAccount(depositAmount);
+        await testCli.depositCollateral(depositAmount, userCollateralAccount);
+    });
+
+    it('Fail if amount is 0', async () => {
+        await requireCustomError(
+            testCli.withdrawCollateral(ZERO, userCollateralAccount),
+            'InsufficientWithdrawal'
+        );
+    });
+
+    it('Fail if amount exceeds collateral', async () => {
+        await requireCustomError(
+            testCli.withdrawCollateral(depositAmount.addn(1), userCollateralAccount),
+            'InsufficientCollateral'
+        );
+    });
+
+    it('Pass', async () => {
+        const signer = testCli.getCurrentSigner();
+        await testCli.withdrawCollateral(withdrawAmount, userCollateralAccount);
+
+        const user = await testCli.getUser(signer.publicKey);
+        requireBNEq(user.collateral, depositAmount.sub(withdrawAmount));
+        requireBNEq(user.cumculativeDeposits, depositAmount.sub(withdrawAmount));
+
+        expect((await getAccount(provider.connection, userCollateralAccount)).amount).eq(BigInt(withdrawAmount.toString()));
+        expect((await getAccount(provider.connection, testCli.collateralVault)).amount).eq(BigInt(depositAmount.sub(withdrawAmount).toString()));
+
+        // 第一条为存款记录，第二条为提款记录
+        const depositHistory = await testCli.getDepositHistory();
+        requireBNEq(depositHistory.head, new BN(2));
+        const record = depositHistory.depositRecords[1];
+        requireBNEq(record.recordId, new BN(2));
+        requireBNEq(record.amount, withdrawAmount);
+        requirePublickeyEq(record.userAuthority, signer.publicKey);
+        requireBNEq(record.collateralBefore, depositAmount);
+        requireBNEq(record.cumulativeDepositsBefore, depositAmount);
+        expect(record.direction).deep.eq({ withdraw: {} });
+    });
+});